@@ -0,0 +1,82 @@
+//! Datapath benchmarks, they need administrator rights and the
+//! tap-windows driver. Set `TAP_BENCH_DEVICE` to use an existing
+//! adapter, otherwise a temporary one is created
+
+#[cfg(windows)]
+mod bench {
+    use criterion::{criterion_group, Criterion, Throughput};
+    use std::io::Write;
+    use tap_windows::Device;
+
+    fn open_device() -> Device {
+        let dev = match std::env::var("TAP_BENCH_DEVICE") {
+            Ok(name) => Device::open(&name).expect("Failed to open device"),
+            Err(_) => {
+                let mut dev =
+                    Device::create().expect("Failed to create device");
+                dev.set_delete_on_drop(true);
+                dev
+            }
+        };
+
+        dev.up().expect("Failed to set device up");
+        dev.reset_stats();
+        dev
+    }
+
+    /// Broadcast frame with an unused ethertype, so the
+    /// stack discards it as soon as possible
+    fn frame(len: usize) -> Vec<u8> {
+        let mut frame = vec![0; len];
+        frame[..6].copy_from_slice(&[0xff; 6]);
+        frame[6..12].copy_from_slice(&[0x02, 0, 0, 0, 0, 1]);
+        frame[12..14].copy_from_slice(&[0x88, 0xb5]);
+        frame
+    }
+
+    fn write(c: &mut Criterion) {
+        let mut dev = open_device();
+        let mut group = c.benchmark_group("write");
+
+        for &len in &[64, 1514] {
+            let frame = frame(len);
+
+            group.throughput(Throughput::Elements(1));
+            group.bench_function(format!("{}", len), |b| {
+                b.iter(|| dev.write(&frame).expect("Failed to write"))
+            });
+        }
+
+        group.finish();
+    }
+
+    fn write_queued(c: &mut Criterion) {
+        let mut dev = open_device();
+        dev.set_write_queue(32)
+            .expect("Failed to enable write queue");
+
+        let frame = frame(1514);
+
+        c.bench_function("write_queued/1514", |b| {
+            b.iter(|| dev.write(&frame).expect("Failed to write"))
+        });
+
+        dev.flush().expect("Failed to flush");
+    }
+
+    fn ioctl(c: &mut Criterion) {
+        let dev = open_device();
+
+        c.bench_function("ioctl/get_mtu", |b| {
+            b.iter(|| dev.get_mtu().expect("Failed to get mtu"))
+        });
+    }
+
+    criterion_group!(benches, write, write_queued, ioctl);
+}
+
+#[cfg(windows)]
+criterion::criterion_main!(bench::benches);
+
+#[cfg(not(windows))]
+fn main() {}
@@ -0,0 +1,57 @@
+/*
+ * C api of the tap-windows crate, available when built with
+ *   cargo rustc --release --features capi --crate-type cdylib
+ *
+ * Functions returning int return 0 on success or a win32 error code,
+ * tap_read and tap_write return the amount of bytes transferred or a
+ * negated win32 error code. A panic is reported as ERROR_GEN_FAILURE.
+ * Strings are nul terminated utf8.
+ */
+#ifndef TAP_WINDOWS_H
+#define TAP_WINDOWS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct TapDevice TapDevice;
+
+/* Create a new adapter, name may be NULL. Release with tap_close */
+int tap_create(const char *name, TapDevice **out);
+
+/* Open an existing adapter by name. Release with tap_close */
+int tap_open(const char *name, TapDevice **out);
+
+/* Close the device, the adapter is left in place */
+void tap_close(TapDevice *dev);
+
+/* Close the device and delete the adapter, dev is always released */
+int tap_delete(TapDevice *dev);
+
+int tap_up(TapDevice *dev);
+int tap_down(TapDevice *dev);
+
+/* Read a single packet, blocking until one is available */
+intptr_t tap_read(TapDevice *dev, uint8_t *buf, size_t len);
+
+/* Write a single packet */
+intptr_t tap_write(TapDevice *dev, const uint8_t *buf, size_t len);
+
+/* Set the ipv4 address and mask, both in dotted decimal notation */
+int tap_set_ip(TapDevice *dev, const char *address, const char *mask);
+
+int tap_set_name(TapDevice *dev, const char *name);
+
+int tap_get_mtu(TapDevice *dev, uint32_t *out);
+
+/* out must hold 6 bytes */
+int tap_get_mac(TapDevice *dev, uint8_t *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,26 @@
+use std::time;
+
+/// Options applied to an address added with `Device::add_address_with`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AddressOptions {
+    /// Exclude the address from source address selection,
+    /// it will still be reachable but never used for outgoing traffic
+    pub skip_as_source: bool,
+    /// How long the address stays valid, `None` for infinite
+    pub valid_lifetime: Option<time::Duration>,
+    /// How long the address stays preferred, `None` for infinite
+    pub preferred_lifetime: Option<time::Duration>,
+}
+
+/// Convert a lifetime to the seconds representation used by
+/// MIB_UNICASTIPADDRESS_ROW, where 0xffffffff means infinite
+pub(crate) fn lifetime_to_secs(lifetime: Option<time::Duration>) -> u32 {
+    const INFINITE: u32 = 0xffffffff;
+
+    match lifetime {
+        Some(lifetime) if lifetime.as_secs() < INFINITE as u64 => {
+            lifetime.as_secs() as u32
+        }
+        _ => INFINITE,
+    }
+}
@@ -0,0 +1,118 @@
+//! Command line tool to manage tap-windows adapters, mirroring
+//! OpenVPN's tapctl. Built with the `cli` feature
+
+#[cfg(windows)]
+use tap_windows::{CreateOptions, Device, DEFAULT_COMPONENT_ID};
+
+#[cfg(windows)]
+use std::{env, io, net, process};
+
+#[cfg(windows)]
+const USAGE: &str = "\
+Usage: tap-windows <command> [options]
+
+Commands:
+    create [--name <name>] [--hwid <hwid>]
+                                Create a new adapter, prints its guid
+    delete <name> [--hwid <hwid>]
+                                Delete an adapter
+    list [--hwid <hwid>]        List the adapters, one per line
+    rename <name> <newname>     Rename an adapter
+    set-ip <name> <address> <mask>
+                                Set the ipv4 address of an adapter
+
+The hardware id defaults to tap0901";
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("tap-windows only runs on Windows");
+    std::process::exit(1);
+}
+
+#[cfg(windows)]
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(err) = run(&args) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(windows)]
+fn run(args: &[String]) -> io::Result<()> {
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => return Err(usage()),
+    };
+
+    let (hwid, args) = take_option(args, "--hwid")?;
+    let hwid = hwid.as_deref().unwrap_or(DEFAULT_COMPONENT_ID);
+
+    match (command, args.as_slice()) {
+        ("create", args) => {
+            let (name, args) = take_option(args, "--name")?;
+            if !args.is_empty() {
+                return Err(usage());
+            }
+
+            let dev = match name {
+                Some(name) => Device::create_named(hwid, &name)?,
+                None => Device::create_with(CreateOptions {
+                    component_id: hwid,
+                    ..Default::default()
+                })?,
+            };
+
+            println!("{}", dev.get_info()?.guid);
+        }
+        ("delete", [name]) => Device::delete_by_name(hwid, name)?,
+        ("list", []) => {
+            for info in Device::enumerate(hwid)? {
+                println!("{}\t{}", info.guid, info.name);
+            }
+        }
+        ("rename", [name, newname]) => Device::open(name)?.set_name(newname)?,
+        ("set-ip", [name, address, mask]) => {
+            let address: net::Ipv4Addr = address.parse().map_err(invalid)?;
+            let mask: net::Ipv4Addr = mask.parse().map_err(invalid)?;
+
+            Device::open(name)?.set_ip(address, mask)?;
+        }
+        ("help", []) | ("--help", []) | ("-h", []) => println!("{}", USAGE),
+        _ => return Err(usage()),
+    }
+
+    Ok(())
+}
+
+/// Remove `--option <value>` from the arguments, returning its value
+#[cfg(windows)]
+fn take_option(
+    args: &[String],
+    option: &str,
+) -> io::Result<(Option<String>, Vec<String>)> {
+    let mut value = None;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == option {
+            value = Some(args.next().cloned().ok_or_else(usage)?);
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+
+    Ok((value, rest))
+}
+
+#[cfg(windows)]
+fn invalid(err: net::AddrParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
+
+#[cfg(windows)]
+fn usage() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("\n{}", USAGE))
+}
@@ -0,0 +1,116 @@
+use std::io;
+
+use crate::{elevation, wmi, Device};
+
+/// Network component bound to an adapter, see `Device::set_binding`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    /// Internet Protocol Version 4, `ms_tcpip`
+    Ipv4,
+    /// Internet Protocol Version 6, `ms_tcpip6`
+    Ipv6,
+    /// File and Printer Sharing for Microsoft Networks, `ms_server`
+    FileSharing,
+    /// Client for Microsoft Networks, `ms_msclient`
+    NetworkClient,
+    /// Microsoft LLDP Protocol Driver, `ms_lldp`
+    Lldp,
+    /// Link-Layer Topology Discovery Mapper I/O Driver, `ms_lltdio`
+    LltdMapper,
+    /// Link-Layer Topology Discovery Responder, `ms_rspndr`
+    LltdResponder,
+    /// QoS Packet Scheduler, `ms_pacer`
+    QosScheduler,
+    /// Microsoft MAC Bridge, `ms_bridge`, see `Device::join_bridge`
+    Bridge,
+    /// Any other component, by id
+    Other(String),
+}
+
+impl Binding {
+    /// Component id of the binding, as listed by
+    /// `Get-NetAdapterBinding`
+    pub fn component_id(&self) -> &str {
+        match self {
+            Binding::Ipv4 => "ms_tcpip",
+            Binding::Ipv6 => "ms_tcpip6",
+            Binding::FileSharing => "ms_server",
+            Binding::NetworkClient => "ms_msclient",
+            Binding::Lldp => "ms_lldp",
+            Binding::LltdMapper => "ms_lltdio",
+            Binding::LltdResponder => "ms_rspndr",
+            Binding::QosScheduler => "ms_pacer",
+            Binding::Bridge => "ms_bridge",
+            Binding::Other(component_id) => component_id,
+        }
+    }
+}
+
+impl Device {
+    /// Enable or disable a protocol or service on the adapter, so
+    /// the tunnel only carries the traffic it is meant to.
+    /// Requires administrator rights and Windows 8 or later
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Binding, Device};
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// for binding in &[Binding::Ipv6, Binding::FileSharing, Binding::Lldp] {
+    ///     dev.set_binding(binding, false)
+    ///         .expect("Failed to unbind component");
+    /// }
+    /// ```
+    pub fn set_binding(
+        &self,
+        binding: &Binding,
+        enabled: bool,
+    ) -> io::Result<()> {
+        elevation::require_elevated()?;
+
+        let component_id = binding.component_id();
+
+        // Pasted in a powershell script, component ids are
+        // made of letters, digits and underscores
+        let valid = !component_id.is_empty()
+            && component_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid binding component id",
+            ));
+        }
+
+        wmi::set_interface_binding(self.get_index()?, component_id, enabled)
+    }
+
+    /// Add the adapter to the network bridge, so its frames are
+    /// switched with the other bridged adapters, for example a
+    /// physical nic. The bridge must already exist, created from
+    /// the network connections window, Windows offers no supported
+    /// api to create one. Requires administrator rights and
+    /// Windows 8 or later
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.join_bridge().expect("Failed to join bridge");
+    /// ```
+    pub fn join_bridge(&self) -> io::Result<()> {
+        self.set_binding(&Binding::Bridge, true)
+    }
+
+    /// Remove the adapter from the network bridge, the bridge itself
+    /// is left in place. Requires administrator rights and Windows 8
+    /// or later
+    pub fn leave_bridge(&self) -> io::Result<()> {
+        self.set_binding(&Binding::Bridge, false)
+    }
+}
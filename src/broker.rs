@@ -0,0 +1,387 @@
+use scopeguard::guard;
+use winapi::shared::ifdef::NET_LUID;
+use winapi::shared::winerror::ERROR_PIPE_BUSY;
+use winapi::um::winnt::{
+    FILE_WRITE_DATA, GENERIC_READ, HANDLE, PROCESS_DUP_HANDLE,
+};
+
+use std::io::{BufRead, Write};
+use std::os::windows::fs::OpenOptionsExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle};
+use std::{fs, io, mem, net, thread, time};
+
+use crate::{
+    elevation, encode_utf16, ffi, iface, netsh, validate_name, Device,
+    DEFAULT_COMPONENT_ID,
+};
+
+/// Lets only SYSTEM and administrators connect, owned by the
+/// administrators so clients can tell the pipe apart from a fake
+const DEFAULT_SDDL: &str = "O:BAD:P(A;;GA;;;SY)(A;;GA;;;BA)";
+
+/// Generic read and write without FILE_CREATE_PIPE_INSTANCE, so
+/// clients can't create instances of their own
+const CLIENT_ACCESS: &str = "0x12019b";
+
+/// How long a client waits for the broker to accept it
+const CONNECT_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+fn pipe_path(pipe_name: &str) -> String {
+    format!(r"\\.\pipe\{}", pipe_name)
+}
+
+/// Elevated side of the broker, it creates, deletes and configures
+/// adapters on behalf of unprivileged processes connecting to its
+/// named pipe. Opened adapters are handed to the client by
+/// duplicating the handle into its process.
+/// Each client is served on its own thread
+/// Example
+/// ```no_run
+/// use tap_windows::BrokerServer;
+///
+/// // Usually run from an elevated service
+/// BrokerServer::new("my-vpn-broker")
+///     .serve()
+///     .expect("Broker failed");
+/// ```
+pub struct BrokerServer {
+    path: Vec<u16>,
+    sddl: Vec<u16>,
+}
+
+impl BrokerServer {
+    /// Create a broker listening on `\\.\pipe\<pipe_name>`, only
+    /// SYSTEM and administrators are allowed to connect
+    pub fn new(pipe_name: &str) -> Self {
+        Self::with_security(pipe_name, DEFAULT_SDDL)
+    }
+
+    /// Create a broker that additionally lets the account or group
+    /// with the given SID connect, such as `S-1-5-21-...-1001`
+    pub fn with_client_sid(pipe_name: &str, sid: &str) -> io::Result<Self> {
+        if sid.is_empty()
+            || !sid.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid SID",
+            ));
+        }
+
+        let sddl = format!("{}(A;;{};;;{})", DEFAULT_SDDL, CLIENT_ACCESS, sid);
+        Ok(Self::with_security(pipe_name, &sddl))
+    }
+
+    /// Create a broker whose pipe is protected by the given SDDL
+    /// security descriptor, to restrict which users can connect.
+    /// Clients only accept a pipe owned by SYSTEM or the administrators
+    pub fn with_security(pipe_name: &str, sddl: &str) -> Self {
+        Self {
+            path: encode_utf16(&pipe_path(pipe_name)),
+            sddl: encode_utf16(sddl),
+        }
+    }
+
+    /// Serve clients until an error prevents accepting new ones,
+    /// failures of a single client are only logged
+    pub fn serve(&self) -> io::Result<()> {
+        elevation::require_elevated()?;
+
+        // An instance is always kept listening, otherwise another
+        // process could take the name over between two clients
+        let mut pipe = self.create_pipe(true)?;
+
+        loop {
+            ffi::connect_named_pipe(pipe.as_raw_handle() as HANDLE)?;
+
+            let next = self.create_pipe(false)?;
+            let client = mem::replace(&mut pipe, next);
+
+            thread::spawn(move || serve_connected(client));
+        }
+    }
+
+    fn create_pipe(&self, first: bool) -> io::Result<fs::File> {
+        let pipe = ffi::create_named_pipe(&self.path, &self.sddl, first)?;
+        Ok(unsafe { fs::File::from_raw_handle(pipe as _) })
+    }
+}
+
+fn serve_connected(pipe: fs::File) {
+    if let Err(_err) = serve_client(&pipe) {
+        warn!(error = %_err, "broker client failed");
+    }
+
+    let _ = pipe.sync_all();
+    let _ = ffi::disconnect_named_pipe(pipe.as_raw_handle() as HANDLE);
+}
+
+fn serve_client(pipe: &fs::File) -> io::Result<()> {
+    let pid =
+        ffi::get_named_pipe_client_process_id(pipe.as_raw_handle() as HANDLE)?;
+
+    let process = ffi::open_process(PROCESS_DUP_HANDLE, pid)?;
+    let process = guard(process, |process| {
+        let _ = ffi::close_handle(process);
+    });
+
+    let mut writer = pipe;
+
+    for request in io::BufReader::new(pipe).lines() {
+        let request = request?;
+        debug!(pid, request = %request, "broker request");
+
+        let response = match execute(&request, *process) {
+            Ok(payload) => format!("ok\t{}", payload),
+            Err(err) => format!(
+                "err\t{}\t{}",
+                err.raw_os_error().unwrap_or(0),
+                err.to_string().replace(&['\t', '\r', '\n'][..], " ")
+            ),
+        };
+
+        writeln!(writer, "{}", response)?;
+    }
+
+    Ok(())
+}
+
+fn execute(request: &str, process: HANDLE) -> io::Result<String> {
+    let args: Vec<&str> = request.split('\t').collect();
+
+    match args.as_slice() {
+        ["create", name] => {
+            let dev = Device::create_named(DEFAULT_COMPONENT_ID, name)?;
+            hand_off(&dev, process)
+        }
+        ["open", name] => {
+            let dev = Device::open(name)?;
+            hand_off(&dev, process)
+        }
+        ["delete", name] => {
+            Device::delete_by_name(DEFAULT_COMPONENT_ID, name)?;
+            Ok(String::new())
+        }
+        ["set_name", name, newname] => {
+            validate_name(newname)?;
+
+            let luid = ffi::alias_to_luid(&encode_utf16(name))?;
+            iface::check_interface(DEFAULT_COMPONENT_ID, &luid)?;
+
+            netsh::set_interface_name(name, newname)?;
+            Ok(String::new())
+        }
+        ["set_ip", name, address, mask] => {
+            let luid = ffi::alias_to_luid(&encode_utf16(name))?;
+            iface::check_interface(DEFAULT_COMPONENT_ID, &luid)?;
+            let index = ffi::luid_to_index(&luid)?;
+
+            let address: net::Ipv4Addr = address.parse().map_err(invalid)?;
+            let mask: net::Ipv4Addr = mask.parse().map_err(invalid)?;
+
+            netsh::set_interface_ip(
+                index,
+                &address.to_string(),
+                &mask.to_string(),
+            )?;
+            Ok(String::new())
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Unknown broker request",
+        )),
+    }
+}
+
+fn invalid(err: net::AddrParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
+
+/// Duplicate the handle into the client, the broker's own handle
+/// is closed when `dev` is dropped while the adapter stays
+fn hand_off(dev: &Device, process: HANDLE) -> io::Result<String> {
+    let handle = dev.duplicate_to(process)?;
+
+    Ok(format!("{}\t{}", handle as usize, dev.get_luid().Value))
+}
+
+/// Unprivileged side of the broker, forwards requests to a
+/// `BrokerServer` running elevated
+/// Example
+/// ```no_run
+/// use tap_windows::BrokerClient;
+/// use std::io::Read;
+///
+/// let mut broker = BrokerClient::connect("my-vpn-broker")
+///     .expect("Failed to connect to broker");
+///
+/// let mut dev = broker.create("My Interface")
+///     .expect("Failed to create device");
+///
+/// broker.set_ip("My Interface", [192, 168, 60, 1], [255, 255, 255, 0])
+///     .expect("Failed to set device ip");
+///
+/// dev.up().expect("Failed to set device up");
+///
+/// let mut buf = [0; 1600];
+/// let amt = dev.read(&mut buf).expect("Failed to read packet");
+/// ```
+pub struct BrokerClient {
+    pipe: io::BufReader<fs::File>,
+}
+
+impl BrokerClient {
+    /// Connect to the broker listening on `\\.\pipe\<pipe_name>`,
+    /// waiting up to 5 seconds if it is busy accepting another client.
+    /// Fails if the pipe was not created by an elevated process
+    pub fn connect(pipe_name: &str) -> io::Result<Self> {
+        let path = pipe_path(pipe_name);
+        let start = time::Instant::now();
+
+        loop {
+            match fs::OpenOptions::new()
+                .access_mode(GENERIC_READ | FILE_WRITE_DATA)
+                .open(&path)
+            {
+                Ok(pipe) => {
+                    check_owner(&pipe)?;
+
+                    return Ok(Self {
+                        pipe: io::BufReader::new(pipe),
+                    });
+                }
+                Err(err)
+                    if is_busy(&err) && start.elapsed() < CONNECT_TIMEOUT =>
+                {
+                    thread::sleep(time::Duration::from_millis(50));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Create a new adapter with the given name
+    pub fn create(&mut self, name: &str) -> io::Result<Device> {
+        let payload = self.request(&["create", name])?;
+        receive_device(&payload)
+    }
+
+    /// Open an existing adapter by name
+    pub fn open(&mut self, name: &str) -> io::Result<Device> {
+        let payload = self.request(&["open", name])?;
+        receive_device(&payload)
+    }
+
+    /// Delete an adapter by name, see `Device::delete_by_name`
+    pub fn delete(&mut self, name: &str) -> io::Result<()> {
+        self.request(&["delete", name]).map(|_| ())
+    }
+
+    /// Rename an adapter
+    pub fn set_name(&mut self, name: &str, newname: &str) -> io::Result<()> {
+        self.request(&["set_name", name, newname]).map(|_| ())
+    }
+
+    /// Set the ipv4 address of an adapter, see `Device::set_ip`
+    pub fn set_ip<A, B>(
+        &mut self,
+        name: &str,
+        address: A,
+        mask: B,
+    ) -> io::Result<()>
+    where
+        A: Into<net::Ipv4Addr>,
+        B: Into<net::Ipv4Addr>,
+    {
+        let address = address.into().to_string();
+        let mask = mask.into().to_string();
+
+        self.request(&["set_ip", name, &address, &mask]).map(|_| ())
+    }
+
+    /// Send a request and wait for its response, returning the payload
+    fn request(&mut self, args: &[&str]) -> io::Result<String> {
+        // Tabs and newlines delimit the protocol, valid names
+        // never contain them
+        if args.iter().any(|arg| arg.contains(&['\t', '\r', '\n'][..])) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Broker arguments can't contain tabs or newlines",
+            ));
+        }
+
+        writeln!(self.pipe.get_mut(), "{}", args.join("\t"))?;
+
+        let mut response = String::new();
+        if self.pipe.read_line(&mut response)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Broker closed the connection",
+            ));
+        }
+
+        let response = response.trim_end_matches(&['\r', '\n'][..]);
+        let mut parts = response.splitn(2, '\t');
+
+        match (parts.next(), parts.next()) {
+            (Some("ok"), payload) => Ok(payload.unwrap_or("").to_owned()),
+            (Some("err"), Some(error)) => {
+                let mut parts = error.splitn(2, '\t');
+
+                match (parts.next(), parts.next()) {
+                    (Some(code), Some(message)) => match code.parse() {
+                        Ok(0) | Err(_) => {
+                            Err(io::Error::new(io::ErrorKind::Other, message))
+                        }
+                        Ok(code) => Err(io::Error::from_raw_os_error(code)),
+                    },
+                    _ => Err(malformed()),
+                }
+            }
+            _ => Err(malformed()),
+        }
+    }
+}
+
+/// The broker is creating the next instance after accepting
+/// another client
+fn is_busy(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(ERROR_PIPE_BUSY as i32)
+}
+
+/// Anyone can create the pipe while the broker is not running,
+/// only a pipe created by an elevated process is trusted
+fn check_owner(pipe: &fs::File) -> io::Result<()> {
+    if ffi::is_owned_by_admin(pipe.as_raw_handle() as HANDLE)? {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Broker pipe is not owned by an administrator",
+        ))
+    }
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Malformed broker response")
+}
+
+/// Build a device from the handle the broker duplicated into
+/// this process
+fn receive_device(payload: &str) -> io::Result<Device> {
+    let mut parts = payload.split('\t');
+
+    let handle = parts.next().and_then(|handle| handle.parse::<usize>().ok());
+    let luid = parts.next().and_then(|luid| luid.parse::<u64>().ok());
+
+    match (handle, luid) {
+        (Some(handle), Some(luid)) => unsafe {
+            Device::from_raw_parts(
+                handle as HANDLE,
+                NET_LUID { Value: luid },
+                DEFAULT_COMPONENT_ID,
+            )
+        },
+        _ => Err(malformed()),
+    }
+}
@@ -0,0 +1,255 @@
+//! C compatible api, see `include/tap_windows.h`.
+//! Functions returning `c_int` return 0 on success or a win32
+//! error code, `tap_read` and `tap_write` return the amount of
+//! bytes transferred or a negated win32 error code. A panic is
+//! reported as `ERROR_GEN_FAILURE` instead of unwinding into C
+
+use winapi::shared::winerror::*;
+
+use std::ffi::CStr;
+use std::io::{Read, Write};
+use std::os::raw::{c_char, c_int};
+use std::{io, net, panic, ptr, slice};
+
+use crate::{Device, DEFAULT_COMPONENT_ID};
+
+/// Win32 error code describing an io error
+fn error_code(err: &io::Error) -> c_int {
+    if let Some(code) = err.raw_os_error().filter(|code| *code != 0) {
+        return code;
+    }
+
+    let code = match err.kind() {
+        io::ErrorKind::PermissionDenied => ERROR_ACCESS_DENIED,
+        io::ErrorKind::InvalidInput => ERROR_INVALID_PARAMETER,
+        io::ErrorKind::NotFound => ERROR_NOT_FOUND,
+        io::ErrorKind::TimedOut => ERROR_TIMEOUT,
+        _ => ERROR_GEN_FAILURE,
+    };
+
+    code as _
+}
+
+fn status(res: io::Result<()>) -> c_int {
+    match res {
+        Ok(()) => 0,
+        Err(err) => error_code(&err),
+    }
+}
+
+/// Returned by functions that panicked
+const FAILURE: c_int = ERROR_GEN_FAILURE as _;
+const FAILURE_LEN: isize = -(ERROR_GEN_FAILURE as isize);
+
+/// Run the body of an exported function, returning `failure` if it
+/// panics
+fn guard<T>(failure: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(panic::AssertUnwindSafe(f)).unwrap_or(failure)
+}
+
+fn invalid_input() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "Invalid argument")
+}
+
+/// Borrow a nul terminated utf8 string
+unsafe fn str_arg<'a>(string: *const c_char) -> io::Result<&'a str> {
+    if string.is_null() {
+        return Err(invalid_input());
+    }
+
+    CStr::from_ptr(string).to_str().map_err(|_| invalid_input())
+}
+
+unsafe fn ipv4_arg(string: *const c_char) -> io::Result<net::Ipv4Addr> {
+    str_arg(string)?.parse().map_err(|_| invalid_input())
+}
+
+unsafe fn device_arg<'a>(dev: *mut Device) -> io::Result<&'a mut Device> {
+    dev.as_mut().ok_or_else(invalid_input)
+}
+
+/// Store a new device in `out`, it must be released with `tap_close`
+unsafe fn store_device(
+    dev: io::Result<Device>,
+    out: *mut *mut Device,
+) -> c_int {
+    if out.is_null() {
+        return ERROR_INVALID_PARAMETER as _;
+    }
+
+    match dev {
+        Ok(dev) => {
+            *out = Box::into_raw(Box::new(dev));
+            0
+        }
+        Err(err) => {
+            *out = ptr::null_mut();
+            error_code(&err)
+        }
+    }
+}
+
+/// Create a new adapter, `name` may be NULL
+#[no_mangle]
+pub unsafe extern "C" fn tap_create(
+    name: *const c_char,
+    out: *mut *mut Device,
+) -> c_int {
+    guard(FAILURE, || {
+        let dev = if name.is_null() {
+            Device::create()
+        } else {
+            str_arg(name).and_then(|name| {
+                Device::create_named(DEFAULT_COMPONENT_ID, name)
+            })
+        };
+
+        store_device(dev, out)
+    })
+}
+
+/// Open an existing adapter by name
+#[no_mangle]
+pub unsafe extern "C" fn tap_open(
+    name: *const c_char,
+    out: *mut *mut Device,
+) -> c_int {
+    guard(FAILURE, || {
+        store_device(str_arg(name).and_then(Device::open), out)
+    })
+}
+
+/// Close the device, the adapter is left in place
+#[no_mangle]
+pub unsafe extern "C" fn tap_close(dev: *mut Device) {
+    guard((), || {
+        if !dev.is_null() {
+            drop(Box::from_raw(dev));
+        }
+    })
+}
+
+/// Close the device and delete the adapter, `dev` is released
+/// even if the deletion fails
+#[no_mangle]
+pub unsafe extern "C" fn tap_delete(dev: *mut Device) -> c_int {
+    guard(FAILURE, || {
+        if dev.is_null() {
+            return ERROR_INVALID_PARAMETER as _;
+        }
+
+        status(Box::from_raw(dev).delete())
+    })
+}
+
+/// Set the media status of the adapter to connected
+#[no_mangle]
+pub unsafe extern "C" fn tap_up(dev: *mut Device) -> c_int {
+    guard(FAILURE, || status(device_arg(dev).and_then(|dev| dev.up())))
+}
+
+/// Set the media status of the adapter to disconnected
+#[no_mangle]
+pub unsafe extern "C" fn tap_down(dev: *mut Device) -> c_int {
+    guard(FAILURE, || {
+        status(device_arg(dev).and_then(|dev| dev.down()))
+    })
+}
+
+/// Read a single packet, blocking until one is available
+#[no_mangle]
+pub unsafe extern "C" fn tap_read(
+    dev: *mut Device,
+    buf: *mut u8,
+    len: usize,
+) -> isize {
+    guard(FAILURE_LEN, || {
+        if buf.is_null() {
+            return -(ERROR_INVALID_PARAMETER as isize);
+        }
+
+        let buf = slice::from_raw_parts_mut(buf, len);
+
+        match device_arg(dev).and_then(|dev| dev.read(buf)) {
+            Ok(amt) => amt as isize,
+            Err(err) => -(error_code(&err) as isize),
+        }
+    })
+}
+
+/// Write a single packet
+#[no_mangle]
+pub unsafe extern "C" fn tap_write(
+    dev: *mut Device,
+    buf: *const u8,
+    len: usize,
+) -> isize {
+    guard(FAILURE_LEN, || {
+        if buf.is_null() {
+            return -(ERROR_INVALID_PARAMETER as isize);
+        }
+
+        let buf = slice::from_raw_parts(buf, len);
+
+        match device_arg(dev).and_then(|dev| dev.write(buf)) {
+            Ok(amt) => amt as isize,
+            Err(err) => -(error_code(&err) as isize),
+        }
+    })
+}
+
+/// Set the ipv4 address and mask, both in dotted decimal notation
+#[no_mangle]
+pub unsafe extern "C" fn tap_set_ip(
+    dev: *mut Device,
+    address: *const c_char,
+    mask: *const c_char,
+) -> c_int {
+    guard(FAILURE, || {
+        status((|| -> io::Result<()> {
+            let dev = device_arg(dev)?;
+            dev.set_ip(ipv4_arg(address)?, ipv4_arg(mask)?)
+        })())
+    })
+}
+
+/// Rename the interface
+#[no_mangle]
+pub unsafe extern "C" fn tap_set_name(
+    dev: *mut Device,
+    name: *const c_char,
+) -> c_int {
+    guard(FAILURE, || {
+        status((|| -> io::Result<()> {
+            device_arg(dev)?.set_name(str_arg(name)?)
+        })())
+    })
+}
+
+/// Retrieve the mtu of the interface
+#[no_mangle]
+pub unsafe extern "C" fn tap_get_mtu(dev: *mut Device, out: *mut u32) -> c_int {
+    guard(FAILURE, || {
+        if out.is_null() {
+            return ERROR_INVALID_PARAMETER as _;
+        }
+
+        status(device_arg(dev).and_then(|dev| dev.get_mtu()).map(|mtu| {
+            *out = mtu;
+        }))
+    })
+}
+
+/// Retrieve the mac of the interface, `out` must hold 6 bytes
+#[no_mangle]
+pub unsafe extern "C" fn tap_get_mac(dev: *mut Device, out: *mut u8) -> c_int {
+    guard(FAILURE, || {
+        if out.is_null() {
+            return ERROR_INVALID_PARAMETER as _;
+        }
+
+        status(device_arg(dev).and_then(|dev| dev.get_mac()).map(|mac| {
+            ptr::copy_nonoverlapping(mac.octets().as_ptr(), out, 6);
+        }))
+    })
+}
@@ -0,0 +1,241 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time;
+
+use crate::sync::Exclusive;
+
+/// Direction of a captured frame, seen from the device handle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Frame read from the device, sent by Windows through the interface
+    Rx,
+    /// Frame written to the device, received by Windows
+    Tx,
+}
+
+/// Callback receiving every frame going through the device
+pub type CaptureCallback = dyn FnMut(Direction, &[u8]) + Send;
+
+/// Capture sink for the frames read from and written to a device,
+/// install it with `Device::set_capture`.
+/// Requires the `capture` feature
+/// Example
+/// ```no_run
+/// use tap_windows::{Capture, Device};
+///
+/// let mut dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// // Keep at most 4 files of 16MiB each
+/// let capture = Capture::rotating("tunnel.pcapng", 16 << 20, 4)
+///     .expect("Failed to create capture");
+///
+/// dev.set_capture(Some(capture));
+/// ```
+pub struct Capture {
+    sink: Sink,
+}
+
+enum Sink {
+    File(PcapngFile),
+    Callback(Exclusive<Box<CaptureCallback>>),
+}
+
+impl Capture {
+    /// Capture into a single pcapng file, growing without limits
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::rotating(path, u64::MAX, 1)
+    }
+
+    /// Capture into pcapng files of at most `max_bytes` each, once a
+    /// file is full the next one is started, up to `max_files` files
+    /// after which the oldest one is overwritten.
+    /// Files after the first are named `<stem>.<n>.<extension>`
+    pub fn rotating<P: AsRef<Path>>(
+        path: P,
+        max_bytes: u64,
+        max_files: u32,
+    ) -> io::Result<Self> {
+        let file = PcapngFile::new(path.as_ref(), max_bytes, max_files)?;
+
+        Ok(Self {
+            sink: Sink::File(file),
+        })
+    }
+
+    /// Hand every frame to a callback instead of writing it to disk
+    pub fn callback<F>(callback: F) -> Self
+    where
+        F: FnMut(Direction, &[u8]) + Send + 'static,
+    {
+        Self {
+            sink: Sink::Callback(Exclusive::new(Box::new(callback))),
+        }
+    }
+
+    pub(crate) fn frame(&mut self, direction: Direction, frame: &[u8]) {
+        match &mut self.sink {
+            Sink::File(file) => {
+                if let Err(_err) = file.write_frame(direction, frame) {
+                    warn!(error = %_err, "failed to write capture");
+                }
+            }
+            Sink::Callback(callback) => callback.get_mut()(direction, frame),
+        }
+    }
+}
+
+/// Pcapng block types
+const SECTION_HEADER_BLOCK: u32 = 0x0a0d0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x00000001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
+
+/// Pcapng options
+const OPT_ENDOFOPT: u16 = 0;
+const EPB_FLAGS: u16 = 2;
+
+/// LINKTYPE_ETHERNET, tap devices carry ethernet frames
+const LINKTYPE_ETHERNET: u16 = 1;
+
+struct PcapngFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+    index: u32,
+    written: u64,
+    file: BufWriter<File>,
+}
+
+impl PcapngFile {
+    fn new(path: &Path, max_bytes: u64, max_files: u32) -> io::Result<Self> {
+        let mut file = Self {
+            path: path.to_owned(),
+            max_bytes,
+            max_files: max_files.max(1),
+            index: 0,
+            written: 0,
+            file: BufWriter::new(File::create(path)?),
+        };
+
+        file.write_header()?;
+
+        Ok(file)
+    }
+
+    fn file_path(&self, index: u32) -> PathBuf {
+        if index == 0 {
+            return self.path.clone();
+        }
+
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let name = match self.path.extension() {
+            Some(ext) => {
+                format!("{}.{}.{}", stem, index, ext.to_string_lossy())
+            }
+            None => format!("{}.{}", stem, index),
+        };
+
+        self.path.with_file_name(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        self.index = (self.index + 1) % self.max_files;
+        self.written = 0;
+        self.file = BufWriter::new(File::create(self.file_path(self.index))?);
+
+        self.write_header()
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let mut block = Vec::with_capacity(48);
+
+        // Section header, no options
+        block.extend_from_slice(&SECTION_HEADER_BLOCK.to_le_bytes());
+        block.extend_from_slice(&28u32.to_le_bytes());
+        block.extend_from_slice(&0x1a2b3c4du32.to_le_bytes());
+        block.extend_from_slice(&1u16.to_le_bytes());
+        block.extend_from_slice(&0u16.to_le_bytes());
+        block.extend_from_slice(&(-1i64).to_le_bytes());
+        block.extend_from_slice(&28u32.to_le_bytes());
+
+        // Interface description, default microsecond resolution
+        block.extend_from_slice(&INTERFACE_DESCRIPTION_BLOCK.to_le_bytes());
+        block.extend_from_slice(&20u32.to_le_bytes());
+        block.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+        block.extend_from_slice(&0u16.to_le_bytes());
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(&20u32.to_le_bytes());
+
+        self.write_block(&block)
+    }
+
+    fn write_frame(
+        &mut self,
+        direction: Direction,
+        frame: &[u8],
+    ) -> io::Result<()> {
+        let timestamp = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .map(|timestamp| timestamp.as_micros() as u64)
+            .unwrap_or(0);
+
+        // Direction as seen by the host: frames read from the device
+        // are outbound, frames written to it are inbound
+        let flags: u32 = match direction {
+            Direction::Rx => 0b10,
+            Direction::Tx => 0b01,
+        };
+
+        let padding = (4 - frame.len() % 4) % 4;
+        let len = (44 + frame.len() + padding) as u32;
+
+        // Header and trailer are built on the stack, so capturing
+        // doesn't allocate for every frame
+        let mut head = [0; 28];
+        head[0..4].copy_from_slice(&ENHANCED_PACKET_BLOCK.to_le_bytes());
+        head[4..8].copy_from_slice(&len.to_le_bytes());
+        head[12..16].copy_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        head[16..20].copy_from_slice(&(timestamp as u32).to_le_bytes());
+        head[20..24].copy_from_slice(&(frame.len() as u32).to_le_bytes());
+        head[24..28].copy_from_slice(&(frame.len() as u32).to_le_bytes());
+
+        let mut tail = [0; 16];
+        tail[0..2].copy_from_slice(&EPB_FLAGS.to_le_bytes());
+        tail[2..4].copy_from_slice(&4u16.to_le_bytes());
+        tail[4..8].copy_from_slice(&flags.to_le_bytes());
+        tail[8..10].copy_from_slice(&OPT_ENDOFOPT.to_le_bytes());
+        tail[12..16].copy_from_slice(&len.to_le_bytes());
+
+        // Files holding just the header are never rotated, so frames
+        // bigger than max_bytes still end up somewhere
+        if self.written + len as u64 > self.max_bytes && self.written > 48 {
+            self.rotate()?;
+        }
+
+        self.write_block(&head)?;
+        self.write_block(frame)?;
+        self.write_block(&[0; 3][..padding])?;
+        self.write_block(&tail)
+    }
+
+    fn write_block(&mut self, block: &[u8]) -> io::Result<()> {
+        self.file.write_all(block)?;
+        self.written += block.len() as u64;
+
+        Ok(())
+    }
+}
+
+impl Drop for PcapngFile {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
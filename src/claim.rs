@@ -0,0 +1,89 @@
+use winapi::shared::winerror::ERROR_FILE_NOT_FOUND;
+
+use std::io;
+use std::os::windows::io::{FromRawHandle, OwnedHandle};
+
+use crate::{decode_utf16, encode_utf16, ffi, Device, DeviceInfo};
+
+/// Name of the mutex claiming the adapter with the given guid, in
+/// the global namespace so claims are seen across sessions
+fn claim_name(guid: &str) -> Vec<u16> {
+    encode_utf16(&format!("Global\\TapWindowsClaim{}", guid))
+}
+
+/// Check if the claim mutex exists. Opening it is only denied if it
+/// does, so that counts as existing too
+fn claim_exists(name: &[u16]) -> bool {
+    match ffi::open_mutex(name) {
+        Ok(mutex) => {
+            drop(unsafe { OwnedHandle::from_raw_handle(mutex as _) });
+            true
+        }
+        Err(err) => err.raw_os_error() != Some(ERROR_FILE_NOT_FOUND as _),
+    }
+}
+
+/// Cooperative claim on an adapter, taken with `Device::try_claim`.
+/// Other processes see the adapter as claimed until this is dropped
+/// or the process exits
+#[derive(Debug)]
+pub struct AdapterClaim {
+    _mutex: OwnedHandle,
+}
+
+impl Device {
+    /// Claim the adapter for this process, so other instances of the
+    /// same product can tell it is taken and pick another one,
+    /// instead of failing to open it with `ERROR_GEN_FAILURE`.
+    /// Returns `None` if another process already holds the claim, and
+    /// `PermissionDenied` if this process may not create the claim.
+    /// Claims are advisory, they don't stop anyone from opening the
+    /// adapter
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let _claim = dev
+    ///     .try_claim()
+    ///     .expect("Failed to claim device")
+    ///     .expect("Device is used by another instance");
+    /// ```
+    pub fn try_claim(&self) -> io::Result<Option<AdapterClaim>> {
+        let guid = ffi::string_from_guid(&self.get_guid()?)?;
+        let name = claim_name(&decode_utf16(&guid));
+
+        let (mutex, existed) = match ffi::create_mutex(&name) {
+            Ok(res) => res,
+            // Either created by a process of another user, or this
+            // one may not create global objects
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                return if claim_exists(&name) {
+                    Ok(None)
+                } else {
+                    Err(err)
+                };
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mutex = unsafe { OwnedHandle::from_raw_handle(mutex as _) };
+
+        if existed {
+            debug!(luid = self.luid.Value, "adapter already claimed");
+            return Ok(None);
+        }
+
+        Ok(Some(AdapterClaim { _mutex: mutex }))
+    }
+}
+
+impl DeviceInfo {
+    /// Check if a process holds a claim on the adapter, see
+    /// `Device::try_claim`
+    pub fn is_claimed(&self) -> bool {
+        claim_exists(&claim_name(&self.guid))
+    }
+}
@@ -0,0 +1,139 @@
+use winapi::shared::ifdef::NET_LUID;
+
+use std::io;
+
+use crate::{encode_utf16, ffi, iface, CreateOptions, Device, DeviceInfo};
+
+/// Runs `f` for every component id in order, moving on to the next
+/// one only when the previous failed with `NotFound`
+fn try_each<'a, T>(
+    component_ids: &[&'a str],
+    mut f: impl FnMut(&'a str) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut last_err = None;
+
+    for &component_id in component_ids {
+        match f(component_id) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                last_err = Some(err);
+            }
+            res => return res,
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "No component id given")
+    }))
+}
+
+impl Device {
+    /// Opens an existing tap-windows device by name, bound to any of
+    /// the given hardware ids, tried in order
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, KNOWN_COMPONENT_IDS};
+    ///
+    /// let dev = Device::open_with_ids("My Own Device", KNOWN_COMPONENT_IDS)
+    ///     .expect("Failed to open device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn open_with_ids(
+        name: &str,
+        component_ids: &[&str],
+    ) -> io::Result<Self> {
+        let name = encode_utf16(name);
+
+        let luid = ffi::alias_to_luid(&name)?;
+
+        Self::open_by_luid_with_ids(component_ids, luid)
+    }
+
+    /// Opens an existing tap-windows device by luid, bound to any of
+    /// the given hardware ids, tried in order
+    pub fn open_by_luid_with_ids(
+        component_ids: &[&str],
+        luid: NET_LUID,
+    ) -> io::Result<Self> {
+        let component_id = try_each(component_ids, |component_id| {
+            iface::check_interface(component_id, &luid).map(|_| component_id)
+        })?;
+
+        Self::open_by_luid(component_id, luid)
+    }
+
+    /// Opens the first free adapter bound to any of the given
+    /// hardware ids, adapters of earlier ids are preferred
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, KNOWN_COMPONENT_IDS};
+    ///
+    /// let dev = Device::open_any_with_ids(KNOWN_COMPONENT_IDS)
+    ///     .expect("Failed to open device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn open_any_with_ids(component_ids: &[&str]) -> io::Result<Self> {
+        try_each(component_ids, Self::open_any)
+    }
+
+    /// Creates a new tap-windows device with the given options, bound
+    /// to the first of the given hardware ids with an installed
+    /// driver. `options.component_id` is ignored
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{CreateOptions, Device, KNOWN_COMPONENT_IDS};
+    ///
+    /// let dev = Device::create_with_ids(
+    ///     KNOWN_COMPONENT_IDS,
+    ///     CreateOptions {
+    ///         name: Some("My Interface"),
+    ///         ..Default::default()
+    ///     },
+    /// )
+    /// .expect("Failed to create device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn create_with_ids(
+        component_ids: &[&str],
+        options: CreateOptions,
+    ) -> io::Result<Self> {
+        try_each(component_ids, |component_id| {
+            Self::create_with(CreateOptions {
+                component_id,
+                ..options
+            })
+        })
+    }
+
+    /// Lists every adapter bound to any of the given hardware ids,
+    /// in the order of the ids. Adapters matching more than one id
+    /// are only listed once
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, KNOWN_COMPONENT_IDS};
+    ///
+    /// let infos = Device::enumerate_with_ids(KNOWN_COMPONENT_IDS)
+    ///     .expect("Failed to enumerate devices");
+    ///
+    /// for info in infos {
+    ///     println!("{} {}", info.name, info.component_id);
+    /// }
+    /// ```
+    pub fn enumerate_with_ids(
+        component_ids: &[&str],
+    ) -> io::Result<Vec<DeviceInfo>> {
+        let mut infos: Vec<DeviceInfo> = Vec::new();
+
+        for &component_id in component_ids {
+            for info in Self::enumerate(component_id)? {
+                if !infos.iter().any(|other| other.luid == info.luid) {
+                    infos.push(info);
+                }
+            }
+        }
+
+        Ok(infos)
+    }
+}
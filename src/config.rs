@@ -0,0 +1,440 @@
+//! Module holding the declarative configuration of a device
+
+use winapi::shared::ifdef::MediaConnectStateConnected;
+use winapi::shared::netioapi::MIB_IPFORWARD_ROW2;
+use winapi::shared::nldef::{
+    IpPrefixOriginManual, NlroManual, MIB_IPPROTO_NETMGMT,
+};
+use winapi::shared::winerror::ERROR_NOT_FOUND;
+use winapi::shared::ws2def::{AF_INET, AF_INET6, AF_UNSPEC};
+
+use std::{io, net};
+
+use crate::{dns, ffi, Device};
+
+/// Address assigned to an interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AddressConfig {
+    /// The address itself
+    pub address: net::IpAddr,
+    /// Length of the on-link prefix
+    pub prefix: u8,
+}
+
+/// Route through an interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RouteConfig {
+    /// Destination network
+    pub destination: net::IpAddr,
+    /// Length of the destination prefix
+    pub prefix: u8,
+    /// Next hop, `None` for on-link routes
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gateway: Option<net::IpAddr>,
+    /// Route metric, `None` to let Windows choose it
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metric: Option<u32>,
+}
+
+/// Desired state of a device, applied with `Device::apply` and
+/// read back with `Device::current_config`.
+/// Fields set to `None` are left untouched when applying, lists
+/// are applied exactly, removing entries not in the list.
+/// With the `serde` feature enabled it can be (de)serialized,
+/// missing fields default to `None`
+/// Example
+/// ```no_run
+/// use tap_windows::{AddressConfig, Device, DeviceConfig};
+///
+/// let dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// let config = DeviceConfig {
+///     addresses: Some(vec![AddressConfig {
+///         address: [10, 8, 0, 2].into(),
+///         prefix: 24,
+///     }]),
+///     dns: Some(vec![[10, 8, 0, 1].into()]),
+///     up: Some(true),
+///     ..Default::default()
+/// };
+///
+/// let current = dev.current_config().expect("Failed to read config");
+/// let changes = config.diff(&current);
+///
+/// if changes != Default::default() {
+///     println!("Reconciling {:?}", changes);
+///     dev.apply(&changes).expect("Failed to apply config");
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DeviceConfig {
+    /// Name (alias) of the interface
+    pub name: Option<String>,
+    /// Manually configured unicast addresses
+    pub addresses: Option<Vec<AddressConfig>>,
+    /// Dns servers
+    pub dns: Option<Vec<net::IpAddr>>,
+    /// Manually configured routes
+    pub routes: Option<Vec<RouteConfig>>,
+    /// Ip mtu of the interface, for both ipv4 and ipv6
+    pub mtu: Option<u32>,
+    /// Media status of the adapter
+    pub up: Option<bool>,
+}
+
+impl DeviceConfig {
+    /// Keep only the fields that are set and differ from `current`,
+    /// the result is what `Device::apply` would change.
+    /// Addresses and routes are compared regardless of their order,
+    /// and a route without metric matches any metric
+    pub fn diff(&self, current: &DeviceConfig) -> DeviceConfig {
+        fn changed<T: Clone + PartialEq>(
+            wanted: &Option<T>,
+            current: &Option<T>,
+        ) -> Option<T> {
+            wanted
+                .clone()
+                .filter(|wanted| Some(wanted) != current.as_ref())
+        }
+
+        fn changed_set<T: Clone>(
+            wanted: &Option<Vec<T>>,
+            current: &Option<Vec<T>>,
+            matches: fn(&T, &T) -> bool,
+        ) -> Option<Vec<T>> {
+            let current = match current {
+                Some(current) => current,
+                None => return wanted.clone(),
+            };
+
+            wanted.clone().filter(|wanted| {
+                !wanted.iter().all(|w| current.iter().any(|c| matches(c, w)))
+                    || !current
+                        .iter()
+                        .all(|c| wanted.iter().any(|w| matches(c, w)))
+            })
+        }
+
+        DeviceConfig {
+            name: changed(&self.name, &current.name),
+            addresses: changed_set(
+                &self.addresses,
+                &current.addresses,
+                |current, wanted| current == wanted,
+            ),
+            dns: changed(&self.dns, &current.dns),
+            routes: changed_set(&self.routes, &current.routes, route_matches),
+            mtu: changed(&self.mtu, &current.mtu),
+            up: changed(&self.up, &current.up),
+        }
+    }
+}
+
+/// Read the current state of every field
+pub fn current_config(dev: &Device) -> io::Result<DeviceConfig> {
+    let row = ffi::get_if_entry2(&dev.luid)?;
+
+    // Ipv4 might be disabled on the interface
+    let mtu = match ffi::get_ip_interface_entry(&dev.luid, AF_INET as _) {
+        Err(err) if err.raw_os_error() == Some(ERROR_NOT_FOUND as _) => {
+            ffi::get_ip_interface_entry(&dev.luid, AF_INET6 as _)?
+        }
+        res => res?,
+    }
+    .NlMtu;
+
+    Ok(DeviceConfig {
+        name: Some(dev.get_name()?),
+        addresses: Some(addresses(dev)?),
+        dns: Some(dns::get_interface_dns(&dev.luid)?),
+        routes: Some(
+            routes(dev)?.into_iter().map(|(route, _)| route).collect(),
+        ),
+        mtu: Some(mtu),
+        up: Some(row.MediaConnectState == MediaConnectStateConnected),
+    })
+}
+
+/// Read the persistent part of the state, everything but the media
+/// status which follows whoever is using the device
+pub fn export_config(dev: &Device) -> io::Result<DeviceConfig> {
+    Ok(DeviceConfig {
+        up: None,
+        ..current_config(dev)?
+    })
+}
+
+/// Restore a snapshot taken with `export_config`
+pub fn import_config(dev: &Device, config: &DeviceConfig) -> io::Result<()> {
+    apply(
+        dev,
+        &DeviceConfig {
+            up: None,
+            ..config.clone()
+        },
+    )
+}
+
+/// Apply the fields that are set, touching only what differs
+pub fn apply(dev: &Device, config: &DeviceConfig) -> io::Result<()> {
+    if let Some(name) = &config.name {
+        if dev.get_name()? != *name {
+            dev.set_name(name)?;
+        }
+    }
+
+    if let Some(mtu) = config.mtu {
+        set_mtu(dev, mtu)?;
+    }
+
+    if let Some(addresses) = &config.addresses {
+        apply_addresses(dev, addresses)?;
+    }
+
+    // After the addresses, gateways must be reachable
+    if let Some(routes) = &config.routes {
+        apply_routes(dev, routes)?;
+    }
+
+    if let Some(servers) = &config.dns {
+        if dns::get_interface_dns(&dev.luid)? != *servers {
+            dev.set_dns(servers)?;
+        }
+    }
+
+    if let Some(up) = config.up {
+        dev.set_status(up)?;
+    }
+
+    Ok(())
+}
+
+fn set_mtu(dev: &Device, mtu: u32) -> io::Result<()> {
+    for &family in &[AF_INET, AF_INET6] {
+        let res = dev.update_ip_interface(family as _, |row| row.NlMtu = mtu);
+
+        match res {
+            // Ipv6 might be disabled on the interface
+            Err(err) if err.raw_os_error() == Some(ERROR_NOT_FOUND as _) => (),
+            res => res?,
+        }
+    }
+
+    Ok(())
+}
+
+fn addresses(dev: &Device) -> io::Result<Vec<AddressConfig>> {
+    let rows = ffi::get_unicast_ip_address_table(AF_UNSPEC as _)?;
+
+    Ok(rows
+        .iter()
+        .filter(|row| row.InterfaceLuid.Value == dev.luid.Value)
+        .filter(|row| row.PrefixOrigin == IpPrefixOriginManual)
+        .filter_map(|row| {
+            Some(AddressConfig {
+                address: ffi::sockaddr_inet_to_ip(&row.Address)?,
+                prefix: row.OnLinkPrefixLength,
+            })
+        })
+        .collect())
+}
+
+/// Replace the manual addresses of the family of `wanted` with it,
+/// leaving the other family alone
+pub fn set_address(dev: &Device, wanted: AddressConfig) -> io::Result<()> {
+    let mut addresses: Vec<_> = addresses(dev)?
+        .into_iter()
+        .filter(|other| other.address.is_ipv4() != wanted.address.is_ipv4())
+        .collect();
+    addresses.push(wanted);
+
+    apply_addresses(dev, &addresses)
+}
+
+pub fn apply_addresses(
+    dev: &Device,
+    wanted: &[AddressConfig],
+) -> io::Result<()> {
+    let rows = ffi::get_unicast_ip_address_table(AF_UNSPEC as _)?;
+    let mut current = Vec::new();
+
+    for row in rows
+        .iter()
+        .filter(|row| row.InterfaceLuid.Value == dev.luid.Value)
+        .filter(|row| row.PrefixOrigin == IpPrefixOriginManual)
+    {
+        let address = match ffi::sockaddr_inet_to_ip(&row.Address) {
+            Some(address) => AddressConfig {
+                address,
+                prefix: row.OnLinkPrefixLength,
+            },
+            None => continue,
+        };
+
+        if wanted.contains(&address) {
+            current.push(address);
+        } else {
+            ffi::delete_unicast_ip_address_entry(row)?;
+        }
+    }
+
+    for address in wanted.iter().filter(|a| !current.contains(a)) {
+        dev.add_address(address.address, address.prefix)?;
+    }
+
+    Ok(())
+}
+
+/// Manually added routes of the interface, along with their rows
+fn routes(dev: &Device) -> io::Result<Vec<(RouteConfig, MIB_IPFORWARD_ROW2)>> {
+    let rows = ffi::get_ip_forward_table2(AF_UNSPEC as _)?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|row| row.InterfaceLuid.Value == dev.luid.Value)
+        .filter(|row| row.Origin == NlroManual)
+        .filter_map(|row| {
+            let destination =
+                ffi::sockaddr_inet_to_ip(&row.DestinationPrefix.Prefix)?;
+            let gateway = ffi::sockaddr_inet_to_ip(&row.NextHop)
+                .filter(|gateway| !gateway.is_unspecified());
+
+            let route = RouteConfig {
+                destination,
+                prefix: row.DestinationPrefix.PrefixLength,
+                gateway,
+                metric: Some(row.Metric),
+            };
+
+            Some((route, row))
+        })
+        .collect())
+}
+
+/// Check if an existing route satisfies a wanted one,
+/// a wanted route without metric accepts any metric
+fn route_matches(current: &RouteConfig, wanted: &RouteConfig) -> bool {
+    current.destination == wanted.destination
+        && current.prefix == wanted.prefix
+        && current.gateway == wanted.gateway
+        && (wanted.metric.is_none() || current.metric == wanted.metric)
+}
+
+fn apply_routes(dev: &Device, wanted: &[RouteConfig]) -> io::Result<()> {
+    let mut current = Vec::new();
+
+    for (route, row) in routes(dev)? {
+        if wanted.iter().any(|wanted| route_matches(&route, wanted)) {
+            current.push(route);
+        } else {
+            ffi::delete_ip_forward_entry2(&row)?;
+        }
+    }
+
+    for route in wanted
+        .iter()
+        .filter(|wanted| !current.iter().any(|c| route_matches(c, wanted)))
+    {
+        add_route(dev, route)?;
+    }
+
+    Ok(())
+}
+
+fn add_route(dev: &Device, route: &RouteConfig) -> io::Result<()> {
+    let mut row = ffi::initialize_ip_forward_entry();
+    row.InterfaceLuid = dev.luid;
+    row.DestinationPrefix.Prefix =
+        ffi::sockaddr_inet_from_ip(&route.destination);
+    row.DestinationPrefix.PrefixLength = route.prefix;
+
+    // On-link routes use the unspecified address of the same family
+    let gateway = route.gateway.unwrap_or(match route.destination {
+        net::IpAddr::V4(_) => net::Ipv4Addr::UNSPECIFIED.into(),
+        net::IpAddr::V6(_) => net::Ipv6Addr::UNSPECIFIED.into(),
+    });
+    row.NextHop = ffi::sockaddr_inet_from_ip(&gateway);
+    row.Protocol = MIB_IPPROTO_NETMGMT;
+
+    if let Some(metric) = route.metric {
+        row.Metric = metric;
+    }
+
+    ffi::create_ip_forward_entry2(&row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(last: u8) -> AddressConfig {
+        AddressConfig {
+            address: [10, 8, 0, last].into(),
+            prefix: 24,
+        }
+    }
+
+    fn route(metric: Option<u32>) -> RouteConfig {
+        RouteConfig {
+            destination: [10, 9, 0, 0].into(),
+            prefix: 16,
+            gateway: Some([10, 8, 0, 1].into()),
+            metric,
+        }
+    }
+
+    #[test]
+    fn diff_ignores_order() {
+        let wanted = DeviceConfig {
+            addresses: Some(vec![address(2), address(3)]),
+            ..Default::default()
+        };
+        let current = DeviceConfig {
+            addresses: Some(vec![address(3), address(2)]),
+            mtu: Some(1500),
+            ..Default::default()
+        };
+
+        assert_eq!(wanted.diff(&current), DeviceConfig::default());
+    }
+
+    #[test]
+    fn diff_keeps_changed_lists() {
+        let wanted = DeviceConfig {
+            addresses: Some(vec![address(2)]),
+            ..Default::default()
+        };
+        let current = DeviceConfig {
+            addresses: Some(vec![address(2), address(3)]),
+            ..Default::default()
+        };
+
+        assert_eq!(wanted.diff(&current), wanted);
+        assert_eq!(wanted.diff(&DeviceConfig::default()), wanted);
+    }
+
+    #[test]
+    fn diff_routes_without_metric_match_any() {
+        let current = DeviceConfig {
+            routes: Some(vec![route(Some(25))]),
+            ..Default::default()
+        };
+
+        let any = DeviceConfig {
+            routes: Some(vec![route(None)]),
+            ..Default::default()
+        };
+        assert_eq!(any.diff(&current), DeviceConfig::default());
+
+        let other = DeviceConfig {
+            routes: Some(vec![route(Some(5))]),
+            ..Default::default()
+        };
+        assert_eq!(other.diff(&current), other);
+    }
+}
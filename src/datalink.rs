@@ -0,0 +1,120 @@
+use pnet_datalink::{
+    Channel, DataLinkReceiver, DataLinkSender, NetworkInterface,
+};
+
+use std::{io, sync::Arc};
+
+use crate::overlapped::Overlapped;
+use crate::{ffi, Device};
+
+/// Ethernet header plus a vlan tag
+const MAX_HEADER_LEN: usize = 18;
+
+impl Device {
+    /// Turn the device into a pnet datalink channel, so tooling
+    /// written against pnet can use it in place of Npcap. The
+    /// sender and receiver can be moved to different threads, the
+    /// device is closed once both are dropped.
+    /// Traffic is counted in the stats, but not captured nor queued
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use pnet_datalink::Channel;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let (mut tx, mut rx) = match dev.datalink_channel() {
+    ///     Ok(Channel::Ethernet(tx, rx)) => (tx, rx),
+    ///     _ => panic!("Failed to create channel"),
+    /// };
+    ///
+    /// let frame = rx.next().expect("Failed to read frame").to_vec();
+    /// tx.send_to(&frame, None);
+    /// ```
+    pub fn datalink_channel(mut self) -> io::Result<Channel> {
+        // Queued writes would complete behind the sender's back
+        self.set_write_queue(0)?;
+
+        let len = self.get_mtu().unwrap_or(1500) as usize + MAX_HEADER_LEN;
+        let dev = Arc::new(self);
+
+        let tx = Sender {
+            dev: dev.clone(),
+            overlapped: Overlapped::new()?,
+            buffer: Vec::new(),
+        };
+
+        let rx = Receiver {
+            dev,
+            overlapped: Overlapped::new()?,
+            buffer: vec![0; len],
+        };
+
+        Ok(Channel::Ethernet(Box::new(tx), Box::new(rx)))
+    }
+}
+
+struct Sender {
+    dev: Arc<Device>,
+    overlapped: Overlapped,
+    /// Reused by `build_and_send`
+    buffer: Vec<u8>,
+}
+
+impl Sender {
+    fn write(&mut self, frame: &[u8]) -> io::Result<()> {
+        let handle = self.dev.raw_handle()?;
+        let len = ffi::write_file(handle, frame, &mut self.overlapped)?;
+        self.dev.counters.add_tx(len as usize);
+
+        Ok(())
+    }
+}
+
+impl DataLinkSender for Sender {
+    fn build_and_send(
+        &mut self,
+        num_packets: usize,
+        packet_size: usize,
+        func: &mut dyn FnMut(&mut [u8]),
+    ) -> Option<io::Result<()>> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.resize(packet_size, 0);
+
+        let res = (0..num_packets).try_for_each(|_| {
+            func(&mut buffer);
+            self.write(&buffer)
+        });
+
+        self.buffer = buffer;
+        Some(res)
+    }
+
+    /// The destination is ignored, a tap device has a single link
+    fn send_to(
+        &mut self,
+        packet: &[u8],
+        _dst: Option<NetworkInterface>,
+    ) -> Option<io::Result<()>> {
+        Some(self.write(packet))
+    }
+}
+
+struct Receiver {
+    dev: Arc<Device>,
+    overlapped: Overlapped,
+    buffer: Vec<u8>,
+}
+
+impl DataLinkReceiver for Receiver {
+    fn next(&mut self) -> io::Result<&[u8]> {
+        let handle = self.dev.raw_handle()?;
+        let len =
+            ffi::read_file(handle, &mut self.buffer, &mut self.overlapped)?
+                as usize;
+        self.dev.counters.add_rx(len);
+
+        Ok(&self.buffer[..len])
+    }
+}
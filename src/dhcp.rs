@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use crate::packet::*;
+use crate::MacAddress;
+
+const SERVER_PORT: u16 = 67;
+const CLIENT_PORT: u16 = 68;
+
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+
+/// Offset of the options in a bootp message, after the cookie
+const OPTIONS_OFFSET: usize = 240;
+
+const DISCOVER: u8 = 1;
+const OFFER: u8 = 2;
+const REQUEST: u8 = 3;
+const ACK: u8 = 5;
+const NAK: u8 = 6;
+const RELEASE: u8 = 7;
+
+const OPTION_PAD: u8 = 0;
+const OPTION_MASK: u8 = 1;
+const OPTION_ROUTER: u8 = 3;
+const OPTION_DNS: u8 = 6;
+const OPTION_REQUESTED_IP: u8 = 50;
+const OPTION_LEASE_TIME: u8 = 51;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_SERVER_ID: u8 = 54;
+const OPTION_RENEWAL_TIME: u8 = 58;
+const OPTION_REBINDING_TIME: u8 = 59;
+const OPTION_END: u8 = 255;
+
+/// Configuration of a `DhcpServer`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DhcpConfig {
+    /// Address the server answers from, usually the gateway
+    pub server: Ipv4Addr,
+    /// Mac address the server answers from, it only needs to be
+    /// unique on the link
+    pub server_mac: MacAddress,
+    /// First address handed out
+    pub pool_start: Ipv4Addr,
+    /// Number of addresses handed out, one per client
+    pub pool_size: u32,
+    /// Subnet mask given to the clients
+    pub mask: Ipv4Addr,
+    /// Default gateway given to the clients
+    pub router: Option<Ipv4Addr>,
+    /// Dns servers given to the clients
+    pub dns: Vec<Ipv4Addr>,
+    /// Lease duration
+    pub lease_time: Duration,
+}
+
+/// Userspace dhcp server answering the DISCOVER and REQUEST frames
+/// read from the adapter, as an alternative to the driver's own
+/// dhcp masquerade when more options or several clients are needed.
+/// Built with the `dhcp` feature
+/// Example
+/// ```no_run
+/// use tap_windows::{Device, DhcpConfig, DhcpServer, MacAddress};
+/// use std::io::{Read, Write};
+/// use std::time::Duration;
+///
+/// let mut dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// let mut dhcp = DhcpServer::new(DhcpConfig {
+///     server: [10, 8, 0, 1].into(),
+///     server_mac: MacAddress([0x02, 0, 0, 0, 0, 1]),
+///     pool_start: [10, 8, 0, 2].into(),
+///     pool_size: 16,
+///     mask: [255, 255, 255, 0].into(),
+///     router: Some([10, 8, 0, 1].into()),
+///     dns: vec![[1, 1, 1, 1].into()],
+///     lease_time: Duration::from_secs(3600),
+/// });
+///
+/// let mut buf = [0; 1600];
+/// loop {
+///     let amt = dev.read(&mut buf).expect("Failed to read packet");
+///
+///     if let Some(reply) = dhcp.handle_frame(&buf[..amt]) {
+///         dev.write(&reply).expect("Failed to write packet");
+///         continue;
+///     }
+///
+///     // Handle the other frames
+/// }
+/// ```
+pub struct DhcpServer {
+    config: DhcpConfig,
+    leases: HashMap<MacAddress, Ipv4Addr>,
+}
+
+impl DhcpServer {
+    /// Create a server with no lease handed out yet
+    pub fn new(config: DhcpConfig) -> Self {
+        Self {
+            config,
+            leases: HashMap::new(),
+        }
+    }
+
+    /// Address currently leased to a client
+    pub fn lease(&self, mac: &MacAddress) -> Option<Ipv4Addr> {
+        self.leases.get(mac).copied()
+    }
+
+    /// Handle a frame read from the adapter, returns the reply to
+    /// write back if it was a dhcp request for this server
+    pub fn handle_frame(&mut self, frame: &[u8]) -> Option<Vec<u8>> {
+        let ip = parse_ipv4(frame)?;
+        if ip.protocol(frame) != IPPROTO_UDP || ip.is_later_fragment(frame) {
+            return None;
+        }
+
+        let udp = frame.get(ip.payload..ip.end)?;
+        if udp.len() < 8 || read_u16(udp, 2) != SERVER_PORT {
+            return None;
+        }
+
+        let message = udp.get(8..)?;
+        if message.len() < OPTIONS_OFFSET
+            || message[0] != 1
+            || message[236..240] != MAGIC_COOKIE
+        {
+            return None;
+        }
+
+        let mut chaddr = [0; 6];
+        chaddr.copy_from_slice(&message[28..34]);
+        let mac = MacAddress(chaddr);
+
+        let options = &message[OPTIONS_OFFSET..];
+        let message_type =
+            *find_option(options, OPTION_MESSAGE_TYPE)?.first()?;
+
+        // The client picked another server
+        if let Some(server) = find_option(options, OPTION_SERVER_ID) {
+            if server != self.config.server.octets() {
+                return None;
+            }
+        }
+
+        let reply_type = match message_type {
+            DISCOVER => {
+                self.allocate(&mac)?;
+                OFFER
+            }
+            REQUEST => {
+                let requested = find_option(options, OPTION_REQUESTED_IP)
+                    .and_then(|ip| <[u8; 4]>::try_from(ip).ok())
+                    .map(Ipv4Addr::from)
+                    .unwrap_or_else(|| {
+                        Ipv4Addr::new(
+                            message[12],
+                            message[13],
+                            message[14],
+                            message[15],
+                        )
+                    });
+
+                match self.allocate(&mac) {
+                    Some(lease) if lease == requested => ACK,
+                    _ => NAK,
+                }
+            }
+            RELEASE => {
+                self.leases.remove(&mac);
+                return None;
+            }
+            _ => return None,
+        };
+
+        Some(self.build_reply(message, &mac, reply_type))
+    }
+
+    /// Find the address leased to `mac`, or lease a free one
+    fn allocate(&mut self, mac: &MacAddress) -> Option<Ipv4Addr> {
+        if let Some(lease) = self.leases.get(mac) {
+            return Some(*lease);
+        }
+
+        let start = u32::from(self.config.pool_start);
+        let lease = (0..self.config.pool_size)
+            .map(|offset| Ipv4Addr::from(start.wrapping_add(offset)))
+            .find(|ip| !self.leases.values().any(|lease| lease == ip))?;
+
+        self.leases.insert(*mac, lease);
+        Some(lease)
+    }
+
+    fn build_reply(
+        &self,
+        request: &[u8],
+        mac: &MacAddress,
+        reply_type: u8,
+    ) -> Vec<u8> {
+        let config = &self.config;
+
+        let mut message = vec![0; OPTIONS_OFFSET];
+        // op, htype, hlen
+        message[..3].copy_from_slice(&[2, 1, 6]);
+        // xid, secs and flags
+        message[4..12].copy_from_slice(&request[4..12]);
+        if reply_type != NAK {
+            if let Some(lease) = self.leases.get(mac) {
+                message[16..20].copy_from_slice(&lease.octets());
+            }
+        }
+        message[20..24].copy_from_slice(&config.server.octets());
+        // giaddr and chaddr
+        message[24..44].copy_from_slice(&request[24..44]);
+        message[236..240].copy_from_slice(&MAGIC_COOKIE);
+
+        let mut option = |code: u8, data: &[u8]| {
+            message.push(code);
+            message.push(data.len() as u8);
+            message.extend_from_slice(data);
+        };
+
+        option(OPTION_MESSAGE_TYPE, &[reply_type]);
+        option(OPTION_SERVER_ID, &config.server.octets());
+
+        if reply_type != NAK {
+            let lease = config.lease_time.as_secs().min(u32::MAX as u64) as u32;
+
+            option(OPTION_LEASE_TIME, &lease.to_be_bytes());
+            option(OPTION_RENEWAL_TIME, &(lease / 2).to_be_bytes());
+            option(OPTION_REBINDING_TIME, &(lease / 8 * 7).to_be_bytes());
+            option(OPTION_MASK, &config.mask.octets());
+
+            if let Some(router) = config.router {
+                option(OPTION_ROUTER, &router.octets());
+            }
+
+            if !config.dns.is_empty() {
+                let dns: Vec<u8> =
+                    config.dns.iter().flat_map(|dns| dns.octets()).collect();
+                option(OPTION_DNS, &dns);
+            }
+        }
+
+        message.push(OPTION_END);
+
+        // Some clients drop bootp messages shorter than 300 bytes
+        if message.len() < 300 {
+            message.resize(300, OPTION_PAD);
+        }
+
+        let mut udp = Vec::with_capacity(8 + message.len());
+        udp.extend_from_slice(&SERVER_PORT.to_be_bytes());
+        udp.extend_from_slice(&CLIENT_PORT.to_be_bytes());
+        udp.extend_from_slice(&((8 + message.len()) as u16).to_be_bytes());
+        // The udp checksum is optional over ipv4
+        udp.extend_from_slice(&[0, 0]);
+        udp.extend_from_slice(&message);
+
+        // The client has no address yet, broadcast the reply
+        build_ipv4_frame(
+            MacAddress([0xff; 6]),
+            config.server_mac,
+            config.server,
+            Ipv4Addr::BROADCAST,
+            IPPROTO_UDP,
+            &udp,
+        )
+    }
+}
+
+/// Find the data of an option, options overrunning the message
+/// are cut short
+fn find_option(mut options: &[u8], code: u8) -> Option<&[u8]> {
+    loop {
+        match options {
+            [OPTION_END, ..] | [] => return None,
+            [OPTION_PAD, rest @ ..] => options = rest,
+            [current, len, rest @ ..] => {
+                let len = (*len as usize).min(rest.len());
+                if *current == code {
+                    return Some(&rest[..len]);
+                }
+                options = &rest[len..];
+            }
+            [_] => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: MacAddress = MacAddress([0x02, 0, 0, 0, 0, 0x10]);
+
+    fn server(pool_size: u32) -> DhcpServer {
+        DhcpServer::new(DhcpConfig {
+            server: [10, 8, 0, 1].into(),
+            server_mac: MacAddress([0x02, 0, 0, 0, 0, 1]),
+            pool_start: [10, 8, 0, 2].into(),
+            pool_size,
+            mask: [255, 255, 255, 0].into(),
+            router: Some([10, 8, 0, 1].into()),
+            dns: vec![[1, 1, 1, 1].into(), [8, 8, 8, 8].into()],
+            lease_time: Duration::from_secs(3600),
+        })
+    }
+
+    /// Client message of `message_type`, `options` are appended
+    /// after the message type
+    fn request(mac: MacAddress, message_type: u8, options: &[u8]) -> Vec<u8> {
+        let mut message = vec![0; OPTIONS_OFFSET];
+        message[..3].copy_from_slice(&[1, 1, 6]);
+        message[4..8].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        message[28..34].copy_from_slice(&mac.octets());
+        message[236..240].copy_from_slice(&MAGIC_COOKIE);
+        message.extend_from_slice(&[OPTION_MESSAGE_TYPE, 1, message_type]);
+        message.extend_from_slice(options);
+        message.push(OPTION_END);
+
+        let mut udp = Vec::new();
+        udp.extend_from_slice(&CLIENT_PORT.to_be_bytes());
+        udp.extend_from_slice(&SERVER_PORT.to_be_bytes());
+        udp.extend_from_slice(&((8 + message.len()) as u16).to_be_bytes());
+        udp.extend_from_slice(&[0, 0]);
+        udp.extend_from_slice(&message);
+
+        build_ipv4_frame(
+            MacAddress([0xff; 6]),
+            mac,
+            Ipv4Addr::UNSPECIFIED,
+            Ipv4Addr::BROADCAST,
+            IPPROTO_UDP,
+            &udp,
+        )
+    }
+
+    /// Bootp message of a reply frame
+    fn message(reply: &[u8]) -> &[u8] {
+        let ip = parse_ipv4(reply).unwrap();
+        assert_eq!(ip.protocol(reply), IPPROTO_UDP);
+        assert_eq!(ip.destination(reply), Ipv4Addr::BROADCAST);
+
+        let udp = &reply[ip.payload..ip.end];
+        assert_eq!(read_u16(udp, 0), SERVER_PORT);
+        assert_eq!(read_u16(udp, 2), CLIENT_PORT);
+        assert_eq!(read_u16(udp, 4) as usize, udp.len());
+
+        &udp[8..]
+    }
+
+    fn option(message: &[u8], code: u8) -> Option<&[u8]> {
+        find_option(&message[OPTIONS_OFFSET..], code)
+    }
+
+    #[test]
+    fn offers_with_options() {
+        let mut server = server(4);
+        let reply = server.handle_frame(&request(CLIENT, DISCOVER, &[]));
+        let reply = reply.unwrap();
+        let message = message(&reply);
+
+        assert_eq!(message[0], 2);
+        assert_eq!(message[4..8], [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(message[16..20], [10, 8, 0, 2]);
+        assert_eq!(message[28..34], CLIENT.octets());
+        assert!(message.len() >= 300);
+
+        assert_eq!(option(message, OPTION_MESSAGE_TYPE), Some(&[OFFER][..]));
+        assert_eq!(option(message, OPTION_SERVER_ID), Some(&[10, 8, 0, 1][..]));
+        assert_eq!(
+            option(message, OPTION_LEASE_TIME),
+            Some(&3600u32.to_be_bytes()[..])
+        );
+        assert_eq!(
+            option(message, OPTION_RENEWAL_TIME),
+            Some(&1800u32.to_be_bytes()[..])
+        );
+        assert_eq!(
+            option(message, OPTION_REBINDING_TIME),
+            Some(&3150u32.to_be_bytes()[..])
+        );
+        assert_eq!(option(message, OPTION_MASK), Some(&[255, 255, 255, 0][..]));
+        assert_eq!(option(message, OPTION_ROUTER), Some(&[10, 8, 0, 1][..]));
+        assert_eq!(
+            option(message, OPTION_DNS),
+            Some(&[1, 1, 1, 1, 8, 8, 8, 8][..])
+        );
+    }
+
+    #[test]
+    fn acks_the_offered_address() {
+        let mut server = server(4);
+        server.handle_frame(&request(CLIENT, DISCOVER, &[]));
+
+        let requested = [OPTION_REQUESTED_IP, 4, 10, 8, 0, 2];
+        let reply = server.handle_frame(&request(CLIENT, REQUEST, &requested));
+        let reply = reply.unwrap();
+
+        assert_eq!(
+            option(message(&reply), OPTION_MESSAGE_TYPE),
+            Some(&[ACK][..])
+        );
+        assert_eq!(server.lease(&CLIENT), Some([10, 8, 0, 2].into()));
+    }
+
+    #[test]
+    fn naks_other_addresses() {
+        let mut server = server(4);
+
+        let requested = [OPTION_REQUESTED_IP, 4, 10, 8, 0, 9];
+        let reply = server.handle_frame(&request(CLIENT, REQUEST, &requested));
+        let reply = reply.unwrap();
+        let message = message(&reply);
+
+        assert_eq!(option(message, OPTION_MESSAGE_TYPE), Some(&[NAK][..]));
+        assert_eq!(message[16..20], [0, 0, 0, 0]);
+        assert_eq!(option(message, OPTION_LEASE_TIME), None);
+    }
+
+    #[test]
+    fn ignores_requests_for_other_servers() {
+        let mut server = server(4);
+        let other = [OPTION_SERVER_ID, 4, 10, 8, 0, 254];
+
+        assert_eq!(
+            server.handle_frame(&request(CLIENT, REQUEST, &other)),
+            None
+        );
+        assert_eq!(server.lease(&CLIENT), None);
+    }
+
+    #[test]
+    fn releases_and_exhausts_the_pool() {
+        let mut server = server(1);
+        let other = MacAddress([0x02, 0, 0, 0, 0, 0x20]);
+
+        assert!(server
+            .handle_frame(&request(CLIENT, DISCOVER, &[]))
+            .is_some());
+        assert_eq!(server.handle_frame(&request(other, DISCOVER, &[])), None);
+
+        assert_eq!(server.handle_frame(&request(CLIENT, RELEASE, &[])), None);
+        assert_eq!(server.lease(&CLIENT), None);
+
+        assert!(server
+            .handle_frame(&request(other, DISCOVER, &[]))
+            .is_some());
+        assert_eq!(server.lease(&other), Some([10, 8, 0, 2].into()));
+    }
+
+    #[test]
+    fn finds_options() {
+        let options = [
+            OPTION_PAD,
+            OPTION_MESSAGE_TYPE,
+            1,
+            DISCOVER,
+            OPTION_PAD,
+            OPTION_DNS,
+            8,
+            1,
+            1,
+        ];
+
+        assert_eq!(find_option(&options, OPTION_MESSAGE_TYPE), Some(&[1][..]));
+        // Cut short by the end of the message
+        assert_eq!(find_option(&options, OPTION_DNS), Some(&[1, 1][..]));
+        assert_eq!(find_option(&options, OPTION_ROUTER), None);
+        assert_eq!(find_option(&[OPTION_END, OPTION_DNS, 0], OPTION_DNS), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Module holding the dns configuration fallback chain
+
+use winapi::shared::guiddef::GUID;
+use winapi::shared::ifdef::NET_LUID;
+use winapi::shared::netioapi::*;
+
+use std::{io, mem, net};
+
+use winapi::um::winnt::KEY_QUERY_VALUE;
+use winapi::um::winreg::HKEY_LOCAL_MACHINE;
+
+use crate::registry::RegKey;
+use crate::{decode_utf16, encode_utf16, ffi, netsh, wmi};
+
+/// Set the dns servers of an interface, trying in order
+/// SetInterfaceDnsSettings, wmi and finally netsh
+pub fn set_interface_dns(
+    luid: &NET_LUID,
+    servers: &[net::IpAddr],
+) -> io::Result<()> {
+    let v4 = servers
+        .iter()
+        .filter_map(|server| match server {
+            net::IpAddr::V4(server) => Some(*server),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let v6 = servers
+        .iter()
+        .filter_map(|server| match server {
+            net::IpAddr::V6(server) => Some(*server),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let v4_strings = v4.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let v6_strings = v6.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+    // Windows 10 2004 and later, on failure keep going down the chain
+    let guid = ffi::luid_to_guid(luid)?;
+    let native = set_native(&guid, &v4_strings, false).map(|res| {
+        res.and_then(|_| set_native(&guid, &v6_strings, true).unwrap_or(Ok(())))
+    });
+
+    if let Some(Ok(())) = native {
+        return Ok(());
+    }
+
+    // Wmi only knows about ipv4 servers
+    let index = ffi::luid_to_index(luid)?;
+    wmi::set_interface_dns(index, &v4)
+        .or_else(|_| netsh::set_interface_dns(index, "ipv4", &v4_strings))?;
+
+    netsh::set_interface_dns(index, "ipv6", &v6_strings)
+}
+
+/// Returns None if SetInterfaceDnsSettings is not available
+fn set_native(
+    guid: &GUID,
+    servers: &[String],
+    ipv6: bool,
+) -> Option<io::Result<()>> {
+    let mut name_server = encode_utf16(&servers.join(","));
+
+    let mut settings: DNS_INTERFACE_SETTINGS = unsafe { mem::zeroed() };
+    settings.Version = DNS_INTERFACE_SETTINGS_VERSION1;
+    settings.Flags = DNS_SETTING_NAMESERVER;
+    settings.NameServer = name_server.as_mut_ptr();
+
+    if ipv6 {
+        settings.Flags |= DNS_SETTING_IPV6;
+    }
+
+    ffi::set_interface_dns_settings(guid, &settings)
+}
+
+/// Read the statically configured dns servers of an interface,
+/// ipv4 servers first, from the tcpip parameters in the registry
+pub fn get_interface_dns(luid: &NET_LUID) -> io::Result<Vec<net::IpAddr>> {
+    const KEYS: [&str; 2] = [
+        r"SYSTEM\CurrentControlSet\Services\Tcpip\Parameters\Interfaces",
+        r"SYSTEM\CurrentControlSet\Services\Tcpip6\Parameters\Interfaces",
+    ];
+
+    let guid = decode_utf16(&ffi::string_from_guid(&ffi::luid_to_guid(luid)?)?);
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+
+    let mut servers = Vec::new();
+
+    for key in KEYS.iter() {
+        // Missing keys or values just mean no servers
+        let value: String = match hklm
+            .open_subkey_with_flags(
+                format!(r"{}\{}", key, guid),
+                KEY_QUERY_VALUE,
+            )
+            .and_then(|key| key.get_value("NameServer"))
+        {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+
+        servers.extend(
+            value
+                .split([',', ' '])
+                .filter_map(|server| server.parse::<net::IpAddr>().ok()),
+        );
+    }
+
+    Ok(servers)
+}
@@ -0,0 +1,196 @@
+use std::borrow::Cow;
+
+use crate::packet::*;
+use crate::Device;
+
+/// Classifier picking the dscp of outgoing packets, see
+/// `Device::set_dscp_classifier`
+pub type DscpClassifier = dyn FnMut(&[u8]) -> Option<u8> + Send;
+
+impl Device {
+    /// Run every ipv4 and ipv6 frame written to the device through
+    /// `classifier`, and set the dscp field of the packet to the
+    /// returned value, from 0 to 63. `None` leaves the packet
+    /// unchanged. The ecn bits are preserved.
+    /// Applies to `write`, `write_many` and `write_with_callback`,
+    /// after the write filter
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// // Expedited forwarding for udp, best effort for the rest
+    /// dev.set_dscp_classifier(|frame| match frame.get(12..14) {
+    ///     Some([0x08, 0x00]) if frame.get(23) == Some(&17) => Some(46),
+    ///     _ => None,
+    /// });
+    /// ```
+    pub fn set_dscp_classifier<F>(&mut self, classifier: F)
+    where
+        F: FnMut(&[u8]) -> Option<u8> + Send + 'static,
+    {
+        *self.dscp_classifier.get_mut() = Some(Box::new(classifier));
+    }
+
+    /// Stop marking the written packets
+    pub fn clear_dscp_classifier(&mut self) {
+        *self.dscp_classifier.get_mut() = None;
+    }
+}
+
+/// Mark a borrowed frame, copying it only if its dscp changes
+pub(crate) fn mark_cow(
+    classifier: &mut Option<Box<DscpClassifier>>,
+    frame: &mut Cow<[u8]>,
+) {
+    let classifier = match classifier {
+        Some(classifier) => classifier,
+        None => return,
+    };
+
+    let dscp = match classifier(frame) {
+        Some(dscp) => dscp & 0x3f,
+        None => return,
+    };
+
+    if matches!(get_dscp(frame), Some(current) if current != dscp) {
+        set_dscp(frame.to_mut(), dscp);
+    }
+}
+
+fn get_dscp(frame: &[u8]) -> Option<u8> {
+    match ether_type(frame)? {
+        ETHERTYPE_IPV4 => {
+            let ip = parse_ipv4(frame)?;
+            Some(frame[ip.start + 1] >> 2)
+        }
+        ETHERTYPE_IPV6 => {
+            let header = frame.get(ETHER_HEADER_LEN..ETHER_HEADER_LEN + 2)?;
+            match header[0] >> 4 {
+                6 => Some((read_u16(header, 0) >> 6) as u8 & 0x3f),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Only called on frames `get_dscp` could parse
+fn set_dscp(frame: &mut [u8], dscp: u8) {
+    match ether_type(frame) {
+        Some(ETHERTYPE_IPV4) => {
+            let start = ETHER_HEADER_LEN;
+            let old = read_u16(frame, start);
+
+            frame[start + 1] = (dscp << 2) | (frame[start + 1] & 0x03);
+
+            // Incremental update of the header checksum, RFC 1624
+            let new = read_u16(frame, start);
+            let sum =
+                !read_u16(frame, start + 10) as u32 + !old as u32 + new as u32;
+            write_u16(frame, start + 10, checksum_finish(sum));
+        }
+        Some(ETHERTYPE_IPV6) => {
+            // The traffic class sits between the version and the
+            // flow label
+            let word = read_u16(frame, ETHER_HEADER_LEN);
+            let word = (word & !(0x3f << 6)) | ((dscp as u16) << 6);
+            write_u16(frame, ETHER_HEADER_LEN, word);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MacAddress;
+
+    fn ipv4_frame(tos: u8) -> Vec<u8> {
+        let mut frame = build_ipv4_frame(
+            MacAddress([2; 6]),
+            MacAddress([4; 6]),
+            [10, 0, 0, 1].into(),
+            [10, 0, 0, 2].into(),
+            IPPROTO_UDP,
+            &[0; 8],
+        );
+
+        frame[ETHER_HEADER_LEN + 1] = tos;
+        write_u16(&mut frame, ETHER_HEADER_LEN + 10, 0);
+        let sum = checksum(&frame[ETHER_HEADER_LEN..ETHER_HEADER_LEN + 20]);
+        write_u16(&mut frame, ETHER_HEADER_LEN + 10, sum);
+        frame
+    }
+
+    fn ipv6_frame(traffic_class: u8) -> Vec<u8> {
+        let mut frame = vec![2; 6];
+        frame.extend_from_slice(&[4; 6]);
+        frame.extend_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+        frame.extend_from_slice(&[
+            0x60 | traffic_class >> 4,
+            traffic_class << 4 | 0x0a,
+            0xbc,
+            0xde,
+        ]);
+        frame.extend_from_slice(&[0, 0, IPPROTO_UDP, 64]);
+        frame.extend_from_slice(&[0; 32]);
+        frame
+    }
+
+    fn mark(frame: &[u8], dscp: Option<u8>) -> Cow<'_, [u8]> {
+        let mut classifier: Option<Box<DscpClassifier>> =
+            Some(Box::new(move |_: &[u8]| dscp));
+        let mut frame = Cow::Borrowed(frame);
+
+        mark_cow(&mut classifier, &mut frame);
+        frame
+    }
+
+    #[test]
+    fn marks_ipv4_keeping_ecn() {
+        let frame = ipv4_frame(0x03);
+        let marked = mark(&frame, Some(46));
+        let header = &marked[ETHER_HEADER_LEN..ETHER_HEADER_LEN + 20];
+
+        assert_eq!(header[1], 46 << 2 | 0x03);
+        assert_eq!(checksum(header), 0);
+        assert_eq!(get_dscp(&marked), Some(46));
+    }
+
+    #[test]
+    fn marks_ipv6_keeping_ecn_and_flow_label() {
+        let frame = ipv6_frame(0x01);
+        let marked = mark(&frame, Some(10));
+
+        assert_eq!(get_dscp(&marked), Some(10));
+        assert_eq!(
+            marked[ETHER_HEADER_LEN..ETHER_HEADER_LEN + 4],
+            [0x62, 0x9a, 0xbc, 0xde]
+        );
+    }
+
+    #[test]
+    fn copies_only_when_changed() {
+        let frame = ipv4_frame(46 << 2);
+
+        assert!(matches!(mark(&frame, Some(46)), Cow::Borrowed(_)));
+        assert!(matches!(mark(&frame, None), Cow::Borrowed(_)));
+        assert!(matches!(mark(&frame, Some(0)), Cow::Owned(_)));
+
+        // Only the low 6 bits are a dscp
+        assert_eq!(get_dscp(&mark(&frame, Some(0xc1))), Some(1));
+    }
+
+    #[test]
+    fn ignores_other_frames() {
+        let mut arp = vec![0xff; 12];
+        arp.extend_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+        arp.extend_from_slice(&[0; 28]);
+
+        assert_eq!(get_dscp(&arp), None);
+        assert!(matches!(mark(&arp, Some(46)), Cow::Borrowed(_)));
+    }
+}
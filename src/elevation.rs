@@ -0,0 +1,38 @@
+use crate::ffi;
+use scopeguard::guard;
+use winapi::um::winnt::TOKEN_QUERY;
+
+use std::io;
+
+/// Check whether the current process runs with administrator rights,
+/// creating and deleting adapters fails without them
+/// Example
+/// ```no_run
+/// if !tap_windows::is_elevated().unwrap_or(false) {
+///     eprintln!("Run me as administrator");
+/// }
+/// ```
+pub fn is_elevated() -> io::Result<bool> {
+    let token = ffi::open_process_token(TOKEN_QUERY)?;
+    let token = guard(token, |token| {
+        let _ = ffi::close_handle(token);
+    });
+
+    ffi::get_token_elevation(*token)
+}
+
+/// Fail early with a meaningful error instead of letting SetupDi or
+/// netsh fail later on with a cryptic one
+pub(crate) fn require_elevated() -> io::Result<()> {
+    // If the check itself fails let the operation go on and report
+    // whatever error it runs into
+    if is_elevated().unwrap_or(true) {
+        return Ok(());
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "Administrator rights are required to configure adapters, \
+         run the process elevated (\"Run as administrator\")",
+    ))
+}
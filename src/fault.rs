@@ -0,0 +1,257 @@
+use std::collections::VecDeque;
+use std::{io, thread, time};
+
+use crate::{Device, MockDevice, TempDevice};
+
+/// Devices whose media status can be toggled by a `FaultyDevice`
+pub trait LinkStatus {
+    /// Set the media status, true for connected
+    fn set_status(&self, status: bool) -> io::Result<()>;
+}
+
+impl LinkStatus for Device {
+    fn set_status(&self, status: bool) -> io::Result<()> {
+        Device::set_status(self, status)
+    }
+}
+
+impl LinkStatus for TempDevice {
+    fn set_status(&self, status: bool) -> io::Result<()> {
+        Device::set_status(self, status)
+    }
+}
+
+impl LinkStatus for MockDevice {
+    fn set_status(&self, status: bool) -> io::Result<()> {
+        MockDevice::set_status(self, status)
+    }
+}
+
+/// Faults injected by a `FaultyDevice`, probabilities go from
+/// 0.0 (never) to 1.0 (every frame) and apply to both directions
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FaultConfig {
+    /// Seed of the random generator, the same seed and traffic
+    /// always give the same faults
+    pub seed: u64,
+    /// Probability of a frame being dropped
+    pub loss: f64,
+    /// Probability of a frame being delivered twice
+    pub duplicate: f64,
+    /// Probability of a frame being held back and delivered
+    /// after the next one
+    pub reorder: f64,
+    /// Delay added to every frame
+    pub latency: time::Duration,
+    /// Time the link stays connected between two flaps
+    pub up_time: time::Duration,
+    /// Time the link stays disconnected during a flap, zero
+    /// disables flapping
+    pub down_time: time::Duration,
+}
+
+/// Wraps a real or mock device and degrades its link on purpose,
+/// to test how vpn code copes with loss, duplication, reordering,
+/// latency and link flaps. Faults are drawn from a seeded random
+/// generator, so a failing test can be replayed.
+/// Flaps are driven by the reads and writes, the media status is
+/// toggled by the first call after each period ends.
+/// Built with the `test-util` feature
+/// Example
+/// ```
+/// use tap_windows::{FaultConfig, FaultyDevice, MockDevice};
+/// use std::io::Write;
+///
+/// let dev = MockDevice::new("My Interface");
+/// let handle = dev.handle();
+///
+/// let mut dev = FaultyDevice::new(dev, FaultConfig {
+///     duplicate: 1.0,
+///     ..Default::default()
+/// });
+///
+/// dev.write(&[1, 2, 3]).unwrap();
+///
+/// assert_eq!(handle.written(), Some(vec![1, 2, 3]));
+/// assert_eq!(handle.written(), Some(vec![1, 2, 3]));
+/// ```
+pub struct FaultyDevice<D> {
+    dev: D,
+    config: FaultConfig,
+    rng: u64,
+    start: time::Instant,
+    /// Status last set by a flap
+    up: bool,
+    /// Frames to return before reading from the device again
+    read_queue: VecDeque<Vec<u8>>,
+    /// Frame read and held back to be reordered
+    held_read: Option<Vec<u8>>,
+    /// Frame written and held back to be reordered
+    held_write: Option<Vec<u8>>,
+}
+
+impl<D> FaultyDevice<D> {
+    /// Wrap `dev`, flap periods start now
+    pub fn new(dev: D, config: FaultConfig) -> Self {
+        Self {
+            dev,
+            rng: config.seed,
+            config,
+            start: time::Instant::now(),
+            up: true,
+            read_queue: VecDeque::new(),
+            held_read: None,
+            held_write: None,
+        }
+    }
+
+    /// Change the injected faults, the random generator is
+    /// seeded again
+    pub fn set_config(&mut self, config: FaultConfig) {
+        self.rng = config.seed;
+        self.config = config;
+    }
+
+    /// Borrow the underlying device
+    pub fn get_ref(&self) -> &D {
+        &self.dev
+    }
+
+    /// Mutably borrow the underlying device, frames it reads and
+    /// writes directly skip the faults
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.dev
+    }
+
+    /// Give back the underlying device, held back frames are lost
+    pub fn into_inner(self) -> D {
+        self.dev
+    }
+
+    /// splitmix64, good enough and seedable with any value
+    fn next_random(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        // 53 random bits, uniform in [0, 1)
+        let sample = (self.next_random() >> 11) as f64 / (1u64 << 53) as f64;
+
+        probability > 0.0 && sample < probability
+    }
+
+    fn delay(&self) {
+        if self.config.latency > time::Duration::from_secs(0) {
+            thread::sleep(self.config.latency);
+        }
+    }
+}
+
+impl<D: LinkStatus> FaultyDevice<D> {
+    /// Toggle the media status if a flap period ended
+    fn flap(&mut self) -> io::Result<()> {
+        let up_time = self.config.up_time.as_secs_f64();
+        let down_time = self.config.down_time.as_secs_f64();
+
+        if down_time <= 0.0 {
+            return Ok(());
+        }
+
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let up = elapsed % (up_time + down_time) < up_time;
+
+        if up != self.up {
+            debug!(up, "fault injection link flap");
+
+            self.dev.set_status(up)?;
+            self.up = up;
+        }
+
+        Ok(())
+    }
+}
+
+/// Frames larger than `buf` are truncated
+impl<D: io::Read + LinkStatus> io::Read for FaultyDevice<D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.flap()?;
+
+        let frame = loop {
+            if let Some(frame) = self.read_queue.pop_front() {
+                break frame;
+            }
+
+            let amt = self.dev.read(buf)?;
+            let frame = buf[..amt].to_vec();
+
+            if self.chance(self.config.loss) {
+                continue;
+            }
+
+            if self.chance(self.config.duplicate) {
+                self.read_queue.push_back(frame.clone());
+            }
+
+            match self.held_read.take() {
+                Some(held) => self.read_queue.push_back(held),
+                None if self.chance(self.config.reorder) => {
+                    self.held_read = Some(frame);
+                    continue;
+                }
+                None => {}
+            }
+
+            break frame;
+        };
+
+        self.delay();
+
+        let len = frame.len().min(buf.len());
+        buf[..len].copy_from_slice(&frame[..len]);
+
+        Ok(len)
+    }
+}
+
+/// Dropped frames are reported as written
+impl<D: io::Write + LinkStatus> io::Write for FaultyDevice<D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.flap()?;
+
+        if self.chance(self.config.loss) {
+            return Ok(buf.len());
+        }
+
+        if self.held_write.is_none() && self.chance(self.config.reorder) {
+            self.held_write = Some(buf.to_vec());
+            return Ok(buf.len());
+        }
+
+        self.delay();
+
+        self.dev.write(buf)?;
+        if self.chance(self.config.duplicate) {
+            self.dev.write(buf)?;
+        }
+
+        if let Some(held) = self.held_write.take() {
+            self.dev.write(&held)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    /// Writes the frame held back for reordering, if any
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(held) = self.held_write.take() {
+            self.dev.write(&held)?;
+        }
+
+        self.dev.flush()
+    }
+}
@@ -1,27 +1,53 @@
 // Many things will be used in the future
 #![allow(unused)]
 
-//! Module holding safe wrappers over winapi functions
+//! Module holding safe wrappers over winapi functions.
+//!
+//! Handles and pointers passed in are trusted to be valid, despite
+//! the functions not being `unsafe`. Part of them is exported by
+//! `raw_ffi`, marked `unsafe` where that trust matters
 
 use winapi::shared::basetsd::*;
 use winapi::shared::guiddef::GUID;
 use winapi::shared::ifdef::*;
 use winapi::shared::minwindef::*;
+// Also defined by winnt, which is glob imported as well
+use winapi::shared::minwindef::INT;
 use winapi::shared::netioapi::*;
+use winapi::shared::sddl::{
+    ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+};
 use winapi::shared::winerror::*;
+use winapi::shared::ws2def::*;
+use winapi::shared::ws2ipdef::*;
 
+use winapi::um::accctrl::SE_KERNEL_OBJECT;
+use winapi::um::aclapi::GetSecurityInfo;
+use winapi::um::cfgmgr32::*;
 use winapi::um::combaseapi::*;
 use winapi::um::errhandlingapi::*;
 use winapi::um::fileapi::*;
 use winapi::um::handleapi::*;
 use winapi::um::ioapiset::*;
+use winapi::um::libloaderapi::*;
+use winapi::um::minwinbase::{OVERLAPPED, SECURITY_ATTRIBUTES};
+use winapi::um::namedpipeapi::*;
+use winapi::um::processthreadsapi::*;
+use winapi::um::securitybaseapi::*;
 use winapi::um::setupapi::*;
+use winapi::um::stringapiset::MultiByteToWideChar;
 use winapi::um::synchapi::*;
+use winapi::um::sysinfoapi::*;
+use winapi::um::winbase::{
+    GetNamedPipeClientProcessId, LocalFree, FILE_FLAG_FIRST_PIPE_INSTANCE,
+    PIPE_ACCESS_DUPLEX, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+    PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
 use winapi::um::winioctl::*;
 use winapi::um::winnt::*;
 use winapi::um::winreg::*;
 
-use std::{io, mem, ptr};
+use std::{io, mem, net, ptr};
 
 #[allow(non_camel_case_types)]
 #[allow(non_snake_case)]
@@ -52,6 +78,41 @@ pub fn string_from_guid(guid: &GUID) -> io::Result<Vec<WCHAR>> {
     }
 }
 
+pub fn multi_byte_to_wide_char(
+    code_page: UINT,
+    bytes: &[u8],
+) -> io::Result<Vec<WCHAR>> {
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let convert = |wide: &mut [WCHAR]| unsafe {
+        MultiByteToWideChar(
+            code_page,
+            0,
+            bytes.as_ptr() as _,
+            bytes.len() as _,
+            wide.as_mut_ptr(),
+            wide.len() as _,
+        )
+    };
+
+    // Query the length first
+    let len = match convert(&mut []) {
+        0 => return Err(io::Error::last_os_error()),
+        len => len as usize,
+    };
+
+    let mut wide = vec![0; len];
+    match convert(&mut wide) {
+        0 => Err(io::Error::last_os_error()),
+        len => {
+            wide.truncate(len as usize);
+            Ok(wide)
+        }
+    }
+}
+
 pub fn alias_to_luid(alias: &[WCHAR]) -> io::Result<NET_LUID> {
     let mut luid = unsafe { mem::zeroed() };
 
@@ -70,6 +131,15 @@ pub fn luid_to_index(luid: &NET_LUID) -> io::Result<NET_IFINDEX> {
     }
 }
 
+pub fn index_to_luid(index: NET_IFINDEX) -> io::Result<NET_LUID> {
+    let mut luid = unsafe { mem::zeroed() };
+
+    match unsafe { ConvertInterfaceIndexToLuid(index, &mut luid) } {
+        0 => Ok(luid),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
 pub fn luid_to_guid(luid: &NET_LUID) -> io::Result<GUID> {
     let mut guid = unsafe { mem::zeroed() };
 
@@ -79,6 +149,15 @@ pub fn luid_to_guid(luid: &NET_LUID) -> io::Result<GUID> {
     }
 }
 
+pub fn guid_to_luid(guid: &GUID) -> io::Result<NET_LUID> {
+    let mut luid = unsafe { mem::zeroed() };
+
+    match unsafe { ConvertInterfaceGuidToLuid(guid, &mut luid) } {
+        0 => Ok(luid),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
 pub fn luid_to_alias(luid: &NET_LUID) -> io::Result<Vec<WCHAR>> {
     // IF_MAX_STRING_SIZE + 1
     let mut alias = vec![0; 257];
@@ -98,19 +177,72 @@ pub fn close_handle(handle: HANDLE) -> io::Result<()> {
     }
 }
 
+pub fn get_handle_information(handle: HANDLE) -> io::Result<DWORD> {
+    let mut flags = 0;
+
+    match unsafe { GetHandleInformation(handle, &mut flags) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(flags),
+    }
+}
+
+pub fn set_handle_information(
+    handle: HANDLE,
+    mask: DWORD,
+    flags: DWORD,
+) -> io::Result<()> {
+    match unsafe { SetHandleInformation(handle, mask, flags) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Duplicate a handle of the current process into another process,
+/// with the same access rights. The returned handle is only valid
+/// in `target_process`
+pub fn duplicate_handle(
+    handle: HANDLE,
+    target_process: HANDLE,
+    inherit_handle: bool,
+) -> io::Result<HANDLE> {
+    let mut target_handle = ptr::null_mut();
+
+    match unsafe {
+        DuplicateHandle(
+            GetCurrentProcess(),
+            handle,
+            target_process,
+            &mut target_handle,
+            0,
+            inherit_handle as _,
+            DUPLICATE_SAME_ACCESS,
+        )
+    } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(target_handle),
+    }
+}
+
 pub fn create_file(
     file_name: &[WCHAR],
     desired_access: DWORD,
     share_mode: DWORD,
+    inherit_handle: bool,
     creation_disposition: DWORD,
     flags_and_attributes: DWORD,
 ) -> io::Result<HANDLE> {
+    let mut security_attributes = SECURITY_ATTRIBUTES {
+        nLength: mem::size_of::<SECURITY_ATTRIBUTES>() as _,
+        lpSecurityDescriptor: ptr::null_mut(),
+        bInheritHandle: inherit_handle as _,
+    };
+
     match unsafe {
         CreateFileW(
             file_name.as_ptr(),
             desired_access,
             share_mode,
-            ptr::null_mut(),
+            &mut security_attributes,
             creation_disposition,
             flags_and_attributes,
             ptr::null_mut(),
@@ -121,37 +253,169 @@ pub fn create_file(
     }
 }
 
-pub fn read_file(handle: HANDLE, buffer: &mut [u8]) -> io::Result<DWORD> {
-    let mut ret = 0;
-
+/// Start an overlapped read, completion is reported through
+/// `overlapped`, that together with `buffer` must stay alive and
+/// untouched until the operation completes
+pub fn read_file_overlapped(
+    handle: HANDLE,
+    buffer: &mut [u8],
+    overlapped: &mut OVERLAPPED,
+) -> io::Result<()> {
     match unsafe {
         ReadFile(
             handle,
             buffer.as_mut_ptr() as _,
             buffer.len() as _,
-            &mut ret,
             ptr::null_mut(),
+            overlapped,
         )
     } {
-        0 => Err(io::Error::last_os_error()),
-        _ => Ok(ret as _),
+        0 => match io::Error::last_os_error() {
+            err if err.raw_os_error() == Some(ERROR_IO_PENDING as _) => Ok(()),
+            err => Err(err),
+        },
+        _ => Ok(()),
     }
 }
 
-pub fn write_file(handle: HANDLE, buffer: &[u8]) -> io::Result<DWORD> {
-    let mut ret = 0;
-
+/// Start an overlapped write, same rules as `read_file_overlapped`
+pub fn write_file_overlapped(
+    handle: HANDLE,
+    buffer: &[u8],
+    overlapped: &mut OVERLAPPED,
+) -> io::Result<()> {
     match unsafe {
         WriteFile(
             handle,
             buffer.as_ptr() as _,
             buffer.len() as _,
-            &mut ret,
             ptr::null_mut(),
+            overlapped,
         )
     } {
+        0 => match io::Error::last_os_error() {
+            err if err.raw_os_error() == Some(ERROR_IO_PENDING as _) => Ok(()),
+            err => Err(err),
+        },
+        _ => Ok(()),
+    }
+}
+
+pub fn get_overlapped_result(
+    handle: HANDLE,
+    overlapped: &mut OVERLAPPED,
+    wait: bool,
+) -> io::Result<DWORD> {
+    let mut ret = 0;
+
+    match unsafe {
+        GetOverlappedResult(handle, overlapped, &mut ret, wait as _)
+    } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(ret),
+    }
+}
+
+pub fn cancel_io_ex(
+    handle: HANDLE,
+    overlapped: &mut OVERLAPPED,
+) -> io::Result<()> {
+    match unsafe { CancelIoEx(handle, overlapped) } {
         0 => Err(io::Error::last_os_error()),
-        _ => Ok(ret as _),
+        _ => Ok(()),
+    }
+}
+
+/// Cancel every pending operation on the handle, from any thread
+pub fn cancel_all_io(handle: HANDLE) -> io::Result<()> {
+    match unsafe { CancelIoEx(handle, ptr::null_mut()) } {
+        0 => match io::Error::last_os_error() {
+            // Nothing was pending
+            err if err.raw_os_error() == Some(ERROR_NOT_FOUND as _) => Ok(()),
+            err => Err(err),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Read from an overlapped handle, waiting for completion
+pub fn read_file(
+    handle: HANDLE,
+    buffer: &mut [u8],
+    overlapped: &mut OVERLAPPED,
+) -> io::Result<DWORD> {
+    read_file_overlapped(handle, buffer, overlapped)?;
+    get_overlapped_result(handle, overlapped, true)
+}
+
+/// Write to an overlapped handle, waiting for completion
+pub fn write_file(
+    handle: HANDLE,
+    buffer: &[u8],
+    overlapped: &mut OVERLAPPED,
+) -> io::Result<DWORD> {
+    write_file_overlapped(handle, buffer, overlapped)?;
+    get_overlapped_result(handle, overlapped, true)
+}
+
+/// Create a manual reset event, initially not signaled
+pub fn create_event() -> io::Result<HANDLE> {
+    match unsafe { CreateEventW(ptr::null_mut(), TRUE, FALSE, ptr::null()) } {
+        handle if handle.is_null() => Err(io::Error::last_os_error()),
+        handle => Ok(handle),
+    }
+}
+
+pub fn set_event(event: HANDLE) -> io::Result<()> {
+    match unsafe { SetEvent(event) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+pub fn reset_event(event: HANDLE) -> io::Result<()> {
+    match unsafe { ResetEvent(event) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Create a named mutex, not owned by anyone, also returning
+/// whether it already existed
+pub fn create_mutex(name: &[WCHAR]) -> io::Result<(HANDLE, bool)> {
+    match unsafe { CreateMutexW(ptr::null_mut(), FALSE, name.as_ptr()) } {
+        handle if handle.is_null() => Err(io::Error::last_os_error()),
+        handle => {
+            Ok((handle, unsafe { GetLastError() } == ERROR_ALREADY_EXISTS))
+        }
+    }
+}
+
+/// Open an existing named mutex
+pub fn open_mutex(name: &[WCHAR]) -> io::Result<HANDLE> {
+    match unsafe { OpenMutexW(SYNCHRONIZE, FALSE, name.as_ptr()) } {
+        handle if handle.is_null() => Err(io::Error::last_os_error()),
+        handle => Ok(handle),
+    }
+}
+
+/// Wait until one of `handles` is signaled, returning its index,
+/// or `None` on timeout
+pub fn wait_for_multiple_objects(
+    handles: &[HANDLE],
+    milliseconds: DWORD,
+) -> io::Result<Option<usize>> {
+    match unsafe {
+        WaitForMultipleObjects(
+            handles.len() as _,
+            handles.as_ptr(),
+            FALSE,
+            milliseconds,
+        )
+    } {
+        WAIT_TIMEOUT => Ok(None),
+        index if (index as usize) < handles.len() => Ok(Some(index as _)),
+        _ => Err(io::Error::last_os_error()),
     }
 }
 
@@ -275,6 +539,26 @@ pub fn get_device_registry_property(
     }
 }
 
+pub fn get_device_instance_id(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+) -> io::Result<Vec<WCHAR>> {
+    let mut instance_id = vec![0; MAX_DEVICE_ID_LEN];
+
+    match unsafe {
+        SetupDiGetDeviceInstanceIdW(
+            devinfo,
+            devinfo_data as *const _ as _,
+            instance_id.as_mut_ptr(),
+            instance_id.len() as _,
+            ptr::null_mut(),
+        )
+    } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(instance_id),
+    }
+}
+
 pub fn build_driver_info_list(
     devinfo: HDEVINFO,
     devinfo_data: &SP_DEVINFO_DATA,
@@ -410,25 +694,44 @@ pub fn open_dev_reg_key(
     }
 }
 
+pub fn create_dev_reg_key(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    scope: DWORD,
+    hw_profile: DWORD,
+    key_type: DWORD,
+) -> io::Result<HKEY> {
+    const INVALID_KEY_VALUE: HKEY = INVALID_HANDLE_VALUE as _;
+
+    match unsafe {
+        SetupDiCreateDevRegKeyW(
+            devinfo,
+            devinfo_data as *const _ as _,
+            scope,
+            hw_profile,
+            key_type,
+            ptr::null_mut(),
+            ptr::null(),
+        )
+    } {
+        INVALID_KEY_VALUE => Err(io::Error::last_os_error()),
+        key => Ok(key),
+    }
+}
+
 pub fn notify_change_key_value(
     key: HKEY,
     watch_subtree: BOOL,
     notify_filter: DWORD,
     milliseconds: DWORD,
 ) -> io::Result<()> {
-    let event = match unsafe {
-        CreateEventW(ptr::null_mut(), FALSE, FALSE, ptr::null())
-    } {
-        INVALID_HANDLE_VALUE => Err(io::Error::last_os_error()),
-        event => Ok(event),
-    }?;
+    let event = create_event()?;
 
-    match unsafe {
-        RegNotifyChangeKeyValue(key, watch_subtree, notify_filter, event, TRUE)
-    } {
-        0 => Ok(()),
-        err => Err(io::Error::from_raw_os_error(err)),
-    }?;
+    let _guard = scopeguard::guard((), |_| {
+        let _ = close_handle(event);
+    });
+
+    reg_notify_change_key_value(key, watch_subtree, notify_filter, event)?;
 
     match unsafe { WaitForSingleObject(event, milliseconds) } {
         0 => Ok(()),
@@ -440,6 +743,126 @@ pub fn notify_change_key_value(
     }
 }
 
+/// Ask for `event` to be signaled on the next change to `key`, the
+/// calling thread must stay alive until then
+pub fn reg_notify_change_key_value(
+    key: HKEY,
+    watch_subtree: BOOL,
+    notify_filter: DWORD,
+    event: HANDLE,
+) -> io::Result<()> {
+    match unsafe {
+        RegNotifyChangeKeyValue(key, watch_subtree, notify_filter, event, TRUE)
+    } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+pub fn reg_open_key_ex(
+    key: HKEY,
+    subkey: &[WCHAR],
+    sam_desired: REGSAM,
+) -> io::Result<HKEY> {
+    let mut result = ptr::null_mut();
+
+    match unsafe {
+        RegOpenKeyExW(key, subkey.as_ptr(), 0, sam_desired, &mut result)
+    } {
+        0 => Ok(result),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+pub fn reg_close_key(key: HKEY) -> io::Result<()> {
+    match unsafe { RegCloseKey(key) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+/// Read a value, returning its type and raw data
+pub fn reg_query_value_ex(
+    key: HKEY,
+    name: &[WCHAR],
+) -> io::Result<(DWORD, Vec<u8>)> {
+    let mut data = vec![0; 64];
+
+    loop {
+        let mut value_type = 0;
+        let mut len = data.len() as DWORD;
+
+        match unsafe {
+            RegQueryValueExW(
+                key,
+                name.as_ptr(),
+                ptr::null_mut(),
+                &mut value_type,
+                data.as_mut_ptr(),
+                &mut len,
+            )
+        } {
+            0 => {
+                data.truncate(len as _);
+                return Ok((value_type, data));
+            }
+            // The value might grow between calls, so keep trying
+            err if err as DWORD == ERROR_MORE_DATA => data.resize(len as _, 0),
+            err => return Err(io::Error::from_raw_os_error(err)),
+        }
+    }
+}
+
+pub fn reg_set_value_ex(
+    key: HKEY,
+    name: &[WCHAR],
+    value_type: DWORD,
+    data: &[u8],
+) -> io::Result<()> {
+    match unsafe {
+        RegSetValueExW(
+            key,
+            name.as_ptr(),
+            0,
+            value_type,
+            data.as_ptr(),
+            data.len() as _,
+        )
+    } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+pub fn reg_enum_key_ex(
+    key: HKEY,
+    index: DWORD,
+) -> Option<io::Result<Vec<WCHAR>>> {
+    // Key names are limited to 255 characters
+    let mut name = vec![0; 256];
+    let mut len = name.len() as DWORD;
+
+    match unsafe {
+        RegEnumKeyExW(
+            key,
+            index,
+            name.as_mut_ptr(),
+            &mut len,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    } {
+        0 => {
+            name.truncate(len as _);
+            Some(Ok(name))
+        }
+        err if err as DWORD == ERROR_NO_MORE_ITEMS => None,
+        err => Some(Err(io::Error::from_raw_os_error(err))),
+    }
+}
+
 pub fn enum_driver_info(
     devinfo: HDEVINFO,
     devinfo_data: &SP_DEVINFO_DATA,
@@ -485,10 +908,9 @@ pub fn device_io_control(
     io_control_code: DWORD,
     in_buffer: &impl Copy,
     out_buffer: &mut impl Copy,
+    overlapped: &mut OVERLAPPED,
 ) -> io::Result<()> {
-    let mut junk = 0;
-
-    match unsafe {
+    let res = match unsafe {
         DeviceIoControl(
             handle,
             io_control_code,
@@ -496,11 +918,547 @@ pub fn device_io_control(
             mem::size_of_val(in_buffer) as _,
             out_buffer as *mut _ as _,
             mem::size_of_val(out_buffer) as _,
-            &mut junk,
             ptr::null_mut(),
+            overlapped,
         )
+    } {
+        0 => match io::Error::last_os_error() {
+            err if err.raw_os_error() == Some(ERROR_IO_PENDING as _) => {
+                get_overlapped_result(handle, overlapped, true).map(|_| ())
+            }
+            err => Err(err),
+        },
+        _ => Ok(()),
+    };
+
+    trace!(io_control_code, result = ?res, "ioctl completed");
+
+    res
+}
+
+pub fn get_ip_interface_entry(
+    luid: &NET_LUID,
+    family: ADDRESS_FAMILY,
+) -> io::Result<MIB_IPINTERFACE_ROW> {
+    let mut row: MIB_IPINTERFACE_ROW = unsafe { mem::zeroed() };
+    unsafe { InitializeIpInterfaceEntry(&mut row) };
+
+    row.Family = family;
+    row.InterfaceLuid = *luid;
+
+    match unsafe { GetIpInterfaceEntry(&mut row) } {
+        0 => Ok(row),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+pub fn get_ip_interface_table(
+    family: ADDRESS_FAMILY,
+) -> io::Result<Vec<MIB_IPINTERFACE_ROW>> {
+    let mut table = ptr::null_mut();
+
+    match unsafe { GetIpInterfaceTable(family, &mut table) } {
+        0 => Ok(()),
+        ERROR_NOT_FOUND => return Ok(Vec::new()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }?;
+
+    let rows = unsafe {
+        std::slice::from_raw_parts(
+            (*table).Table.as_ptr(),
+            (*table).NumEntries as _,
+        )
+        .to_vec()
+    };
+
+    unsafe { FreeMibTable(table as _) };
+
+    Ok(rows)
+}
+
+pub fn set_ip_interface_entry(row: &MIB_IPINTERFACE_ROW) -> io::Result<()> {
+    let mut row = *row;
+
+    // SetIpInterfaceEntry rejects ipv4 rows with a site prefix length
+    if row.Family == AF_INET as ADDRESS_FAMILY {
+        row.SitePrefixLength = 0;
+    }
+
+    match unsafe { SetIpInterfaceEntry(&mut row) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+pub fn get_unicast_ip_address_table(
+    family: ADDRESS_FAMILY,
+) -> io::Result<Vec<MIB_UNICASTIPADDRESS_ROW>> {
+    let mut table = ptr::null_mut();
+
+    match unsafe { GetUnicastIpAddressTable(family, &mut table) } {
+        0 => Ok(()),
+        ERROR_NOT_FOUND => return Ok(Vec::new()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }?;
+
+    let rows = unsafe {
+        std::slice::from_raw_parts(
+            (*table).Table.as_ptr(),
+            (*table).NumEntries as _,
+        )
+        .to_vec()
+    };
+
+    unsafe { FreeMibTable(table as _) };
+
+    Ok(rows)
+}
+
+pub fn delete_unicast_ip_address_entry(
+    row: &MIB_UNICASTIPADDRESS_ROW,
+) -> io::Result<()> {
+    match unsafe { DeleteUnicastIpAddressEntry(row) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+/// Look up a function exported by a system library, used for
+/// functions missing from older versions of Windows. A library that
+/// is not loaded yet is loaded once and kept for the process lifetime,
+/// as the returned function must stay valid
+pub fn get_proc_address(module: &str, name: &str) -> Option<FARPROC> {
+    let module: Vec<WCHAR> =
+        module.encode_utf16().chain(std::iter::once(0)).collect();
+    let name: Vec<u8> = name.bytes().chain(std::iter::once(0)).collect();
+
+    let module = match unsafe { GetModuleHandleW(module.as_ptr()) } {
+        handle if handle.is_null() => unsafe { LoadLibraryW(module.as_ptr()) },
+        handle => handle,
+    };
+    if module.is_null() {
+        return None;
+    }
+
+    match unsafe { GetProcAddress(module, name.as_ptr() as _) } {
+        proc if proc.is_null() => None,
+        proc => Some(proc),
+    }
+}
+
+/// Returns None if SetInterfaceDnsSettings is not available,
+/// it was introduced in Windows 10 2004
+pub fn set_interface_dns_settings(
+    guid: &GUID,
+    settings: &DNS_INTERFACE_SETTINGS,
+) -> Option<io::Result<()>> {
+    type SetInterfaceDnsSettingsFn = unsafe extern "system" fn(
+        GUID,
+        *const DNS_INTERFACE_SETTINGS,
+    )
+        -> NETIO_STATUS;
+
+    let proc = get_proc_address("iphlpapi.dll", "SetInterfaceDnsSettings")?;
+    let proc: SetInterfaceDnsSettingsFn = unsafe { mem::transmute(proc) };
+
+    match unsafe { proc(*guid, settings) } {
+        0 => Some(Ok(())),
+        err => Some(Err(io::Error::from_raw_os_error(err as _))),
+    }
+}
+
+pub fn initialize_unicast_ip_address_entry() -> MIB_UNICASTIPADDRESS_ROW {
+    let mut row: MIB_UNICASTIPADDRESS_ROW = unsafe { mem::zeroed() };
+    unsafe { InitializeUnicastIpAddressEntry(&mut row) };
+    row
+}
+
+pub fn create_unicast_ip_address_entry(
+    row: &MIB_UNICASTIPADDRESS_ROW,
+) -> io::Result<()> {
+    match unsafe { CreateUnicastIpAddressEntry(row) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+pub fn sockaddr_inet_from_ip(address: &net::IpAddr) -> SOCKADDR_INET {
+    let mut sockaddr: SOCKADDR_INET = unsafe { mem::zeroed() };
+
+    match address {
+        net::IpAddr::V4(address) => unsafe {
+            let ipv4 = sockaddr.Ipv4_mut();
+            ipv4.sin_family = AF_INET as _;
+            *ipv4.sin_addr.S_un.S_addr_mut() =
+                u32::from_ne_bytes(address.octets());
+        },
+        net::IpAddr::V6(address) => unsafe {
+            let ipv6 = sockaddr.Ipv6_mut();
+            ipv6.sin6_family = AF_INET6 as _;
+            *ipv6.sin6_addr.u.Byte_mut() = address.octets();
+        },
+    }
+
+    sockaddr
+}
+
+pub fn sockaddr_inet_to_ip(sockaddr: &SOCKADDR_INET) -> Option<net::IpAddr> {
+    match unsafe { *sockaddr.si_family() } as INT {
+        AF_INET => {
+            let address = unsafe { *sockaddr.Ipv4().sin_addr.S_un.S_addr() };
+            Some(net::Ipv4Addr::from(address.to_ne_bytes()).into())
+        }
+        AF_INET6 => {
+            let address = unsafe { *sockaddr.Ipv6().sin6_addr.u.Byte() };
+            Some(net::Ipv6Addr::from(address).into())
+        }
+        _ => None,
+    }
+}
+
+pub fn get_if_entry2(luid: &NET_LUID) -> io::Result<MIB_IF_ROW2> {
+    let mut row: MIB_IF_ROW2 = unsafe { mem::zeroed() };
+    row.InterfaceLuid = *luid;
+
+    match unsafe { GetIfEntry2(&mut row) } {
+        0 => Ok(row),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+/// CM_Register_Notification was introduced in Windows 8
+pub fn register_notification(
+    filter: &CM_NOTIFY_FILTER,
+    context: PVOID,
+    callback: PCM_NOTIFY_CALLBACK,
+) -> io::Result<HCMNOTIFICATION> {
+    type RegisterNotificationFn = unsafe extern "system" fn(
+        *const CM_NOTIFY_FILTER,
+        PVOID,
+        PCM_NOTIFY_CALLBACK,
+        *mut HCMNOTIFICATION,
+    ) -> CONFIGRET;
+
+    let proc = get_proc_address("cfgmgr32.dll", "CM_Register_Notification")
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "CM_Register_Notification is not available",
+            )
+        })?;
+    let proc: RegisterNotificationFn = unsafe { mem::transmute(proc) };
+
+    let mut handle = ptr::null_mut();
+
+    match unsafe { proc(filter, context, callback, &mut handle) } {
+        CR_SUCCESS => Ok(handle),
+        err => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("CM_Register_Notification failed ({})", err),
+        )),
+    }
+}
+
+pub fn unregister_notification(handle: HCMNOTIFICATION) -> io::Result<()> {
+    type UnregisterNotificationFn =
+        unsafe extern "system" fn(HCMNOTIFICATION) -> CONFIGRET;
+
+    let proc = get_proc_address("cfgmgr32.dll", "CM_Unregister_Notification")
+        .ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "CM_Unregister_Notification is not available",
+        )
+    })?;
+    let proc: UnregisterNotificationFn = unsafe { mem::transmute(proc) };
+
+    match unsafe { proc(handle) } {
+        CR_SUCCESS => Ok(()),
+        err => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("CM_Unregister_Notification failed ({})", err),
+        )),
+    }
+}
+
+/// Undocumented, exported by iphlpapi since Windows 8
+pub fn get_interface_compartment_id(
+    luid: &NET_LUID,
+) -> io::Result<NET_IF_COMPARTMENT_ID> {
+    type GetInterfaceCompartmentIdFn =
+        unsafe extern "system" fn(*const NET_LUID) -> NET_IF_COMPARTMENT_ID;
+
+    let proc = get_proc_address("iphlpapi.dll", "GetInterfaceCompartmentId")
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "GetInterfaceCompartmentId is not available",
+            )
+        })?;
+    let proc: GetInterfaceCompartmentIdFn = unsafe { mem::transmute(proc) };
+
+    match unsafe { proc(luid) } {
+        NET_IF_COMPARTMENT_ID_UNSPECIFIED => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Interface has no compartment",
+        )),
+        id => Ok(id),
+    }
+}
+
+/// Undocumented, exported by iphlpapi since Windows 8
+pub fn set_interface_compartment_id(
+    luid: &NET_LUID,
+    id: NET_IF_COMPARTMENT_ID,
+) -> io::Result<()> {
+    type SetInterfaceCompartmentIdFn =
+        unsafe extern "system" fn(
+            *const NET_LUID,
+            NET_IF_COMPARTMENT_ID,
+        ) -> NETIO_STATUS;
+
+    let proc = get_proc_address("iphlpapi.dll", "SetInterfaceCompartmentId")
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "SetInterfaceCompartmentId is not available",
+            )
+        })?;
+    let proc: SetInterfaceCompartmentIdFn = unsafe { mem::transmute(proc) };
+
+    match unsafe { proc(luid, id) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+pub fn open_process_token(desired_access: DWORD) -> io::Result<HANDLE> {
+    let mut token = ptr::null_mut();
+
+    match unsafe {
+        OpenProcessToken(GetCurrentProcess(), desired_access, &mut token)
     } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(token),
+    }
+}
+
+pub fn get_token_elevation(token: HANDLE) -> io::Result<bool> {
+    let mut elevation: TOKEN_ELEVATION = unsafe { mem::zeroed() };
+    let mut size = 0;
+
+    match unsafe {
+        GetTokenInformation(
+            token,
+            TokenElevation,
+            &mut elevation as *mut _ as _,
+            mem::size_of_val(&elevation) as _,
+            &mut size,
+        )
+    } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(elevation.TokenIsElevated != 0),
+    }
+}
+
+pub fn get_native_system_info() -> SYSTEM_INFO {
+    let mut system_info = unsafe { mem::zeroed() };
+
+    unsafe { GetNativeSystemInfo(&mut system_info) };
+
+    system_info
+}
+
+pub fn notify_ip_interface_change(
+    family: ADDRESS_FAMILY,
+    callback: PIPINTERFACE_CHANGE_CALLBACK,
+    context: PVOID,
+) -> io::Result<HANDLE> {
+    let mut handle = ptr::null_mut();
+
+    match unsafe {
+        NotifyIpInterfaceChange(family, callback, context, 0, &mut handle)
+    } {
+        0 => Ok(handle),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+pub fn cancel_mib_change_notify2(handle: HANDLE) -> io::Result<()> {
+    match unsafe { CancelMibChangeNotify2(handle) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+pub fn initialize_ip_forward_entry() -> MIB_IPFORWARD_ROW2 {
+    let mut row: MIB_IPFORWARD_ROW2 = unsafe { mem::zeroed() };
+    unsafe { InitializeIpForwardEntry(&mut row) };
+    row
+}
+
+pub fn create_ip_forward_entry2(row: &MIB_IPFORWARD_ROW2) -> io::Result<()> {
+    match unsafe { CreateIpForwardEntry2(row) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+pub fn delete_ip_forward_entry2(row: &MIB_IPFORWARD_ROW2) -> io::Result<()> {
+    match unsafe { DeleteIpForwardEntry2(row) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }
+}
+
+pub fn get_ip_forward_table2(
+    family: ADDRESS_FAMILY,
+) -> io::Result<Vec<MIB_IPFORWARD_ROW2>> {
+    let mut table = ptr::null_mut();
+
+    match unsafe { GetIpForwardTable2(family, &mut table) } {
+        0 => Ok(()),
+        ERROR_NOT_FOUND => return Ok(Vec::new()),
+        err => Err(io::Error::from_raw_os_error(err as _)),
+    }?;
+
+    let rows = unsafe {
+        std::slice::from_raw_parts(
+            (*table).Table.as_ptr(),
+            (*table).NumEntries as _,
+        )
+        .to_vec()
+    };
+
+    unsafe { FreeMibTable(table as _) };
+
+    Ok(rows)
+}
+
+pub fn open_process(desired_access: DWORD, pid: DWORD) -> io::Result<HANDLE> {
+    match unsafe { OpenProcess(desired_access, FALSE, pid) } {
+        handle if handle.is_null() => Err(io::Error::last_os_error()),
+        handle => Ok(handle),
+    }
+}
+
+/// Create a byte mode named pipe instance that only accepts local
+/// clients, access is controlled by the `sddl` security descriptor.
+/// When `first` is set, fails if a pipe with that name already exists
+pub fn create_named_pipe(
+    name: &[WCHAR],
+    sddl: &[WCHAR],
+    first: bool,
+) -> io::Result<HANDLE> {
+    let mut descriptor = ptr::null_mut();
+
+    match unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl.as_ptr(),
+            SDDL_REVISION_1 as _,
+            &mut descriptor,
+            ptr::null_mut(),
+        )
+    } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }?;
+
+    let mut security_attributes = SECURITY_ATTRIBUTES {
+        nLength: mem::size_of::<SECURITY_ATTRIBUTES>() as _,
+        lpSecurityDescriptor: descriptor,
+        bInheritHandle: FALSE,
+    };
+
+    let open_mode = if first {
+        PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE
+    } else {
+        PIPE_ACCESS_DUPLEX
+    };
+
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            open_mode,
+            PIPE_TYPE_BYTE
+                | PIPE_READMODE_BYTE
+                | PIPE_WAIT
+                | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            4096,
+            4096,
+            0,
+            &mut security_attributes,
+        )
+    };
+    let err = io::Error::last_os_error();
+
+    unsafe { LocalFree(descriptor) };
+
+    match handle {
+        INVALID_HANDLE_VALUE => Err(err),
+        handle => Ok(handle),
+    }
+}
+
+/// Wait for a client to connect to a pipe instance
+pub fn connect_named_pipe(pipe: HANDLE) -> io::Result<()> {
+    match unsafe { ConnectNamedPipe(pipe, ptr::null_mut()) } {
+        0 => match io::Error::last_os_error() {
+            // The client connected before the call
+            err if err.raw_os_error() == Some(ERROR_PIPE_CONNECTED as _) => {
+                Ok(())
+            }
+            err => Err(err),
+        },
+        _ => Ok(()),
+    }
+}
+
+pub fn disconnect_named_pipe(pipe: HANDLE) -> io::Result<()> {
+    match unsafe { DisconnectNamedPipe(pipe) } {
         0 => Err(io::Error::last_os_error()),
         _ => Ok(()),
     }
 }
+
+pub fn get_named_pipe_client_process_id(pipe: HANDLE) -> io::Result<DWORD> {
+    let mut pid = 0;
+
+    match unsafe { GetNamedPipeClientProcessId(pipe, &mut pid) } {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok(pid),
+    }
+}
+
+/// Check if a kernel object is owned by SYSTEM or the administrators
+/// group, which only elevated processes can set as owner
+pub fn is_owned_by_admin(handle: HANDLE) -> io::Result<bool> {
+    let mut owner = ptr::null_mut();
+    let mut descriptor = ptr::null_mut();
+
+    match unsafe {
+        GetSecurityInfo(
+            handle,
+            SE_KERNEL_OBJECT,
+            OWNER_SECURITY_INFORMATION,
+            &mut owner,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut descriptor,
+        )
+    } {
+        ERROR_SUCCESS => {}
+        err => return Err(io::Error::from_raw_os_error(err as _)),
+    }
+
+    let trusted = unsafe {
+        IsWellKnownSid(owner, WinLocalSystemSid) != 0
+            || IsWellKnownSid(owner, WinBuiltinAdministratorsSid) != 0
+    };
+
+    unsafe { LocalFree(descriptor) };
+
+    Ok(trusted)
+}
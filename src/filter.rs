@@ -0,0 +1,78 @@
+use std::borrow::Cow;
+
+use crate::Device;
+
+/// What to do with a frame, returned by the filters set with
+/// `Device::set_read_filter` and `Device::set_write_filter`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FilterAction {
+    /// Let the frame through unchanged
+    Pass,
+    /// Silently discard the frame
+    Drop,
+    /// Let this frame through in place of the original one
+    Replace(Vec<u8>),
+}
+
+/// Frame filter, see `Device::set_read_filter`
+pub type FrameFilter = dyn FnMut(&[u8]) -> FilterAction + Send;
+
+impl Device {
+    /// Run every frame read from the device through `filter`
+    /// before handing it out, dropped frames are skipped and the
+    /// read waits for the next one. Replaced frames longer than the
+    /// read buffer are truncated.
+    /// Applies to `read`, `read_frame`, `ReadRing` and `DeviceSet`
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, FilterAction};
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// // Drop everything but ipv4 and arp
+    /// dev.set_read_filter(|frame| match frame.get(12..14) {
+    ///     Some([0x08, 0x00]) | Some([0x08, 0x06]) => FilterAction::Pass,
+    ///     _ => FilterAction::Drop,
+    /// });
+    /// ```
+    pub fn set_read_filter<F>(&mut self, filter: F)
+    where
+        F: FnMut(&[u8]) -> FilterAction + Send + 'static,
+    {
+        *self.read_filter.get_mut() = Some(Box::new(filter));
+    }
+
+    /// Run every frame written to the device through `filter`,
+    /// dropped frames are reported as written.
+    /// Applies to `write`, `write_many` and `write_with_callback`
+    pub fn set_write_filter<F>(&mut self, filter: F)
+    where
+        F: FnMut(&[u8]) -> FilterAction + Send + 'static,
+    {
+        *self.write_filter.get_mut() = Some(Box::new(filter));
+    }
+
+    /// Remove both the read and the write filter
+    pub fn clear_filters(&mut self) {
+        *self.read_filter.get_mut() = None;
+        *self.write_filter.get_mut() = None;
+    }
+}
+
+/// Run a frame through an optional filter, `None` if it was dropped
+pub(crate) fn apply<'a>(
+    filter: &mut Option<Box<FrameFilter>>,
+    frame: &'a [u8],
+) -> Option<Cow<'a, [u8]>> {
+    let filter = match filter {
+        Some(filter) => filter,
+        None => return Some(Cow::Borrowed(frame)),
+    };
+
+    match filter(frame) {
+        FilterAction::Pass => Some(Cow::Borrowed(frame)),
+        FilterAction::Drop => None,
+        FilterAction::Replace(frame) => Some(Cow::Owned(frame)),
+    }
+}
@@ -0,0 +1,127 @@
+use etherparse::{EtherType, Ethernet2Header, SlicedPacket};
+
+use std::io::{self, Read, Write};
+use std::mem;
+
+use crate::{Device, MacAddress};
+
+/// Ethernet header plus a vlan tag
+const MAX_HEADER_LEN: usize = 18;
+
+/// Ethernet frame read with `Device::read_frame`, borrowing the
+/// device's frame buffer
+#[derive(Clone, Debug)]
+pub struct Frame<'a> {
+    /// Destination mac address
+    pub destination: MacAddress,
+    /// Source mac address
+    pub source: MacAddress,
+    /// Ether type of the payload
+    pub ether_type: EtherType,
+    /// Everything after the ethernet header
+    pub payload: &'a [u8],
+    /// Further parsed vlan, ip and transport headers
+    pub packet: SlicedPacket<'a>,
+}
+
+/// Ethernet frame to send with `Device::write_frame`
+#[derive(Clone, Copy, Debug)]
+pub struct FrameBuilder<'a> {
+    /// Destination mac address
+    pub destination: MacAddress,
+    /// Source mac address
+    pub source: MacAddress,
+    /// Ether type of the payload
+    pub ether_type: EtherType,
+    /// Everything after the ethernet header
+    pub payload: &'a [u8],
+}
+
+impl Device {
+    /// Read a single frame and parse its headers
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use etherparse::EtherType;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let frame = dev.read_frame().expect("Failed to read frame");
+    /// if frame.ether_type == EtherType::IPV4 {
+    ///     println!("{} -> {}", frame.source, frame.destination);
+    /// }
+    /// ```
+    pub fn read_frame(&mut self) -> io::Result<Frame<'_>> {
+        let mut buffer = self.take_frame_buffer();
+        buffer.resize(buffer.capacity(), 0);
+
+        let res = self.read(&mut buffer);
+        self.frame_buffer = buffer;
+
+        parse_frame(&self.frame_buffer[..res?])
+    }
+
+    /// Build a frame and write it
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, FrameBuilder, MacAddress};
+    /// use etherparse::EtherType;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// # let payload = [0u8; 46];
+    /// dev.write_frame(&FrameBuilder {
+    ///     destination: MacAddress([0xff; 6]),
+    ///     source: dev.get_mac().expect("Failed to get mac"),
+    ///     ether_type: EtherType::IPV4,
+    ///     payload: &payload,
+    /// })
+    /// .expect("Failed to write frame");
+    /// ```
+    pub fn write_frame(&mut self, frame: &FrameBuilder) -> io::Result<()> {
+        let header = Ethernet2Header {
+            destination: frame.destination.octets(),
+            source: frame.source.octets(),
+            ether_type: frame.ether_type,
+        };
+
+        let mut buffer = self.take_frame_buffer();
+        buffer.clear();
+        buffer.extend_from_slice(&header.to_bytes());
+        buffer.extend_from_slice(frame.payload);
+
+        let res = self.write(&buffer);
+        self.frame_buffer = buffer;
+
+        res.map(|_| ())
+    }
+
+    /// Take the frame buffer, with room for the largest frame
+    fn take_frame_buffer(&mut self) -> Vec<u8> {
+        let mut buffer = mem::take(&mut self.frame_buffer);
+
+        if buffer.capacity() == 0 {
+            let mtu = self.get_mtu().unwrap_or(1500) as usize;
+            buffer.reserve_exact(mtu + MAX_HEADER_LEN);
+        }
+
+        buffer
+    }
+}
+
+fn parse_frame(data: &[u8]) -> io::Result<Frame<'_>> {
+    let (header, payload) = Ethernet2Header::from_slice(data)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let packet = SlicedPacket::from_ethernet(data)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    Ok(Frame {
+        destination: MacAddress(header.destination),
+        source: MacAddress(header.source),
+        ether_type: header.ether_type,
+        payload,
+        packet,
+    })
+}
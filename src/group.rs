@@ -0,0 +1,141 @@
+use std::iter::FromIterator;
+use std::{error, fmt, io, thread};
+
+use crate::{Device, DeviceConfig};
+
+/// Errors of the devices that failed during `DeviceGroup::apply`,
+/// each along with the index of the device in the group
+#[derive(Debug)]
+pub struct GroupError {
+    /// Failed devices, ordered by index
+    pub errors: Vec<(usize, io::Error)>,
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Failed to configure {} device(s)", self.errors.len())?;
+
+        for (index, err) in &self.errors {
+            write!(f, "; device {}: {}", index, err)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl error::Error for GroupError {}
+
+impl From<GroupError> for io::Error {
+    fn from(err: GroupError) -> Self {
+        io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
+/// A set of devices configured together, for gateways bringing up
+/// many tunnels at once
+/// Example
+/// ```no_run
+/// use tap_windows::{Device, DeviceConfig, DeviceGroup};
+///
+/// let group: DeviceGroup = (0..16)
+///     .map(|i| Device::open(&format!("Tunnel {}", i)))
+///     .collect::<Result<_, _>>()
+///     .expect("Failed to open devices");
+///
+/// let config = DeviceConfig {
+///     mtu: Some(1400),
+///     up: Some(true),
+///     ..Default::default()
+/// };
+///
+/// if let Err(err) = group.apply(&config) {
+///     for (index, err) in &err.errors {
+///         println!("{}: {}", group.devices()[*index], err);
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct DeviceGroup {
+    devices: Vec<Device>,
+}
+
+impl DeviceGroup {
+    /// Create an empty group
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a device to the group, returns its index
+    pub fn push(&mut self, dev: Device) -> usize {
+        self.devices.push(dev);
+        self.devices.len() - 1
+    }
+
+    /// Get the devices of the group
+    pub fn devices(&self) -> &[Device] {
+        &self.devices
+    }
+
+    /// Get mutable access to the devices of the group
+    pub fn devices_mut(&mut self) -> &mut [Device] {
+        &mut self.devices
+    }
+
+    /// Give back the devices of the group
+    pub fn into_inner(self) -> Vec<Device> {
+        self.devices
+    }
+
+    /// Apply a configuration to every device, in parallel with a
+    /// thread per device, see `Device::apply`. All the devices are
+    /// configured even if some fail, the errors are collected.
+    /// A name can only be given to one adapter, so `config.name`
+    /// should be left to `None`
+    pub fn apply(&self, config: &DeviceConfig) -> Result<(), GroupError> {
+        let results: Vec<io::Result<()>> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .devices
+                .iter()
+                .map(|dev| scope.spawn(move || dev.apply(config)))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or_else(|_| {
+                        Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            "Panicked while applying configuration",
+                        ))
+                    })
+                })
+                .collect()
+        });
+
+        let errors: Vec<_> = results
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, res)| res.err().map(|err| (index, err)))
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(GroupError { errors })
+        }
+    }
+}
+
+impl From<Vec<Device>> for DeviceGroup {
+    fn from(devices: Vec<Device>) -> Self {
+        Self { devices }
+    }
+}
+
+impl FromIterator<Device> for DeviceGroup {
+    fn from_iter<I: IntoIterator<Item = Device>>(iter: I) -> Self {
+        Self {
+            devices: iter.into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use crate::Device;
+
+/// Observer of the external commands (netsh, powershell) run by the
+/// crate, set with `Device::set_command_hook`.
+/// Both methods are called on the thread running the command
+pub trait CommandHook: Send + Sync {
+    /// Called before running a command, returning an error vetoes
+    /// it, the error is then returned by the operation that needed
+    /// the command
+    fn before(&self, _program: &str, _args: &[&str]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once a command that was not vetoed has run, with the
+    /// outcome of the command
+    fn after(
+        &self,
+        _program: &str,
+        _args: &[&str],
+        _result: Result<(), &io::Error>,
+    ) {
+    }
+}
+
+static COMMAND_HOOK: Mutex<Option<Arc<dyn CommandHook>>> = Mutex::new(None);
+
+/// Run `command` between the calls to the hook, if any
+pub(crate) fn run<T, F>(
+    program: &str,
+    args: &[&str],
+    command: F,
+) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T>,
+{
+    // Not held while calling out, the hook may run commands itself
+    let hook = COMMAND_HOOK
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+
+    let hook = match hook {
+        Some(hook) => hook,
+        None => return command(),
+    };
+
+    match hook.before(program, args) {
+        Ok(()) => {}
+        Err(err) => {
+            debug!(program, ?args, error = %err, "command vetoed by hook");
+            return Err(err);
+        }
+    }
+
+    let res = command();
+    hook.after(program, args, res.as_ref().map(|_| ()));
+
+    res
+}
+
+impl Device {
+    /// Set a hook observing every external command run by the crate
+    /// in this process, to audit, log or veto them. `None` removes
+    /// the hook
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{CommandHook, Device};
+    /// use std::io;
+    /// use std::sync::Arc;
+    ///
+    /// struct DenyPowershell;
+    ///
+    /// impl CommandHook for DenyPowershell {
+    ///     fn before(&self, program: &str, args: &[&str]) -> io::Result<()> {
+    ///         println!("Running {} {:?}", program, args);
+    ///
+    ///         match program {
+    ///             "powershell" => Err(io::Error::new(
+    ///                 io::ErrorKind::PermissionDenied,
+    ///                 "Powershell is not allowed",
+    ///             )),
+    ///             _ => Ok(()),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// Device::set_command_hook(Some(Arc::new(DenyPowershell)));
+    /// ```
+    pub fn set_command_hook(hook: Option<Arc<dyn CommandHook>>) {
+        *COMMAND_HOOK.lock().unwrap_or_else(|err| err.into_inner()) = hook;
+    }
+}
@@ -0,0 +1,120 @@
+use std::net::Ipv4Addr;
+
+use crate::packet::*;
+use crate::MacAddress;
+
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+
+const ECHO_REQUEST: u8 = 8;
+const ECHO_REPLY: u8 = 0;
+
+/// Answers arp requests and pings for a "virtual gateway" address
+/// on the adapter, so connectivity checks and Windows' gateway
+/// probing succeed without a host behind the address.
+/// Built with the `icmp` feature
+/// Example
+/// ```no_run
+/// use tap_windows::{Device, IcmpResponder, MacAddress};
+/// use std::io::{Read, Write};
+///
+/// let mut dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// let gateway = IcmpResponder::new(
+///     [10, 8, 0, 1].into(),
+///     MacAddress([0x02, 0, 0, 0, 0, 1]),
+/// );
+///
+/// let mut buf = [0; 1600];
+/// loop {
+///     let amt = dev.read(&mut buf).expect("Failed to read packet");
+///
+///     if let Some(reply) = gateway.handle_frame(&buf[..amt]) {
+///         dev.write(&reply).expect("Failed to write packet");
+///         continue;
+///     }
+///
+///     // Handle the other frames
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IcmpResponder {
+    address: Ipv4Addr,
+    mac: MacAddress,
+}
+
+impl IcmpResponder {
+    /// Answer for `address`, using `mac` as its hardware address
+    pub fn new(address: Ipv4Addr, mac: MacAddress) -> Self {
+        Self { address, mac }
+    }
+
+    /// Handle a frame read from the adapter, returns the reply to
+    /// write back if it was an arp request or an echo request for
+    /// the gateway address
+    pub fn handle_frame(&self, frame: &[u8]) -> Option<Vec<u8>> {
+        match ether_type(frame)? {
+            ETHERTYPE_ARP => self.handle_arp(frame),
+            ETHERTYPE_IPV4 => self.handle_icmp(frame),
+            _ => None,
+        }
+    }
+
+    fn handle_arp(&self, frame: &[u8]) -> Option<Vec<u8>> {
+        let arp = frame.get(ETHER_HEADER_LEN..ETHER_HEADER_LEN + 28)?;
+
+        // Ethernet and ipv4 only
+        if arp[..6] != [0, 1, 8, 0, 6, 4]
+            || read_u16(arp, 6) != ARP_REQUEST
+            || arp[24..28] != self.address.octets()
+        {
+            return None;
+        }
+
+        let mut reply = Vec::with_capacity(ETHER_HEADER_LEN + 28);
+        reply.extend_from_slice(&arp[8..14]);
+        reply.extend_from_slice(&self.mac.octets());
+        reply.extend_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+
+        reply.extend_from_slice(&arp[..6]);
+        reply.extend_from_slice(&ARP_REPLY.to_be_bytes());
+        reply.extend_from_slice(&self.mac.octets());
+        reply.extend_from_slice(&self.address.octets());
+        // The requester becomes the target
+        reply.extend_from_slice(&arp[8..18]);
+
+        Some(reply)
+    }
+
+    fn handle_icmp(&self, frame: &[u8]) -> Option<Vec<u8>> {
+        let ip = parse_ipv4(frame)?;
+        if ip.protocol(frame) != IPPROTO_ICMP
+            || ip.destination(frame) != self.address
+            || ip.is_later_fragment(frame)
+        {
+            return None;
+        }
+
+        let icmp = frame.get(ip.payload..ip.end)?;
+        if icmp.len() < 8 || icmp[0] != ECHO_REQUEST || icmp[1] != 0 {
+            return None;
+        }
+
+        // Same identifier, sequence number and data
+        let mut reply = icmp.to_vec();
+        reply[0] = ECHO_REPLY;
+        write_u16(&mut reply, 2, 0);
+        let sum = checksum(&reply);
+        write_u16(&mut reply, 2, sum);
+
+        Some(build_ipv4_frame(
+            source_mac(frame),
+            self.mac,
+            self.address,
+            ip.source(frame),
+            IPPROTO_ICMP,
+            &reply,
+        ))
+    }
+}
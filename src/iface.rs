@@ -3,17 +3,17 @@ use winapi::shared::minwindef::*;
 
 use winapi::um::fileapi::*;
 use winapi::um::setupapi::*;
+use winapi::um::winbase::FILE_FLAG_OVERLAPPED;
 use winapi::um::winnt::*;
+use winapi::um::winreg::{HKEY_LOCAL_MACHINE, REGSAM};
 
 use scopeguard::{guard, ScopeGuard};
-use winreg::RegKey;
 
-use std::io;
+use std::sync::Mutex;
+use std::{io, mem, thread, time};
 
-use crate::{decode_utf16, encode_utf16, ffi};
-
-/// tap-windows hardware ID
-const HARDWARE_ID: &str = "tap0901";
+use crate::registry::RegKey;
+use crate::{decode_utf16, encode_utf16, ffi, legacy, CreateOptions, OwnerTag};
 
 winapi::DEFINE_GUID! {
     GUID_NETWORK_ADAPTER,
@@ -21,8 +21,39 @@ winapi::DEFINE_GUID! {
     0xbf, 0xc1, 0x08, 0x00, 0x2b, 0xe1, 0x03, 0x18
 }
 
-/// Create a new interface and returns its NET_LUID
-pub fn create_interface() -> io::Result<NET_LUID> {
+/// Registry key holding the driver keys of network adapters
+const NETWORK_CLASS_KEY: &str = concat!(
+    r"SYSTEM\CurrentControlSet\Control\Class\",
+    "{4D36E972-E325-11CE-BFC1-08002BE10318}"
+);
+
+/// `*NdisDeviceType` of adapters that are not a network connection
+const NDIS_DEVICE_TYPE_ENDPOINT: DWORD = 1;
+
+/// Upper bound of the backoff used while waiting for an interface
+const MAX_POLL_INTERVAL: time::Duration = time::Duration::from_millis(250);
+
+/// Device paths tried when opening an interface, in order. Some
+/// Terminal Server sessions and sandboxes can't use the global
+/// namespace
+const PATH_TEMPLATES: [&str; 2] = [r"\\.\Global\{guid}.tap", r"\\.\{guid}.tap"];
+
+/// Custom path tried before `PATH_TEMPLATES`, see
+/// `Device::set_path_template`
+static PATH_TEMPLATE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Registry key holding the connection settings of network adapters
+const NETWORK_CONNECTIONS_KEY: &str = concat!(
+    r"SYSTEM\CurrentControlSet\Control\Network\",
+    "{4D36E972-E325-11CE-BFC1-08002BE10318}"
+);
+
+/// Create a new interface and returns its NET_LUID, the properties
+/// in `options` are applied before the device is first started.
+/// The name is not handled here, as the interface has no alias yet
+pub fn create_interface(options: &CreateOptions) -> io::Result<NET_LUID> {
+    let component_id = options.component_id;
+
     let devinfo = ffi::create_device_info_list(&GUID_NETWORK_ADAPTER)?;
 
     let _guard = guard((), |_| {
@@ -44,9 +75,18 @@ pub fn create_interface() -> io::Result<NET_LUID> {
         devinfo,
         &devinfo_data,
         SPDRP_HARDWAREID,
-        &encode_utf16(HARDWARE_ID),
+        &encode_utf16(component_id),
     )?;
 
+    if let Some(description) = options.description {
+        ffi::set_device_registry_property(
+            devinfo,
+            &devinfo_data,
+            SPDRP_FRIENDLYNAME,
+            &encode_utf16(description),
+        )?;
+    }
+
     ffi::build_driver_info_list(devinfo, &devinfo_data, SPDIT_COMPATDRIVER)?;
 
     let _guard = guard((), |_| {
@@ -59,6 +99,7 @@ pub fn create_interface() -> io::Result<NET_LUID> {
 
     let mut driver_version = 0;
     let mut member_index = 0;
+    let mut hardware_ids = Vec::new();
 
     while let Some(drvinfo_data) = ffi::enum_driver_info(
         devinfo,
@@ -86,11 +127,22 @@ pub fn create_interface() -> io::Result<NET_LUID> {
             _ => continue,
         };
 
-        let is_compatible = drvinfo_detail
+        let ids = drvinfo_detail
             .HardwareID
             .split(|b| *b == 0)
-            .map(|id| decode_utf16(id))
-            .any(|id| id.eq_ignore_ascii_case(HARDWARE_ID));
+            .map(decode_utf16)
+            .filter(|id| !id.is_empty())
+            .collect::<Vec<_>>();
+
+        let is_compatible =
+            ids.iter().any(|id| id.eq_ignore_ascii_case(component_id));
+
+        // Remembered for the error message in case nothing matches
+        for id in ids {
+            if !hardware_ids.contains(&id) {
+                hardware_ids.push(id);
+            }
+        }
 
         if !is_compatible {
             continue;
@@ -105,7 +157,7 @@ pub fn create_interface() -> io::Result<NET_LUID> {
     }
 
     if driver_version == 0 {
-        return Err(io::Error::new(io::ErrorKind::NotFound, "No driver found"));
+        return Err(no_driver_error(component_id, &hardware_ids));
     }
 
     let uninstaller = guard((), |_| {
@@ -125,6 +177,43 @@ pub fn create_interface() -> io::Result<NET_LUID> {
         DIF_INSTALLINTERFACES,
     );
 
+    if options.guid.is_some()
+        || options.mac.is_some()
+        || options.suppress_network_prompt
+    {
+        let key = ffi::create_dev_reg_key(
+            devinfo,
+            &devinfo_data,
+            DICS_FLAG_GLOBAL,
+            0,
+            DIREG_DRV,
+        )?;
+
+        let key = RegKey::predef(key);
+
+        // The network class installer picks up the anticipated
+        // instance id from the driver key when installing the device
+        if let Some(guid) = &options.guid {
+            let instance_id = ffi::string_from_guid(guid)?;
+
+            key.set_value(
+                "NetSetupAnticipatedInstanceId",
+                &decode_utf16(&instance_id),
+            )?;
+        }
+
+        // Read by the driver when the device starts
+        if let Some(mac) = &options.mac {
+            key.set_value("MAC", &mac.to_string())?;
+        }
+
+        // Network location awareness ignores endpoint adapters,
+        // so no profile is ever asked for
+        if options.suppress_network_prompt {
+            key.set_value("*NdisDeviceType", &NDIS_DEVICE_TYPE_ENDPOINT)?;
+        }
+    }
+
     ffi::call_class_installer(devinfo, &devinfo_data, DIF_INSTALLDEVICE)?;
 
     let key = ffi::open_dev_reg_key(
@@ -138,29 +227,90 @@ pub fn create_interface() -> io::Result<NET_LUID> {
 
     let key = RegKey::predef(key);
 
-    while let Err(_) = key.get_value::<DWORD, &str>("*IfType") {
-        ffi::notify_change_key_value(
-            key.raw_handle(),
-            TRUE,
-            REG_NOTIFY_CHANGE_NAME,
-            2000,
-        )?;
-    }
+    // The inf values are written before the device starts, so once
+    // the luid index shows up a missing `*IfType` is not coming
+    let luid_index: DWORD = wait_key_value(&key, "NetLuidIndex")?;
+    let if_type = legacy::if_type_from_key(&key)?;
 
-    while let Err(_) = key.get_value::<DWORD, &str>("NetLuidIndex") {
-        ffi::notify_change_key_value(
-            key.raw_handle(),
-            TRUE,
-            REG_NOTIFY_CHANGE_NAME,
-            2000,
-        )?;
+    // Defuse the uninstaller
+    ScopeGuard::into_inner(uninstaller);
+
+    let mut luid = NET_LUID { Value: 0 };
+
+    luid.set_IfType(if_type as _);
+    luid.set_NetLuidIndex(luid_index as _);
+
+    Ok(luid)
+}
+
+/// Wait for a value to be written to a key, values are considered
+/// missing only while reading them fails with `NotFound`
+fn wait_key_value(key: &RegKey, name: &str) -> io::Result<DWORD> {
+    loop {
+        match key.get_value(name) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                ffi::notify_change_key_value(
+                    key.raw_handle(),
+                    TRUE,
+                    REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                    2000,
+                )?;
+            }
+            res => return res,
+        }
     }
+}
 
-    let if_type: DWORD = key.get_value("*IfType")?;
-    let luid_index: DWORD = key.get_value("NetLuidIndex")?;
+/// Build the error returned when no driver matches the component id,
+/// listing what was found and the architectures involved, since a
+/// driver for the wrong architecture is the most common cause
+fn no_driver_error(component_id: &str, hardware_ids: &[String]) -> io::Error {
+    let system_info = ffi::get_native_system_info();
+
+    let os_arch = match unsafe { system_info.u.s().wProcessorArchitecture } {
+        PROCESSOR_ARCHITECTURE_INTEL => "x86",
+        PROCESSOR_ARCHITECTURE_AMD64 => "x64",
+        PROCESSOR_ARCHITECTURE_ARM => "arm",
+        PROCESSOR_ARCHITECTURE_ARM64 => "arm64",
+        _ => "unknown",
+    };
+
+    let found = if hardware_ids.is_empty() {
+        "no compatible drivers".to_string()
+    } else {
+        hardware_ids.join(", ")
+    };
+
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "No driver found for {} on {} Windows ({} process), found: {}",
+            component_id,
+            os_arch,
+            std::env::consts::ARCH,
+            found,
+        ),
+    )
+}
 
-    // Defuse the uninstaller
-    ScopeGuard::into_inner(uninstaller);
+/// Summary of an interface found during enumeration
+pub struct Interface {
+    pub luid: NET_LUID,
+    pub driver_version: String,
+    pub owner: Option<OwnerTag>,
+}
+
+/// Interface found by scanning the registry
+pub struct RegistryInterface {
+    pub interface: Interface,
+    pub guid: String,
+    pub name: String,
+}
+
+/// Build the NET_LUID of an interface from its driver key
+fn luid_from_key(key: &RegKey) -> io::Result<NET_LUID> {
+    let if_type = legacy::if_type_from_key(key)?;
+    let luid_index: DWORD = key.get_value("NetLuidIndex")?;
 
     let mut luid = NET_LUID { Value: 0 };
 
@@ -170,8 +320,12 @@ pub fn create_interface() -> io::Result<NET_LUID> {
     Ok(luid)
 }
 
-/// Check if the given interface exists and is a valid tap-windows device
-pub fn check_interface(luid: &NET_LUID) -> io::Result<()> {
+/// Walk every present interface with the given hardware id, calling
+/// `f` on each one until it returns `Some`
+fn find_interface<T, F>(component_id: &str, mut f: F) -> io::Result<Option<T>>
+where
+    F: FnMut(HDEVINFO, &SP_DEVINFO_DATA, &RegKey, NET_LUID) -> Option<T>,
+{
     let devinfo = ffi::get_class_devs(&GUID_NETWORK_ADAPTER, DIGCF_PRESENT)?;
 
     let _guard = guard((), |_| {
@@ -198,7 +352,7 @@ pub fn check_interface(luid: &NET_LUID) -> io::Result<()> {
             Err(_) => continue,
         };
 
-        if !decode_utf16(&hardware_id).eq_ignore_ascii_case(HARDWARE_ID) {
+        if !decode_utf16(&hardware_id).eq_ignore_ascii_case(component_id) {
             continue;
         }
 
@@ -214,114 +368,427 @@ pub fn check_interface(luid: &NET_LUID) -> io::Result<()> {
             Err(_) => continue,
         };
 
-        let if_type: DWORD = match key.get_value("*IfType") {
-            Ok(if_type) => if_type,
-            Err(_) => continue,
-        };
-
-        let luid_index: DWORD = match key.get_value("NetLuidIndex") {
-            Ok(luid_index) => luid_index,
+        let luid = match luid_from_key(&key) {
+            Ok(luid) => luid,
             Err(_) => continue,
         };
 
-        let mut luid2 = NET_LUID { Value: 0 };
-
-        luid2.set_IfType(if_type as _);
-        luid2.set_NetLuidIndex(luid_index as _);
-
-        if luid.Value != luid2.Value {
-            continue;
+        if let Some(res) = f(devinfo, &devinfo_data, &key, luid) {
+            return Ok(Some(res));
         }
-
-        // Found it!
-        return Ok(());
     }
 
-    Err(io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+    Ok(None)
 }
 
-/// Deletes an existing interface
-pub fn delete_interface(luid: &NET_LUID) -> io::Result<()> {
-    let devinfo = ffi::get_class_devs(&GUID_NETWORK_ADAPTER, DIGCF_PRESENT)?;
+/// List every present interface with the given hardware id
+pub fn enum_interfaces(component_id: &str) -> io::Result<Vec<Interface>> {
+    let mut interfaces = Vec::new();
 
-    let _guard = guard((), |_| {
-        let _ = ffi::destroy_device_info_list(devinfo);
-    });
+    find_interface(component_id, |_, _, key, luid| {
+        let driver_version = key.get_value("DriverVersion").unwrap_or_default();
 
-    let mut member_index = 0;
+        interfaces.push(Interface {
+            luid,
+            driver_version,
+            owner: OwnerTag::read(key),
+        });
 
-    while let Some(devinfo_data) = ffi::enum_device_info(devinfo, member_index)
-    {
-        member_index += 1;
+        None::<()>
+    })?;
 
-        let devinfo_data = match devinfo_data {
-            Ok(devinfo_data) => devinfo_data,
+    Ok(interfaces)
+}
+
+/// List every interface with the given hardware id by scanning the
+/// network class registry keys, without going through SetupDi.
+/// Interfaces found this way might not be present
+pub fn enum_interfaces_registry(
+    component_id: &str,
+) -> io::Result<Vec<RegistryInterface>> {
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+
+    let class = hklm.open_subkey_with_flags(NETWORK_CLASS_KEY, KEY_READ)?;
+    let connections =
+        hklm.open_subkey_with_flags(NETWORK_CONNECTIONS_KEY, KEY_READ)?;
+
+    let mut interfaces = Vec::new();
+
+    for subkey in class.enum_keys() {
+        // Some subkeys (like Properties) are not readable, skip them
+        let key = match subkey
+            .and_then(|subkey| class.open_subkey_with_flags(subkey, KEY_READ))
+        {
+            Ok(key) => key,
             Err(_) => continue,
         };
 
-        let hardware_id = match ffi::get_device_registry_property(
-            devinfo,
-            &devinfo_data,
-            SPDRP_HARDWAREID,
-        ) {
-            Ok(hardware_id) => hardware_id,
+        let id: String = match key.get_value("ComponentId") {
+            Ok(id) => id,
             Err(_) => continue,
         };
 
-        if !decode_utf16(&hardware_id).eq_ignore_ascii_case(HARDWARE_ID) {
+        if !id.eq_ignore_ascii_case(component_id) {
             continue;
         }
 
-        let key = match ffi::open_dev_reg_key(
-            devinfo,
-            &devinfo_data,
-            DICS_FLAG_GLOBAL,
-            0,
-            DIREG_DRV,
-            KEY_QUERY_VALUE | KEY_NOTIFY,
-        ) {
-            Ok(key) => RegKey::predef(key),
+        let guid: String = match key.get_value("NetCfgInstanceId") {
+            Ok(guid) => guid,
             Err(_) => continue,
         };
 
-        let if_type: DWORD = match key.get_value("*IfType") {
-            Ok(if_type) => if_type,
+        let luid = match luid_from_key(&key) {
+            Ok(luid) => luid,
             Err(_) => continue,
         };
 
-        let luid_index: DWORD = match key.get_value("NetLuidIndex") {
-            Ok(luid_index) => luid_index,
-            Err(_) => continue,
-        };
+        let name = connections
+            .open_subkey_with_flags(format!(r"{}\Connection", guid), KEY_READ)
+            .and_then(|key| key.get_value("Name"))
+            .unwrap_or_default();
+
+        interfaces.push(RegistryInterface {
+            interface: Interface {
+                luid,
+                driver_version: key
+                    .get_value("DriverVersion")
+                    .unwrap_or_default(),
+                owner: OwnerTag::read(&key),
+            },
+            guid,
+            name,
+        });
+    }
+
+    Ok(interfaces)
+}
+
+/// Check if the given interface exists and is a valid tap-windows device
+pub fn check_interface(component_id: &str, luid: &NET_LUID) -> io::Result<()> {
+    find_interface(component_id, |_, _, _, luid2| {
+        if luid.Value == luid2.Value {
+            Some(())
+        } else {
+            None
+        }
+    })?
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+}
+
+/// Check again, right before changing it, that a device found by
+/// `find_interface` really is the software adapter behind `luid`.
+/// Guards against removing or rewriting a physical nic, should the
+/// component id given by the user be the one of real hardware or
+/// the registry hold stale values
+fn verify_interface(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    key: &RegKey,
+    luid: &NET_LUID,
+) -> io::Result<()> {
+    let guid = ffi::luid_to_guid(luid)
+        .and_then(|guid| ffi::string_from_guid(&guid))?;
+    let instance_guid: String = key.get_value("NetCfgInstanceId")?;
+
+    if !decode_utf16(&guid).eq_ignore_ascii_case(&instance_guid) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Device does not match the interface luid",
+        ));
+    }
+
+    // Adapters created through SetupDi are enumerated by the root bus
+    let instance_id = ffi::get_device_instance_id(devinfo, devinfo_data)?;
+    let instance_id = decode_utf16(&instance_id);
+
+    let root = instance_id
+        .get(..5)
+        .map_or(false, |prefix| prefix.eq_ignore_ascii_case(r"ROOT\"));
+    if !root {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("Refusing to modify hardware device {}", instance_id),
+        ));
+    }
+
+    Ok(())
+}
 
-        let mut luid2 = NET_LUID { Value: 0 };
+/// Deletes an existing interface
+pub fn delete_interface(component_id: &str, luid: &NET_LUID) -> io::Result<()> {
+    find_interface(component_id, |devinfo, devinfo_data, key, luid2| {
+        if luid.Value != luid2.Value {
+            return None;
+        }
 
-        luid2.set_IfType(if_type as _);
-        luid2.set_NetLuidIndex(luid_index as _);
+        Some(verify_interface(devinfo, devinfo_data, key, luid).and_then(
+            |_| ffi::call_class_installer(devinfo, devinfo_data, DIF_REMOVE),
+        ))
+    })?
+    .unwrap_or_else(|| {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+    })
+}
 
+/// Open the driver registry key of an interface
+pub fn open_driver_key(
+    component_id: &str,
+    luid: &NET_LUID,
+    sam_desired: REGSAM,
+) -> io::Result<RegKey> {
+    find_interface(component_id, |devinfo, devinfo_data, key, luid2| {
         if luid.Value != luid2.Value {
-            continue;
+            return None;
         }
 
-        // Found it!
-        return ffi::call_class_installer(devinfo, &devinfo_data, DIF_REMOVE);
-    }
+        // Only writes need the check
+        if sam_desired & KEY_SET_VALUE != 0 {
+            if let Err(err) = verify_interface(devinfo, devinfo_data, key, luid)
+            {
+                return Some(Err(err));
+            }
+        }
+
+        Some(
+            ffi::open_dev_reg_key(
+                devinfo,
+                devinfo_data,
+                DICS_FLAG_GLOBAL,
+                0,
+                DIREG_DRV,
+                sam_desired,
+            )
+            .map(RegKey::predef),
+        )
+    })?
+    .unwrap_or_else(|| {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+    })
+}
 
-    Err(io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+/// Restart an interface by disabling and enabling it
+pub fn restart_interface(
+    component_id: &str,
+    luid: &NET_LUID,
+) -> io::Result<()> {
+    find_interface(component_id, |devinfo, devinfo_data, key, luid2| {
+        if luid.Value != luid2.Value {
+            return None;
+        }
+
+        Some(
+            verify_interface(devinfo, devinfo_data, key, luid)
+                .and_then(|_| change_state(devinfo, devinfo_data, DICS_DISABLE))
+                .and_then(|_| change_state(devinfo, devinfo_data, DICS_ENABLE)),
+        )
+    })?
+    .unwrap_or_else(|| {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+    })
+}
+
+/// Change the state of a device through DIF_PROPERTYCHANGE
+fn change_state(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    state: DWORD,
+) -> io::Result<()> {
+    let mut params: SP_PROPCHANGE_PARAMS = unsafe { mem::zeroed() };
+    params.ClassInstallHeader.cbSize =
+        mem::size_of::<SP_CLASSINSTALL_HEADER>() as _;
+    params.ClassInstallHeader.InstallFunction = DIF_PROPERTYCHANGE;
+    params.StateChange = state;
+    params.Scope = DICS_FLAG_GLOBAL;
+    params.HwProfile = 0;
+
+    ffi::set_class_install_params(devinfo, devinfo_data, &params)?;
+    ffi::call_class_installer(devinfo, devinfo_data, DIF_PROPERTYCHANGE)
+}
+
+/// Get the device instance id of an interface
+pub fn get_instance_id(
+    component_id: &str,
+    luid: &NET_LUID,
+) -> io::Result<Vec<WCHAR>> {
+    find_interface(component_id, |devinfo, devinfo_data, _, luid2| {
+        if luid.Value != luid2.Value {
+            return None;
+        }
+
+        Some(ffi::get_device_instance_id(devinfo, devinfo_data))
+    })?
+    .unwrap_or_else(|| {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+    })
+}
+
+/// Get the enumeration summary of a single interface
+pub fn get_interface(
+    component_id: &str,
+    luid: &NET_LUID,
+) -> io::Result<Interface> {
+    find_interface(component_id, |_, _, key, luid2| {
+        if luid.Value != luid2.Value {
+            return None;
+        }
+
+        Some(Interface {
+            luid: luid2,
+            driver_version: key.get_value("DriverVersion").unwrap_or_default(),
+            owner: OwnerTag::read(key),
+        })
+    })?
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+}
+
+/// Set the description shown in Device Manager for an interface
+pub fn set_interface_description(
+    component_id: &str,
+    luid: &NET_LUID,
+    description: &str,
+) -> io::Result<()> {
+    let description = encode_utf16(description);
+
+    find_interface(component_id, |devinfo, devinfo_data, key, luid2| {
+        if luid.Value != luid2.Value {
+            return None;
+        }
+
+        Some(
+            verify_interface(devinfo, devinfo_data, key, luid)
+                .and_then(|_| {
+                    ffi::set_device_registry_property(
+                        devinfo,
+                        devinfo_data,
+                        SPDRP_FRIENDLYNAME,
+                        &description,
+                    )
+                })
+                .and_then(|_| {
+                    ffi::set_device_registry_property(
+                        devinfo,
+                        devinfo_data,
+                        SPDRP_DEVICEDESC,
+                        &description,
+                    )
+                }),
+        )
+    })?
+    .unwrap_or_else(|| {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Device not found"))
+    })
 }
 
-/// Open an handle to an interface
-pub fn open_interface(luid: &NET_LUID) -> io::Result<HANDLE> {
+/// Open the connection key of an interface, holding its name
+pub fn open_connection_key(
+    luid: &NET_LUID,
+    sam_desired: REGSAM,
+) -> io::Result<RegKey> {
     let guid = ffi::luid_to_guid(luid)
         .and_then(|guid| ffi::string_from_guid(&guid))?;
 
-    let path = format!(r"\\.\Global\{}.tap", &decode_utf16(&guid));
-
-    ffi::create_file(
-        &encode_utf16(&path),
-        GENERIC_READ | GENERIC_WRITE,
-        FILE_SHARE_READ | FILE_SHARE_WRITE,
-        OPEN_EXISTING,
-        FILE_ATTRIBUTE_SYSTEM,
+    RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey_with_flags(
+        format!(
+            r"{}\{}\Connection",
+            NETWORK_CONNECTIONS_KEY,
+            decode_utf16(&guid)
+        ),
+        sam_desired,
     )
 }
+
+/// Set the custom path template, see `Device::set_path_template`
+pub fn set_path_template(template: Option<&str>) {
+    let mut current =
+        PATH_TEMPLATE.lock().unwrap_or_else(|err| err.into_inner());
+    *current = template.map(str::to_owned);
+}
+
+/// Device paths of an interface, in the order they are tried
+pub fn interface_paths(luid: &NET_LUID) -> io::Result<Vec<String>> {
+    let guid = ffi::luid_to_guid(luid)
+        .and_then(|guid| ffi::string_from_guid(&guid))?;
+    let guid = decode_utf16(&guid);
+
+    let custom = PATH_TEMPLATE
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+
+    Ok(custom
+        .iter()
+        .map(String::as_str)
+        .chain(PATH_TEMPLATES.iter().copied())
+        .map(|template| template.replace("{guid}", &guid))
+        .collect())
+}
+
+/// Get the preferred device path of an interface
+pub fn interface_path(luid: &NET_LUID) -> io::Result<String> {
+    let mut paths = interface_paths(luid)?;
+    Ok(paths.remove(0))
+}
+
+/// Open an handle to an interface, trying every device path
+/// and returning the error of the first one if all fail
+pub fn open_interface(
+    luid: &NET_LUID,
+    inheritable: bool,
+) -> io::Result<HANDLE> {
+    let mut first_err = None;
+
+    for path in interface_paths(luid)? {
+        match ffi::create_file(
+            &encode_utf16(&path),
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            inheritable,
+            OPEN_EXISTING,
+            // Allows multiple reads and writes to be pending at once
+            FILE_ATTRIBUTE_SYSTEM | FILE_FLAG_OVERLAPPED,
+        ) {
+            Ok(handle) => return Ok(handle),
+            Err(err) => {
+                trace!(path = %path, error = %err, "failed to open path");
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+
+    Err(first_err.expect("No device path tried"))
+}
+
+/// Open an handle to an interface, retrying until the
+/// interface is ready or the timeout expires, the delay between
+/// attempts starts at `poll_interval` and doubles every time
+pub fn wait_open_interface(
+    luid: &NET_LUID,
+    inheritable: bool,
+    timeout: time::Duration,
+    poll_interval: time::Duration,
+) -> io::Result<HANDLE> {
+    // Don't let the backoff grow past this, unless asked to
+    let max_interval = poll_interval.max(MAX_POLL_INTERVAL);
+
+    let start = time::Instant::now();
+    let mut interval = poll_interval;
+
+    loop {
+        match open_interface(luid, inheritable) {
+            Ok(handle) => return Ok(handle),
+            Err(_) => match timeout.checked_sub(start.elapsed()) {
+                Some(remaining) => {
+                    thread::sleep(interval.min(remaining));
+
+                    interval = interval.saturating_mul(2).min(max_interval);
+                }
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Interface timed out",
+                    ))
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use winapi::shared::ifdef::*;
+use winapi::shared::winerror::ERROR_GEN_FAILURE;
+
+use winapi::shared::ws2def::AF_UNSPEC;
+
+use std::{io, net};
+
+use crate::{decode_utf16, ffi, iface, MacAddress, OwnerTag};
+
+/// Snapshot of the state of a tap-windows adapter, as returned
+/// by `Device::enumerate` and `Device::get_info`.
+/// With the `serde` feature enabled it implements `Serialize`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeviceInfo {
+    /// Name (alias) of the interface
+    pub name: String,
+    /// Guid of the interface, in the `{XXXXXXXX-XXXX-...}` form
+    pub guid: String,
+    /// Raw value of the interface NET_LUID
+    pub luid: u64,
+    /// Index of the interface
+    pub index: u32,
+    /// Hardware id of the driver bound to the adapter
+    pub component_id: String,
+    /// Version of the driver bound to the adapter
+    pub driver_version: String,
+    /// Mac address of the adapter
+    pub mac: MacAddress,
+    /// Mtu of the interface
+    pub mtu: u32,
+    /// Unicast addresses configured on the interface
+    pub ips: Vec<net::IpAddr>,
+    /// True if the media status of the adapter is connected
+    pub up: bool,
+    /// Ownership tag of the adapter, if any
+    pub owner: Option<OwnerTag>,
+}
+
+impl DeviceInfo {
+    /// Check if another process currently holds the adapter open.
+    /// Returns a `NotFound` error if the adapter no longer exists,
+    /// so that a busy adapter can be told apart from a missing one
+    pub fn is_in_use(&self) -> io::Result<bool> {
+        let luid = NET_LUID { Value: self.luid };
+
+        iface::check_interface(&self.component_id, &luid)?;
+
+        // The driver only allows a single handle to be open at a time,
+        // a second open fails with ERROR_GEN_FAILURE
+        match iface::open_interface(&luid, false) {
+            Ok(handle) => {
+                let _ = ffi::close_handle(handle);
+                Ok(false)
+            }
+            Err(err) if err.raw_os_error() == Some(ERROR_GEN_FAILURE as _) => {
+                Ok(true)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Build the snapshot of a present interface
+pub(crate) fn snapshot(
+    component_id: &str,
+    interface: iface::Interface,
+) -> io::Result<DeviceInfo> {
+    let luid = interface.luid;
+    let row = ffi::get_if_entry2(&luid)?;
+
+    let guid = ffi::string_from_guid(&row.InterfaceGuid)?;
+
+    let mut mac = [0; 6];
+    mac.copy_from_slice(&row.PhysicalAddress[..6]);
+
+    let ips = ffi::get_unicast_ip_address_table(AF_UNSPEC as _)?
+        .iter()
+        .filter(|row| row.InterfaceLuid.Value == luid.Value)
+        .filter_map(|row| ffi::sockaddr_inet_to_ip(&row.Address))
+        .collect();
+
+    Ok(DeviceInfo {
+        name: decode_utf16(&row.Alias),
+        guid: decode_utf16(&guid),
+        luid: luid.Value,
+        index: row.InterfaceIndex,
+        component_id: component_id.to_owned(),
+        driver_version: interface.driver_version,
+        mac: MacAddress(mac),
+        mtu: row.Mtu,
+        ips,
+        up: row.MediaConnectState == MediaConnectStateConnected,
+        owner: interface.owner,
+    })
+}
+
+/// List every adapter bound to the given hardware id,
+/// falling back to the registry if SetupDi fails
+pub(crate) fn enumerate(component_id: &str) -> io::Result<Vec<DeviceInfo>> {
+    let interfaces = match iface::enum_interfaces(component_id) {
+        Ok(interfaces) => interfaces,
+        Err(_) => return enumerate_registry(component_id),
+    };
+
+    // The adapter might have disappeared in the meantime
+    Ok(interfaces
+        .into_iter()
+        .filter_map(|interface| snapshot(component_id, interface).ok())
+        .collect())
+}
+
+/// List every adapter bound to the given hardware id using
+/// only the registry, adapters not currently present are skipped
+pub(crate) fn enumerate_registry(
+    component_id: &str,
+) -> io::Result<Vec<DeviceInfo>> {
+    let interfaces = iface::enum_interfaces_registry(component_id)?;
+
+    // Not present adapters have no interface
+    Ok(interfaces
+        .into_iter()
+        .filter_map(|found| {
+            let info = snapshot(component_id, found.interface).ok()?;
+
+            Some(DeviceInfo {
+                name: found.name,
+                guid: found.guid,
+                ..info
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,187 @@
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use crate::packet::*;
+use crate::MacAddress;
+
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+
+/// Outcome of a keepalive probe, see `Keepalive`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkHealth {
+    /// The probe was answered after the given round trip time
+    Alive(Duration),
+    /// The probe was not answered in time
+    Lost,
+}
+
+/// Configuration of a `Keepalive`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeepaliveConfig {
+    /// Address of the interface, the one probed
+    pub address: Ipv4Addr,
+    /// Mac address the probes are sent from, it only needs to be
+    /// unique on the link
+    pub mac: MacAddress,
+    /// Time between two probes
+    pub interval: Duration,
+    /// Time after which an unanswered probe is reported as lost
+    pub timeout: Duration,
+}
+
+/// Checks that frames still go through the driver and the Windows
+/// network stack, by periodically writing an arp probe for the
+/// interface's own address and waiting for Windows to answer it.
+/// Every probe is reported to the callback, a run of `Lost` means
+/// a wedged driver or a disconnected interface.
+/// Call `poll` regularly, at least once per interval, and pass the
+/// frames read to `handle_frame`.
+/// Built with the `keepalive` feature
+/// Example
+/// ```no_run
+/// use tap_windows::{
+///     Device, DeviceSet, Keepalive, KeepaliveConfig, LinkHealth, MacAddress,
+/// };
+/// use std::io::Write;
+/// use std::time::Duration;
+///
+/// let mut set = DeviceSet::new(2048);
+/// let id = set.insert(Device::open("My Own Device")?)?;
+///
+/// let mut keepalive = Keepalive::new(
+///     KeepaliveConfig {
+///         address: [10, 8, 0, 2].into(),
+///         mac: MacAddress([0x02, 0, 0, 0, 0, 1]),
+///         interval: Duration::from_secs(1),
+///         timeout: Duration::from_millis(500),
+///     },
+///     |health| {
+///         if health == LinkHealth::Lost {
+///             eprintln!("Keepalive probe lost");
+///         }
+///     },
+/// );
+///
+/// loop {
+///     let timeout = Some(Duration::from_millis(100));
+///     if let Some((_, frame)) = set.next_frame_timeout(timeout)? {
+///         if keepalive.handle_frame(frame) {
+///             continue;
+///         }
+///
+///         // Handle the other frames
+///     }
+///
+///     if let Some(probe) = keepalive.poll() {
+///         set.get_mut(id).unwrap().write(&probe)?;
+///     }
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Keepalive {
+    config: KeepaliveConfig,
+    callback: Box<dyn FnMut(LinkHealth) + Send>,
+    /// When the last probe was sent
+    last_probe: Option<Instant>,
+    /// Whether the last probe is still waiting for its answer
+    pending: bool,
+}
+
+impl Keepalive {
+    /// Create a keepalive, the first probe is sent on the
+    /// first `poll`
+    pub fn new<F>(config: KeepaliveConfig, callback: F) -> Self
+    where
+        F: FnMut(LinkHealth) + Send + 'static,
+    {
+        Self {
+            config,
+            callback: Box::new(callback),
+            last_probe: None,
+            pending: false,
+        }
+    }
+
+    /// Report the probe that timed out, and return the next probe
+    /// to write to the device once the interval elapsed
+    pub fn poll(&mut self) -> Option<Vec<u8>> {
+        let now = Instant::now();
+
+        if let Some(last_probe) = self.last_probe {
+            let elapsed = now.saturating_duration_since(last_probe);
+
+            if self.pending && elapsed >= self.config.timeout {
+                self.pending = false;
+                (self.callback)(LinkHealth::Lost);
+            }
+
+            if elapsed < self.config.interval {
+                return None;
+            }
+        }
+
+        // A probe answered after the next one was sent would be
+        // reported for the wrong one, skip it
+        if self.pending {
+            return None;
+        }
+
+        self.last_probe = Some(now);
+        self.pending = true;
+
+        Some(self.build_probe())
+    }
+
+    /// Handle a frame read from the device, returns true if it was
+    /// the answer to a probe, which should not be processed further
+    pub fn handle_frame(&mut self, frame: &[u8]) -> bool {
+        if !self.is_answer(frame) {
+            return false;
+        }
+
+        if let (true, Some(last_probe)) = (self.pending, self.last_probe) {
+            self.pending = false;
+            (self.callback)(LinkHealth::Alive(last_probe.elapsed()));
+        }
+
+        true
+    }
+
+    /// An arp probe, RFC 5227, the zero sender address keeps the
+    /// probe out of the arp cache of Windows
+    fn build_probe(&self) -> Vec<u8> {
+        let mac = self.config.mac.octets();
+
+        let mut probe = Vec::with_capacity(ETHER_HEADER_LEN + 28);
+        probe.extend_from_slice(&[0xff; 6]);
+        probe.extend_from_slice(&mac);
+        probe.extend_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+
+        probe.extend_from_slice(&[0, 1, 8, 0, 6, 4]);
+        probe.extend_from_slice(&ARP_REQUEST.to_be_bytes());
+        probe.extend_from_slice(&mac);
+        probe.extend_from_slice(&[0; 4]);
+        probe.extend_from_slice(&[0; 6]);
+        probe.extend_from_slice(&self.config.address.octets());
+
+        probe
+    }
+
+    fn is_answer(&self, frame: &[u8]) -> bool {
+        let arp = match ether_type(frame) {
+            Some(ETHERTYPE_ARP) => {
+                match frame.get(ETHER_HEADER_LEN..ETHER_HEADER_LEN + 28) {
+                    Some(arp) => arp,
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+
+        arp[..6] == [0, 1, 8, 0, 6, 4]
+            && read_u16(arp, 6) == ARP_REPLY
+            && arp[14..18] == self.config.address.octets()
+            && arp[18..24] == self.config.mac.octets()
+    }
+}
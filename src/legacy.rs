@@ -0,0 +1,170 @@
+//! Compatibility with the NDIS 5 tap-windows driver, still found on
+//! Windows 7 and LTSB images. Older versions may only expose the
+//! non global device path, which `iface::interface_paths` already
+//! falls back to
+use winapi::shared::ipifcons::IF_TYPE_ETHERNET_CSMACD;
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::winerror::*;
+use winapi::um::winioctl::*;
+
+use std::io;
+
+use crate::registry::RegKey;
+use crate::{ffi, Device, DeviceInfo};
+
+/// First version of the NDIS 6 tap-windows6 driver, anything older
+/// is the legacy NDIS 5 driver
+const NDIS6_VERSION: [u32; 2] = [9, 21];
+
+/// Check if a driver version, as returned by `Device::get_version`,
+/// belongs to the legacy driver
+fn is_legacy_version(version: &[u32]) -> bool {
+    match version {
+        [major, minor, ..] => [*major, *minor] < NDIS6_VERSION,
+        _ => false,
+    }
+}
+
+/// NDIS major version of the driver with the given version
+fn ndis_major(version: &[u32]) -> u32 {
+    if is_legacy_version(version) {
+        5
+    } else {
+        6
+    }
+}
+
+/// Same as `is_legacy_version`, for the `9.9.2.0` form stored in the
+/// registry. Unparsable versions are not considered legacy
+fn is_legacy_version_str(version: &str) -> bool {
+    let version: Option<Vec<u32>> =
+        version.split('.').map(|part| part.parse().ok()).collect();
+
+    match version {
+        Some(version) => is_legacy_version(&version),
+        None => false,
+    }
+}
+
+/// Read the `*IfType` of an adapter, NDIS 5 drivers don't have to
+/// declare it in their inf, in which case it is always ethernet
+pub(crate) fn if_type_from_key(key: &RegKey) -> io::Result<DWORD> {
+    match key.get_value("*IfType") {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Ok(IF_TYPE_ETHERNET_CSMACD)
+        }
+        res => res,
+    }
+}
+
+impl Device {
+    /// Check if the adapter is driven by the legacy NDIS 5 driver,
+    /// which lacks some of the ioctls used by this crate. Those
+    /// fail with an `Unsupported` error naming the driver version
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// if dev.is_legacy_driver().unwrap_or(false) {
+    ///     println!("Please upgrade to tap-windows6");
+    /// }
+    /// ```
+    pub fn is_legacy_driver(&self) -> io::Result<bool> {
+        self.get_version()
+            .map(|version| is_legacy_version(&version))
+    }
+
+    /// Get the NDIS major version of the bound driver, 6 for
+    /// tap-windows6 and 5 for the legacy driver. Some configuration
+    /// features are missing on 5, and throughput is lower
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let ndis = dev.get_ndis_version()
+    ///     .expect("Failed to get ndis version");
+    ///
+    /// println!("Served by an NDIS {} driver", ndis);
+    /// ```
+    pub fn get_ndis_version(&self) -> io::Result<u32> {
+        self.get_version().map(|version| ndis_major(&version))
+    }
+
+    /// Replace the error of an ioctl the legacy driver doesn't know
+    /// with one that says so, instead of a bare "Incorrect function"
+    pub(crate) fn legacy_ioctl_error(&self, err: io::Error) -> io::Error {
+        match err.raw_os_error() {
+            Some(code)
+                if code == ERROR_INVALID_FUNCTION as i32
+                    || code == ERROR_NOT_SUPPORTED as i32 => {}
+            _ => return err,
+        }
+
+        let handle = match self.raw_handle() {
+            Ok(handle) => handle,
+            Err(_) => return err,
+        };
+
+        // Asked directly, a failing version ioctl would recurse
+        let mut version = [0u32; 3];
+        let res = {
+            let mut overlapped = self
+                .ioctl_overlapped
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+
+            ffi::device_io_control(
+                handle,
+                CTL_CODE(
+                    FILE_DEVICE_UNKNOWN,
+                    2,
+                    METHOD_BUFFERED,
+                    FILE_ANY_ACCESS,
+                ),
+                &(),
+                &mut version,
+                &mut overlapped,
+            )
+        };
+
+        match res {
+            Ok(()) if is_legacy_version(&version) => io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "Not supported by the legacy tap-windows driver {}.{}, \
+                     tap-windows6 {}.{} or later is required",
+                    version[0], version[1], NDIS6_VERSION[0], NDIS6_VERSION[1],
+                ),
+            ),
+            _ => err,
+        }
+    }
+}
+
+impl DeviceInfo {
+    /// Check if the adapter is driven by the legacy NDIS 5 driver,
+    /// see `Device::is_legacy_driver`
+    pub fn is_legacy_driver(&self) -> bool {
+        is_legacy_version_str(&self.driver_version)
+    }
+
+    /// NDIS major version of the driver, see `Device::get_ndis_version`.
+    /// `None` if the registry holds no usable driver version
+    pub fn ndis_version(&self) -> Option<u32> {
+        let version: Option<Vec<u32>> = self
+            .driver_version
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect();
+
+        version
+            .filter(|version| version.len() >= 2)
+            .map(|version| ndis_major(&version))
+    }
+}
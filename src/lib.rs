@@ -3,6 +3,8 @@
 //! created by OpenVPN to manage tap interfaces.
 //! Look at the documentation for `Device` for a
 //! pretty simple example on how to use this library.
+//! Enable the `tracing` feature to get events for adapter creation,
+//! deletion, ioctls and netsh invocations through `tracing`.
 #![cfg(windows)]
 
 /// Encode a string as a utf16 buffer
@@ -17,19 +19,180 @@ fn decode_utf16(string: &[u16]) -> String {
     String::from_utf16_lossy(&string[..end])
 }
 
+// Must come first, the macros are used by every other module
+#[macro_use]
+mod trace;
+
+mod address;
+mod binding;
+#[cfg(feature = "broker")]
+mod broker;
+#[cfg(feature = "capi")]
+mod capi;
+#[cfg(feature = "capture")]
+mod capture;
+mod claim;
+mod component;
+mod config;
+#[cfg(feature = "pnet_datalink")]
+mod datalink;
+#[cfg(feature = "dhcp")]
+mod dhcp;
+mod dns;
+mod dscp;
+mod elevation;
+#[cfg(feature = "test-util")]
+mod fault;
 mod ffi;
+mod filter;
+#[cfg(feature = "etherparse")]
+mod frame;
+mod group;
+mod hook;
+#[cfg(feature = "icmp")]
+mod icmp;
 mod iface;
+mod info;
+#[cfg(feature = "keepalive")]
+mod keepalive;
+mod legacy;
+pub mod low_level;
+mod mac;
+#[cfg(feature = "test-util")]
+mod mock;
+mod mss;
+mod mux;
+mod name;
 mod netsh;
+mod notify;
+mod openvpn;
+mod options;
+mod overlapped;
+mod owner;
+mod packet;
+mod priority;
+mod profile;
+mod properties;
+mod queue;
+mod ratelimit;
+#[cfg(feature = "raw-ffi")]
+pub mod raw_ffi;
+mod recover;
+mod registry;
+mod rename;
+#[cfg(feature = "capture")]
+mod replay;
+mod retry;
+mod ring;
+mod script;
+mod setup;
+mod shutdown;
+mod stats;
+mod status;
+mod sync;
+#[cfg(feature = "metrics")]
+mod telemetry;
+#[cfg(feature = "test-util")]
+mod temp;
+#[cfg(feature = "tun2")]
+mod tun;
+mod unconfigured;
+mod vlan;
+mod wmi;
+
+pub use address::AddressOptions;
+pub use binding::Binding;
+#[cfg(feature = "broker")]
+pub use broker::{BrokerClient, BrokerServer};
+#[cfg(feature = "capture")]
+pub use capture::{Capture, CaptureCallback, Direction};
+pub use claim::AdapterClaim;
+pub use config::{AddressConfig, DeviceConfig, RouteConfig};
+#[cfg(feature = "dhcp")]
+pub use dhcp::{DhcpConfig, DhcpServer};
+pub use dscp::DscpClassifier;
+pub use elevation::is_elevated;
+#[cfg(feature = "test-util")]
+pub use fault::{FaultConfig, FaultyDevice, LinkStatus};
+pub use filter::{FilterAction, FrameFilter};
+#[cfg(feature = "etherparse")]
+pub use frame::{Frame, FrameBuilder};
+pub use group::{DeviceGroup, GroupError};
+pub use hook::CommandHook;
+#[cfg(feature = "icmp")]
+pub use icmp::IcmpResponder;
+pub use info::DeviceInfo;
+#[cfg(feature = "keepalive")]
+pub use keepalive::{Keepalive, KeepaliveConfig, LinkHealth};
+pub use mac::{MacAddress, ParseMacError};
+#[cfg(feature = "test-util")]
+pub use mock::{MockDevice, MockHandle};
+pub use mux::DeviceSet;
+pub use name::{validate_name, InvalidName};
+pub use notify::{DeviceEvent, DeviceWatcher, StatusEvent, StatusWatcher};
+pub use openvpn::{
+    ParsePushError, PushGateway, PushOption, PushOptions, Topology,
+};
+pub use options::CreateOptions;
+pub use owner::OwnerTag;
+pub use priority::{InterfacePriority, PriorityGuard};
+pub use profile::{NetworkCategory, NetworkProfile};
+pub use properties::{DriverProperties, MediaStatusMode};
+pub use queue::WriteCallback;
+pub use registry::RegValue;
+pub use rename::RenameWatcher;
+#[cfg(feature = "capture")]
+pub use replay::{replay, Pacing};
+pub use retry::RetryPolicy;
+pub use ring::ReadRing;
+pub use setup::DeviceSetup;
+pub use shutdown::ShutdownOptions;
+pub use stats::{InterfaceStats, StatsRate, StatsWatcher, TrafficStats};
+pub use status::DeviceStatus;
+#[cfg(feature = "test-util")]
+pub use temp::TempDevice;
+#[cfg(feature = "tun2")]
+pub use tun::TunDevice;
+pub use unconfigured::UnconfiguredDevice;
+pub use vlan::{pop_vlan_tag, push_vlan_tag, VlanDevice, VlanTag};
+
+/// Hardware id of the tap-windows6 driver shipped with OpenVPN
+pub const DEFAULT_COMPONENT_ID: &str = "tap0901";
+
+/// Hardware ids the tap-windows6 driver has been installed under,
+/// older OpenVPN releases register the adapter as `tap0901` while
+/// 2.5 and later root enumerate it as `root\tap0901`
+pub const KNOWN_COMPONENT_IDS: &[&str] =
+    &[DEFAULT_COMPONENT_ID, r"root\tap0901"];
 
-use std::{io, net, time};
+use overlapped::Overlapped;
+use std::borrow::Cow;
+use std::os::windows::io::{
+    AsHandle, AsRawHandle, BorrowedHandle, FromRawHandle, IntoRawHandle,
+    OwnedHandle,
+};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::{fmt, io, net, ptr, time};
+use sync::Exclusive;
+use winapi::shared::guiddef::GUID;
 use winapi::shared::ifdef::NET_LUID;
+use winapi::shared::netioapi::*;
+use winapi::shared::nldef::*;
+use winapi::shared::winerror::ERROR_INVALID_HANDLE;
+use winapi::shared::ws2def::{ADDRESS_FAMILY, AF_INET6, AF_UNSPEC};
+use winapi::um::winbase::HANDLE_FLAG_INHERIT;
 use winapi::um::winioctl::*;
-use winapi::um::winnt::HANDLE;
+use winapi::um::winnt::{HANDLE, KEY_QUERY_VALUE, KEY_SET_VALUE};
 
 /// A tap-windows device handle, it offers facilities to:
 /// - create, open and delete interfaces
 /// - write and read the current configuration
 /// - write and read packets from the device
+///
+/// `Device` is `Send` and `Sync`, it can be moved to another thread
+/// or shared through an `Arc`. Methods taking `&self` can be called
+/// from several threads at once, reading and writing packets needs
+/// `&mut self`
 /// Example
 /// ```no_run
 /// use tap_windows::Device;
@@ -68,10 +231,80 @@ use winapi::um::winnt::HANDLE;
 /// ```
 pub struct Device {
     luid: NET_LUID,
-    handle: HANDLE,
+    /// `None` only while restarting, or once given away
+    handle: Option<OwnedHandle>,
+    component_id: String,
+    delete_on_drop: bool,
+    auto_reopen: bool,
+    counters: stats::Counters,
+    read_overlapped: Overlapped,
+    write_overlapped: Overlapped,
+    ioctl_overlapped: Mutex<Overlapped>,
+    /// Grown on demand by `write_many`
+    batch_overlapped: Vec<Overlapped>,
+    write_queue: Option<queue::WriteQueue>,
+    rate_limit: Option<ratelimit::TokenBucket>,
+    mss_clamp: Option<u16>,
+    dscp_classifier: Exclusive<Option<Box<DscpClassifier>>>,
+    /// Last known alias, refreshed on `set_name` and by the
+    /// watcher returned from `on_renamed`
+    alias: Arc<Mutex<Option<String>>>,
+    read_filter: Exclusive<Option<Box<FrameFilter>>>,
+    write_filter: Exclusive<Option<Box<FrameFilter>>>,
+    #[cfg(feature = "capture")]
+    capture: Option<Capture>,
+    /// Shared by `read_frame` and `write_frame`
+    #[cfg(feature = "etherparse")]
+    frame_buffer: Vec<u8>,
+}
+
+// Checked at compile time, these types must stay shareable
+#[allow(dead_code)]
+fn assert_send_sync() {
+    fn check<T: Send + Sync>() {}
+
+    check::<Device>();
+    check::<DeviceWatcher>();
+    check::<StatusWatcher>();
+    check::<StatsWatcher>();
+    check::<RenameWatcher>();
 }
 
 impl Device {
+    /// Build a device from an already open handle,
+    /// the handle is closed if this fails
+    fn from_parts(
+        luid: NET_LUID,
+        handle: HANDLE,
+        component_id: &str,
+    ) -> io::Result<Self> {
+        let handle = unsafe { OwnedHandle::from_raw_handle(handle as _) };
+
+        Ok(Self {
+            luid,
+            handle: Some(handle),
+            component_id: component_id.to_owned(),
+            delete_on_drop: false,
+            auto_reopen: false,
+            counters: Default::default(),
+            read_overlapped: Overlapped::new()?,
+            write_overlapped: Overlapped::new()?,
+            ioctl_overlapped: Mutex::new(Overlapped::new()?),
+            batch_overlapped: Vec::new(),
+            write_queue: None,
+            rate_limit: None,
+            mss_clamp: None,
+            dscp_classifier: Exclusive::new(None),
+            alias: Arc::new(Mutex::new(None)),
+            read_filter: Exclusive::new(None),
+            write_filter: Exclusive::new(None),
+            #[cfg(feature = "capture")]
+            capture: None,
+            #[cfg(feature = "etherparse")]
+            frame_buffer: Vec::new(),
+        })
+    }
+
     /// Creates a new tap-windows device
     /// Example
     /// ```no_run
@@ -83,30 +316,129 @@ impl Device {
     /// println!("{:?}", dev.get_name());
     /// ```
     pub fn create() -> io::Result<Self> {
-        let luid = iface::create_interface()?;
+        Self::create_with(CreateOptions::default())
+    }
+
+    /// Creates a new tap-windows device and names it, if the
+    /// rename fails the new device is deleted
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    ///
+    /// let dev = Device::create_named(DEFAULT_COMPONENT_ID, "My Interface")
+    ///     .expect("Failed to create device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn create_named(component_id: &str, name: &str) -> io::Result<Self> {
+        Self::create_with(CreateOptions {
+            component_id,
+            name: Some(name),
+            ..Default::default()
+        })
+    }
+
+    /// Creates a new tap-windows device with a chosen guid, keeping
+    /// the guid (and device path) stable across reinstalls
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    /// use winapi::shared::guiddef::GUID;
+    ///
+    /// let guid = GUID {
+    ///     Data1: 0x6b9e3a1c,
+    ///     Data2: 0x52f4,
+    ///     Data3: 0x4b8d,
+    ///     Data4: [0x9a, 0x07, 0x1e, 0x3c, 0x5d, 0x2f, 0x80, 0x11],
+    /// };
+    ///
+    /// let dev = Device::create_with_guid(DEFAULT_COMPONENT_ID, &guid)
+    ///     .expect("Failed to create device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn create_with_guid(
+        component_id: &str,
+        guid: &GUID,
+    ) -> io::Result<Self> {
+        Self::create_with(CreateOptions {
+            component_id,
+            guid: Some(*guid),
+            ..Default::default()
+        })
+    }
+
+    /// Creates a new tap-windows device with the given options.
+    /// Description, mac and guid are written before the device is
+    /// first started, so no restart is needed to apply them.
+    /// If any step fails the new device is deleted.
+    /// Requires administrator rights, a `PermissionDenied` error is
+    /// returned otherwise
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{CreateOptions, Device};
+    ///
+    /// let dev = Device::create_with(CreateOptions {
+    ///     name: Some("My Interface"),
+    ///     description: Some("Acme VPN Adapter"),
+    ///     ..Default::default()
+    /// })
+    /// .expect("Failed to create device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn create_with(options: CreateOptions) -> io::Result<Self> {
+        elevation::require_elevated()?;
+
+        debug!(component_id = options.component_id, "creating interface");
+
+        let luid = match iface::create_interface(&options) {
+            Ok(luid) => luid,
+            Err(err) => {
+                warn!(error = %err, "failed to create interface");
+                return Err(err);
+            }
+        };
 
         // Even after retrieving the luid, we might need to wait
-        let start = time::Instant::now();
-        let handle = loop {
-            // If we surpassed 2 seconds just return
-            let now = time::Instant::now();
-            if now - start > time::Duration::from_secs(2) {
-                return Err(io::Error::new(
-                    io::ErrorKind::TimedOut,
-                    "Interface timed out",
-                ));
+        let handle = match iface::wait_open_interface(
+            &luid,
+            options.inheritable,
+            options.open_timeout,
+            options.poll_interval,
+        ) {
+            Ok(handle) => handle,
+            Err(err) => {
+                warn!(
+                    luid = luid.Value,
+                    error = %err,
+                    "new interface timed out"
+                );
+
+                let _ = iface::delete_interface(options.component_id, &luid);
+                return Err(err);
             }
+        };
 
-            match iface::open_interface(&luid) {
-                Err(_) => {
-                    std::thread::yield_now();
-                    continue;
-                }
-                Ok(handle) => break handle,
-            };
+        debug!(luid = luid.Value, "created interface");
+
+        let dev = match Self::from_parts(luid, handle, options.component_id) {
+            Ok(dev) => dev,
+            Err(err) => {
+                let _ = iface::delete_interface(options.component_id, &luid);
+                return Err(err);
+            }
         };
 
-        Ok(Self { luid, handle })
+        if let Some(name) = options.name {
+            if let Err(err) = dev.set_name(name) {
+                // Roll back, don't leave an unnamed adapter behind
+                let _ = dev.delete();
+                return Err(err);
+            }
+        }
+
+        Ok(dev)
     }
 
     /// Opens an existing tap-windows device by name
@@ -123,16 +455,254 @@ impl Device {
         let name = encode_utf16(name);
 
         let luid = ffi::alias_to_luid(&name)?;
-        iface::check_interface(&luid)?;
 
-        let handle = iface::open_interface(&luid)?;
+        Self::open_by_luid(DEFAULT_COMPONENT_ID, luid)
+    }
+
+    /// Opens an existing tap-windows device by name, retrying while
+    /// the adapter is still held by a process that just exited
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, RetryPolicy};
+    ///
+    /// let policy = RetryPolicy::default();
+    ///
+    /// let dev = Device::open_with_retry("My Own Device", &policy)
+    ///     .expect("Failed to open device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn open_with_retry(
+        name: &str,
+        policy: &RetryPolicy,
+    ) -> io::Result<Self> {
+        let name = encode_utf16(name);
+
+        let luid = ffi::alias_to_luid(&name)?;
+
+        iface::check_interface(DEFAULT_COMPONENT_ID, &luid)?;
+
+        let handle = policy.run(|| iface::open_interface(&luid, false))?;
+
+        Self::from_parts(luid, handle, DEFAULT_COMPONENT_ID)
+    }
+
+    /// Opens the first adapter bound to the given hardware id that is
+    /// not already in use, the same way OpenVPN picks a free adapter
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    ///
+    /// let dev = Device::open_any(DEFAULT_COMPONENT_ID)
+    ///     .or_else(|_| Device::create())
+    ///     .expect("Failed to open device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn open_any(component_id: &str) -> io::Result<Self> {
+        for interface in iface::enum_interfaces(component_id)? {
+            // Adapters held by another process fail with
+            // ERROR_GEN_FAILURE, just skip them
+            if let Ok(handle) = iface::open_interface(&interface.luid, false) {
+                return Self::from_parts(interface.luid, handle, component_id);
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No available device found",
+        ))
+    }
+
+    /// Opens the first free adapter bound to the given hardware id,
+    /// like `open_any`, and renames it, reusing an adapter left by
+    /// OpenVPN or a previous run instead of creating a new one.
+    /// A free adapter already named `new_name` is preferred. If the
+    /// rename fails the adapter is left as it was
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    ///
+    /// let name = "My Interface";
+    /// let dev = Device::adopt_any(DEFAULT_COMPONENT_ID, name)
+    ///     .or_else(|_| Device::create_named(DEFAULT_COMPONENT_ID, name))
+    ///     .expect("Failed to get a device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn adopt_any(component_id: &str, new_name: &str) -> io::Result<Self> {
+        let luid = ffi::alias_to_luid(&encode_utf16(new_name));
+        if let Ok(luid) = luid {
+            if iface::check_interface(component_id, &luid).is_ok() {
+                if let Ok(handle) = iface::open_interface(&luid, false) {
+                    return Self::from_parts(luid, handle, component_id);
+                }
+            }
+        }
+
+        let dev = Self::open_any(component_id)?;
+        dev.set_name(new_name)?;
+
+        debug!(luid = dev.luid.Value, new_name, "adopted interface");
+
+        Ok(dev)
+    }
+
+    /// Opens an existing tap-windows device by guid, unlike the
+    /// name the guid of an adapter is stable across renames
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    /// use winapi::shared::guiddef::GUID;
+    ///
+    /// # let guid: GUID = unsafe { std::mem::zeroed() };
+    /// let dev = Device::open_by_guid(DEFAULT_COMPONENT_ID, &guid)
+    ///     .expect("Failed to open device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn open_by_guid(component_id: &str, guid: &GUID) -> io::Result<Self> {
+        let luid = ffi::guid_to_luid(guid)?;
+
+        Self::open_by_luid(component_id, luid)
+    }
+
+    /// Opens an existing tap-windows device by interface index,
+    /// as reported by GetAdaptersAddresses or the routing table
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    ///
+    /// let dev = Device::open_by_index(DEFAULT_COMPONENT_ID, 12)
+    ///     .expect("Failed to open device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn open_by_index(component_id: &str, index: u32) -> io::Result<Self> {
+        let luid = ffi::index_to_luid(index)?;
+
+        Self::open_by_luid(component_id, luid)
+    }
+
+    /// Opens an existing tap-windows device by luid, useful when
+    /// the luid was already obtained from the ip helper apis
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    /// use winapi::shared::ifdef::NET_LUID;
+    ///
+    /// # let luid = NET_LUID { Value: 0 };
+    /// let dev = Device::open_by_luid(DEFAULT_COMPONENT_ID, luid)
+    ///     .expect("Failed to open device");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn open_by_luid(
+        component_id: &str,
+        luid: NET_LUID,
+    ) -> io::Result<Self> {
+        iface::check_interface(component_id, &luid)?;
+
+        let handle = match iface::open_interface(&luid, false) {
+            Ok(handle) => handle,
+            Err(err) => {
+                warn!(
+                    luid = luid.Value,
+                    error = %err,
+                    "failed to open interface"
+                );
+                return Err(err);
+            }
+        };
+
+        debug!(luid = luid.Value, component_id, "opened interface");
+
+        Self::from_parts(luid, handle, component_id)
+    }
+
+    /// Builds a device from a handle opened elsewhere, for example
+    /// by a privileged broker process that passed it to this one.
+    /// The handle is closed if this fails
+    ///
+    /// # Safety
+    /// `handle` must be an open handle to the tap device with the
+    /// given luid, opened with `FILE_FLAG_OVERLAPPED`, and must not
+    /// be used or closed by anyone else afterwards
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    ///
+    /// let (handle, luid) = Device::open("My Own Device")
+    ///     .expect("Failed to open device")
+    ///     .into_raw_parts();
+    ///
+    /// let dev = unsafe {
+    ///     Device::from_raw_parts(handle, luid, DEFAULT_COMPONENT_ID)
+    /// }
+    /// .expect("Failed to build device");
+    /// ```
+    pub unsafe fn from_raw_parts(
+        handle: HANDLE,
+        luid: NET_LUID,
+        component_id: &str,
+    ) -> io::Result<Self> {
+        Self::from_parts(luid, handle, component_id)
+    }
+
+    /// Decomposes the device into its handle and luid, the handle
+    /// is left open and the interface is never deleted, even if
+    /// `set_delete_on_drop` was used. Pending queued writes are
+    /// completed first. The handle is null if a failed `restart`
+    /// or `reopen` closed it
+    pub fn into_raw_parts(mut self) -> (HANDLE, NET_LUID) {
+        // The queued buffers must outlive the writes
+        if let (Ok(handle), Some(mut queue)) =
+            (self.raw_handle(), self.write_queue.take())
+        {
+            let _ = queue.flush(handle);
+        }
+
+        let handle = match self.handle.take() {
+            Some(handle) => handle.into_raw_handle() as HANDLE,
+            None => ptr::null_mut(),
+        };
+        self.delete_on_drop = false;
+
+        (handle, self.luid)
+    }
+
+    /// Lists every adapter bound to the given hardware id
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    ///
+    /// let infos = Device::enumerate(DEFAULT_COMPONENT_ID)
+    ///     .expect("Failed to enumerate devices");
+    ///
+    /// for info in infos {
+    ///     let in_use = info.is_in_use().unwrap_or(false);
+    ///     println!("{} {} in use: {}", info.name, info.guid, in_use);
+    /// }
+    /// ```
+    pub fn enumerate(component_id: &str) -> io::Result<Vec<DeviceInfo>> {
+        info::enumerate(component_id)
+    }
 
-        Ok(Self { luid, handle })
+    /// Lists every adapter bound to the given hardware id by reading
+    /// the network registry keys, the same way OpenVPN's tapctl does.
+    /// Useful when SetupDi enumeration is slow or blocked,
+    /// `enumerate` already falls back to this when SetupDi fails
+    pub fn enumerate_registry(
+        component_id: &str,
+    ) -> io::Result<Vec<DeviceInfo>> {
+        info::enumerate_registry(component_id)
     }
 
     /// Deletes the interface before closing it.
     /// By default interfaces are never deleted on Drop,
-    /// with this you can choose if you want deletion or not
+    /// with this (or `set_delete_on_drop`) you can choose
+    /// if you want deletion or not.
+    /// Requires administrator rights, like `create`
     /// Example
     /// ```no_run
     /// use tap_windows::Device;
@@ -145,12 +715,155 @@ impl Device {
     /// // Perform a quick cleanup before exiting
     /// dev.delete().expect("Failed to delete device");
     /// ```
-    pub fn delete(self) -> io::Result<()> {
-        iface::delete_interface(&self.luid)?;
+    pub fn delete(mut self) -> io::Result<()> {
+        // Already gone, don't try again on drop
+        self.delete_on_drop = false;
+
+        elevation::require_elevated()?;
+
+        debug!(luid = self.luid.Value, "deleting interface");
+        iface::delete_interface(&self.component_id, &self.luid)?;
 
         Ok(())
     }
 
+    /// Choose whether the interface is deleted when the device is
+    /// dropped, this also happens while unwinding from a panic.
+    /// Errors during deletion on drop are ignored
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let mut dev = Device::create()
+    ///     .expect("Failed to create device");
+    ///
+    /// // The interface goes away together with dev
+    /// dev.set_delete_on_drop(true);
+    /// ```
+    pub fn set_delete_on_drop(&mut self, delete_on_drop: bool) {
+        self.delete_on_drop = delete_on_drop;
+    }
+
+    /// Deletes an adapter by name without opening it, useful for
+    /// cleaning up adapters whose handle can't be opened anymore
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    ///
+    /// Device::delete_by_name(DEFAULT_COMPONENT_ID, "My Own Device")
+    ///     .expect("Failed to delete device");
+    /// ```
+    pub fn delete_by_name(component_id: &str, name: &str) -> io::Result<()> {
+        elevation::require_elevated()?;
+
+        let luid = ffi::alias_to_luid(&encode_utf16(name))?;
+
+        debug!(luid = luid.Value, name, "deleting interface");
+        iface::delete_interface(component_id, &luid)
+    }
+
+    /// Restarts the adapter by disabling and enabling it, this applies
+    /// changes made to the driver registry key (mac, mtu, media status)
+    /// and can recover a wedged driver without deleting the adapter.
+    /// The device handle is closed and reopened in the process, if
+    /// it can't be reopened the device fails every operation until
+    /// `reopen` succeeds
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.restart().expect("Failed to restart device");
+    /// ```
+    pub fn restart(&mut self) -> io::Result<()> {
+        if let (Ok(handle), Some(queue)) =
+            (self.raw_handle(), &mut self.write_queue)
+        {
+            let _ = queue.flush(handle);
+        }
+
+        // The new handle keeps the same inheritability
+        let inheritable = self.is_inheritable().unwrap_or(false);
+
+        // The driver refuses to stop while a handle is open
+        self.handle = None;
+
+        let res = iface::restart_interface(&self.component_id, &self.luid);
+
+        // Opened again even if the restart failed, in which case the
+        // adapter is usually still running
+        let handle = iface::wait_open_interface(
+            &self.luid,
+            inheritable,
+            options::DEFAULT_OPEN_TIMEOUT,
+            options::DEFAULT_POLL_INTERVAL,
+        );
+        if let Ok(handle) = handle {
+            self.handle =
+                Some(unsafe { OwnedHandle::from_raw_handle(handle as _) });
+        }
+
+        res.and(handle.map(|_| ()))
+    }
+
+    /// Watch the adapter for removal and arrival, for example when it
+    /// is disabled from Device Manager or its driver is updated.
+    /// The callback runs on a system thread until the returned
+    /// watcher is dropped. Requires Windows 8 or later
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DeviceEvent};
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let _watcher = dev
+    ///     .watch_device(|event| {
+    ///         if event == DeviceEvent::Removed {
+    ///             println!("Adapter removed");
+    ///         }
+    ///     })
+    ///     .expect("Failed to watch device");
+    /// ```
+    pub fn watch_device<F>(&self, callback: F) -> io::Result<DeviceWatcher>
+    where
+        F: Fn(DeviceEvent) + Send + Sync + 'static,
+    {
+        let instance_id =
+            iface::get_instance_id(&self.component_id, &self.luid)?;
+
+        DeviceWatcher::new(&instance_id, Box::new(callback))
+    }
+
+    /// Watch the operational status of the interface, so that
+    /// Windows tearing it down (sleep, driver reset) is noticed
+    /// right away instead of through read errors.
+    /// The callback runs on a system thread until the returned
+    /// watcher is dropped
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, StatusEvent};
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let _watcher = dev
+    ///     .watch_status(|event| {
+    ///         if event == StatusEvent::Down {
+    ///             println!("Interface went down");
+    ///         }
+    ///     })
+    ///     .expect("Failed to watch status");
+    /// ```
+    pub fn watch_status<F>(&self, callback: F) -> io::Result<StatusWatcher>
+    where
+        F: Fn(StatusEvent) + Send + Sync + 'static,
+    {
+        StatusWatcher::new(self.luid, Box::new(callback))
+    }
+
     /// Sets the status of the interface to connected.
     /// Equivalent to `.set_status(true)`
     pub fn up(&self) -> io::Result<()> {
@@ -164,24 +877,22 @@ impl Device {
     }
 
     /// Retieve the mac of the interface
-    pub fn get_mac(&self) -> io::Result<[u8; 6]> {
+    pub fn get_mac(&self) -> io::Result<MacAddress> {
         let mut mac = [0; 6];
 
-        ffi::device_io_control(
-            self.handle,
+        self.device_io_control(
             CTL_CODE(FILE_DEVICE_UNKNOWN, 1, METHOD_BUFFERED, FILE_ANY_ACCESS),
             &(),
             &mut mac,
         )
-        .map(|_| mac)
+        .map(|_| MacAddress(mac))
     }
 
     /// Retrieve the version of the driver
     pub fn get_version(&self) -> io::Result<[u32; 3]> {
         let mut version = [0; 3];
 
-        ffi::device_io_control(
-            self.handle,
+        self.device_io_control(
             CTL_CODE(FILE_DEVICE_UNKNOWN, 2, METHOD_BUFFERED, FILE_ANY_ACCESS),
             &(),
             &mut version,
@@ -193,8 +904,7 @@ impl Device {
     pub fn get_mtu(&self) -> io::Result<u32> {
         let mut mtu = 0;
 
-        ffi::device_io_control(
-            self.handle,
+        self.device_io_control(
             CTL_CODE(FILE_DEVICE_UNKNOWN, 3, METHOD_BUFFERED, FILE_ANY_ACCESS),
             &(),
             &mut mtu,
@@ -202,73 +912,1113 @@ impl Device {
         .map(|_| mtu)
     }
 
-    /// Retrieve the name of the interface
-    pub fn get_name(&self) -> io::Result<String> {
-        ffi::luid_to_alias(&self.luid).map(|name| decode_utf16(&name))
-    }
+    /// Retrieve a snapshot of the adapter state
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let info = dev.get_info().expect("Failed to get device info");
+    /// println!("{} mtu: {} ips: {:?}", info.name, info.mtu, info.ips);
+    /// ```
+    pub fn get_info(&self) -> io::Result<DeviceInfo> {
+        let interface = iface::get_interface(&self.component_id, &self.luid)?;
 
-    /// Set the name of the interface
-    pub fn set_name(&self, newname: &str) -> io::Result<()> {
-        let name = self.get_name()?;
-        netsh::set_interface_name(&name, newname)
+        info::snapshot(&self.component_id, interface)
     }
 
-    /// Set the ip of the interface
+    /// Retrieve the traffic counters of the interface
+    /// Example
     /// ```no_run
     /// use tap_windows::Device;
     ///
-    /// let dev = Device::create()
-    ///     .expect("Failed to create device");
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
     ///
-    /// dev.set_ip([192, 168, 60, 1], [255, 255, 255, 0])
-    ///     .expect("Failed to set interface ip");
+    /// let stats = dev.get_stats().expect("Failed to get device stats");
+    /// println!("in: {} out: {}", stats.in_octets, stats.out_octets);
+    /// ```
+    pub fn get_stats(&self) -> io::Result<InterfaceStats> {
+        let row = ffi::get_if_entry2(&self.luid)?;
+
+        Ok(InterfaceStats::from_row(&row))
+    }
+
+    /// Sample the traffic counters of the interface every `interval`
+    /// and pass the rates since the previous sample to `callback`,
+    /// which runs on a dedicated thread until the returned watcher
+    /// is dropped. Samples failing while the interface is gone are
+    /// skipped
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::time::Duration;
     ///
-    /// println!("{:?}", dev.get_name());
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let _watcher = dev
+    ///     .watch_stats(Duration::from_secs(1), |rate| {
+    ///         println!("rx: {} bps tx: {} bps", rate.rx_bps, rate.tx_bps);
+    ///     })
+    ///     .expect("Failed to watch stats");
     /// ```
-    pub fn set_ip<A, B>(&self, address: A, mask: B) -> io::Result<()>
+    pub fn watch_stats<F>(
+        &self,
+        interval: time::Duration,
+        callback: F,
+    ) -> io::Result<StatsWatcher>
     where
-        A: Into<net::Ipv4Addr>,
-        B: Into<net::Ipv4Addr>,
+        F: FnMut(&StatsRate) + Send + 'static,
     {
-        let name = self.get_name()?;
-        let address = address.into().to_string();
-        let mask = mask.into().to_string();
-
-        netsh::set_interface_ip(&name, &address, &mask)
+        StatsWatcher::new(self.luid, interval, callback)
     }
 
-    /// Set the status of the interface, true for connected,
-    /// false for disconnected.
-    pub fn set_status(&self, status: bool) -> io::Result<()> {
-        let status: u32 = if status { 1 } else { 0 };
+    /// Retrieve the frames and bytes read and written through this
+    /// device since it was opened or since the last `reset_stats`
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::io::Read;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let mut buf = [0; 4096];
+    /// dev.read(&mut buf).expect("Failed to read packet");
+    ///
+    /// let stats = dev.stats();
+    /// println!("rx: {} frames {} bytes", stats.rx_frames, stats.rx_bytes);
+    /// ```
+    pub fn stats(&self) -> TrafficStats {
+        self.counters.get()
+    }
 
-        ffi::device_io_control(
-            self.handle,
-            CTL_CODE(FILE_DEVICE_UNKNOWN, 6, METHOD_BUFFERED, FILE_ANY_ACCESS),
-            &status,
-            &mut (),
-        )
+    /// Reset the counters returned by `stats`
+    pub fn reset_stats(&self) {
+        self.counters.reset()
     }
-}
+
+    /// Queue up to `depth` writes without waiting for the driver to
+    /// complete them, a write waits only when the queue is full.
+    /// Errors of queued writes are reported by a later `write` or by
+    /// `flush`, which waits for all of them. A depth of 0 disables
+    /// the queue, the default, after flushing the pending writes
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::io::Write;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_write_queue(16).expect("Failed to enable write queue");
+    ///
+    /// # let frames: Vec<Vec<u8>> = Vec::new();
+    /// for frame in frames {
+    ///     dev.write(&frame).expect("Failed to write frame");
+    /// }
+    ///
+    /// dev.flush().expect("Failed to flush writes");
+    /// ```
+    pub fn set_write_queue(&mut self, depth: usize) -> io::Result<()> {
+        let handle = self.raw_handle()?;
+        if let Some(queue) = &mut self.write_queue {
+            queue.flush(handle)?;
+        }
+
+        self.write_queue = match depth {
+            0 => None,
+            depth => Some(queue::WriteQueue::new(depth)?),
+        };
+
+        Ok(())
+    }
+
+    /// Write a frame without copying it, calling `callback` with the
+    /// result of the write and the frame once the driver is done
+    /// with it, so the buffer can be reused. With the write queue
+    /// enabled the callback runs from a later `write`,
+    /// `write_with_callback`, `poll_writes` or `flush`, otherwise
+    /// before this returns. The callback is always called exactly
+    /// once, errors returned here belong to previous queued writes
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::io::Write;
+    /// use std::sync::mpsc;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_write_queue(16).expect("Failed to enable write queue");
+    ///
+    /// let (pool, free) = mpsc::channel();
+    /// for _ in 0..4 {
+    ///     let frame = vec![0u8; 60];
+    ///     let pool = pool.clone();
+    ///     dev.write_with_callback(frame, move |res, frame| {
+    ///         if let Err(err) = res {
+    ///             println!("Write failed: {}", err);
+    ///         }
+    ///         let _ = pool.send(frame);
+    ///     })
+    ///     .expect("Failed to write frame");
+    /// }
+    ///
+    /// dev.flush().expect("Failed to flush writes");
+    /// assert_eq!(free.try_iter().count(), 4);
+    /// ```
+    pub fn write_with_callback<F>(
+        &mut self,
+        frame: Vec<u8>,
+        callback: F,
+    ) -> io::Result<()>
+    where
+        F: FnOnce(io::Result<()>, Vec<u8>) + Send + 'static,
+    {
+        let handle = match self.raw_handle() {
+            Ok(handle) => handle,
+            Err(err) => {
+                callback(Err(err), frame);
+                return Ok(());
+            }
+        };
+
+        let prepared = match self.prepare_write(&frame) {
+            Some(Cow::Borrowed(_)) => Some(None),
+            Some(Cow::Owned(rewritten)) => Some(Some(rewritten)),
+            None => None,
+        };
+
+        // Frames rewritten along the way are handed back instead
+        let frame = match prepared {
+            Some(rewritten) => rewritten.unwrap_or(frame),
+            None => {
+                callback(Ok(()), frame);
+                return Ok(());
+            }
+        };
+
+        // Only frames the driver accepted are accounted for
+        let counters = &self.counters;
+        #[cfg(feature = "capture")]
+        let capture = self.capture.as_mut();
+        let posted = move |frame: &[u8]| {
+            counters.add_tx(frame.len());
+
+            #[cfg(feature = "capture")]
+            if let Some(capture) = capture {
+                capture.frame(Direction::Tx, frame);
+            }
+        };
+
+        match &mut self.write_queue {
+            Some(queue) => {
+                queue.write_owned(handle, frame, Box::new(callback), posted)
+            }
+            None => {
+                let res =
+                    ffi::write_file(handle, &frame, &mut self.write_overlapped);
+                if let Ok(len) = res {
+                    posted(&frame[..len as usize]);
+                }
+                callback(res.map(|_| ()), frame);
+                Ok(())
+            }
+        }
+    }
+
+    /// Run the callbacks of the queued writes the driver already
+    /// completed, without waiting for the others. Returns the first
+    /// error of a completed write without a callback
+    pub fn poll_writes(&mut self) -> io::Result<()> {
+        let handle = self.raw_handle()?;
+        match &mut self.write_queue {
+            Some(queue) => queue.poll(handle),
+            None => Ok(()),
+        }
+    }
+
+    /// Write several frames at once, all the writes are submitted
+    /// before waiting for any of them, which saves a round trip per
+    /// frame compared to calling `write` in a loop. Frames go through
+    /// the same hooks as with `write`.
+    /// Returns the number of frames written, or the first error
+    /// encountered after waiting for every submitted write
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// # let (first, second) = (vec![0u8; 60], vec![0u8; 60]);
+    /// dev.write_many(&[&first, &second])
+    ///     .expect("Failed to write frames");
+    /// ```
+    pub fn write_many(&mut self, frames: &[&[u8]]) -> io::Result<usize> {
+        let handle = self.raw_handle()?;
+
+        while self.batch_overlapped.len() < frames.len() {
+            self.batch_overlapped.push(Overlapped::new()?);
+        }
+
+        // Rewritten frames must outlive their writes
+        let mut posted = Vec::with_capacity(frames.len());
+        let mut res = Ok(());
+        let mut written = 0;
+
+        for frame in frames {
+            // Dropped frames count as written, as with `write`
+            let frame = match self.prepare_write(frame) {
+                Some(frame) => frame,
+                None => {
+                    written += 1;
+                    continue;
+                }
+            };
+
+            let overlapped = &mut self.batch_overlapped[posted.len()];
+            if let Err(err) =
+                ffi::write_file_overlapped(handle, &frame, overlapped)
+            {
+                res = Err(err);
+                break;
+            }
+
+            posted.push(frame);
+            written += 1;
+        }
+
+        // Every posted write must complete before the frames
+        // can be given back to the caller
+        for (_frame, overlapped) in
+            posted.iter().zip(&mut self.batch_overlapped)
+        {
+            match ffi::get_overlapped_result(handle, overlapped, true) {
+                Ok(len) => {
+                    self.counters.add_tx(len as usize);
+
+                    #[cfg(feature = "capture")]
+                    if let Some(capture) = &mut self.capture {
+                        capture.frame(Direction::Tx, &_frame[..len as usize]);
+                    }
+                }
+                Err(err) => res = res.and(Err(err)),
+            }
+        }
+
+        res.map(|_| written)
+    }
+
+    /// Keep `depth` reads of up to `frame_size` bytes pending on the
+    /// device, for higher receive throughput than calling `read` in
+    /// a loop. The device can't be used while the ring exists, and
+    /// the pending reads are cancelled when the ring is dropped
+    pub fn read_ring(
+        &mut self,
+        depth: usize,
+        frame_size: usize,
+    ) -> io::Result<ReadRing<'_>> {
+        ReadRing::new(self, depth, frame_size)
+    }
+
+    /// Install a capture receiving every frame read from and written
+    /// to the device, `None` stops the current capture.
+    /// Requires the `capture` feature
+    #[cfg(feature = "capture")]
+    pub fn set_capture(&mut self, capture: Option<Capture>) {
+        self.capture = capture;
+    }
+
+    /// Allow or forbid child processes from inheriting the handle,
+    /// an inheritable handle is passed to every child created with
+    /// `bInheritHandles`, for example a sandboxed worker process
+    /// that gets the handle value through its command line and
+    /// rebuilds the device with `Device::from_raw_parts`
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::os::windows::io::AsRawHandle;
+    /// use std::process::Command;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_inheritable(true)
+    ///     .expect("Failed to make the handle inheritable");
+    ///
+    /// let handle = dev.handle()
+    ///     .expect("Failed to get handle")
+    ///     .as_raw_handle() as usize;
+    /// Command::new("worker.exe")
+    ///     .arg(handle.to_string())
+    ///     .spawn()
+    ///     .expect("Failed to spawn worker");
+    /// ```
+    pub fn set_inheritable(&self, inheritable: bool) -> io::Result<()> {
+        let flags = match inheritable {
+            true => HANDLE_FLAG_INHERIT,
+            false => 0,
+        };
+
+        ffi::set_handle_information(
+            self.raw_handle()?,
+            HANDLE_FLAG_INHERIT,
+            flags,
+        )
+    }
+
+    /// Check if child processes can inherit the handle
+    pub fn is_inheritable(&self) -> io::Result<bool> {
+        ffi::get_handle_information(self.raw_handle()?)
+            .map(|flags| flags & HANDLE_FLAG_INHERIT != 0)
+    }
+
+    /// Duplicate the handle into another process, which can pass it
+    /// to `Device::from_raw_parts` together with the luid. Lets an
+    /// elevated helper create and configure the adapter and hand
+    /// only the datapath to an unprivileged process.
+    /// `process` needs the `PROCESS_DUP_HANDLE` access right, the
+    /// returned value is only meaningful inside that process.
+    /// Fails if a `restart` or `reopen` couldn't open the interface
+    /// again, rather than handing out some other handle
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use winapi::um::processthreadsapi::OpenProcess;
+    /// use winapi::um::winnt::PROCESS_DUP_HANDLE;
+    ///
+    /// let dev = Device::create()
+    ///     .expect("Failed to create device");
+    /// dev.set_ip([192, 168, 60, 1], [255, 255, 255, 0])
+    ///     .expect("Failed to set device ip");
+    ///
+    /// # let pid = 0;
+    /// let process = unsafe { OpenProcess(PROCESS_DUP_HANDLE, 0, pid) };
+    ///
+    /// let handle = dev.duplicate_to(process)
+    ///     .expect("Failed to duplicate handle");
+    ///
+    /// // Send handle and dev.get_luid() to the other process
+    /// println!("{:?} {}", handle, dev.get_luid().Value);
+    /// ```
+    pub fn duplicate_to(&self, process: HANDLE) -> io::Result<HANDLE> {
+        let handle = self.handle()?;
+        ffi::duplicate_handle(handle.as_raw_handle() as _, process, false)
+    }
+
+    /// Borrow the device handle, for use with crates taking the std
+    /// handle types. Fails if a `restart` or `reopen` couldn't open
+    /// the interface again
+    pub fn handle(&self) -> io::Result<BorrowedHandle<'_>> {
+        match &self.handle {
+            Some(handle) => Ok(handle.as_handle()),
+            None => {
+                Err(io::Error::from_raw_os_error(ERROR_INVALID_HANDLE as _))
+            }
+        }
+    }
+
+    /// Retrieve the hardware id of the driver bound to the interface
+    pub fn component_id(&self) -> &str {
+        &self.component_id
+    }
+
+    /// Retrieve the preferred device path of the interface, in the
+    /// `\\.\Global\{GUID}.tap` form unless a template was set with
+    /// `set_path_template`
+    pub fn get_path(&self) -> io::Result<String> {
+        iface::interface_path(&self.luid)
+    }
+
+    /// Set a custom device path to try first when opening any
+    /// interface in this process, before `\\.\Global\{GUID}.tap` and
+    /// `\\.\{GUID}.tap`. `{guid}` is replaced with the braced guid of
+    /// the interface. `None` goes back to the default paths
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// Device::set_path_template(Some(r"\\?\GLOBALROOT\Device\{guid}.tap"));
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    /// ```
+    pub fn set_path_template(template: Option<&str>) {
+        iface::set_path_template(template)
+    }
+
+    /// Retrieve the luid of the interface
+    pub fn get_luid(&self) -> NET_LUID {
+        self.luid
+    }
+
+    /// Retrieve the guid of the interface
+    pub fn get_guid(&self) -> io::Result<GUID> {
+        ffi::luid_to_guid(&self.luid)
+    }
+
+    /// Retrieve the index of the interface
+    pub fn get_index(&self) -> io::Result<u32> {
+        ffi::luid_to_index(&self.luid)
+    }
+
+    /// Retrieve the routing compartment of the interface, 1 being
+    /// the default compartment
+    pub fn get_compartment_id(&self) -> io::Result<u32> {
+        ffi::get_interface_compartment_id(&self.luid)
+    }
+
+    /// Move the interface to another routing compartment, which
+    /// must already exist. Interfaces in different compartments
+    /// have separate routing tables, so the routes of one tunnel
+    /// can't conflict with another. Relies on an undocumented
+    /// iphlpapi export, requires administrator rights
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_compartment_id(2)
+    ///     .expect("Failed to set device compartment");
+    /// ```
+    pub fn set_compartment_id(&self, id: u32) -> io::Result<()> {
+        elevation::require_elevated()?;
+
+        ffi::set_interface_compartment_id(&self.luid, id)
+    }
+
+    /// Retrieve the name of the interface. The name is cached, and the
+    /// cached name is checked to still belong to this interface before
+    /// it is used, so external renames are picked up as well
+    pub fn get_name(&self) -> io::Result<String> {
+        let mut alias = self.lock_alias();
+
+        self.cached_alias(&mut alias)
+    }
+
+    /// Set the name of the interface, names containing characters not
+    /// allowed by Windows are rejected with an `InvalidName` error
+    pub fn set_name(&self, newname: &str) -> io::Result<()> {
+        validate_name(newname)?;
+
+        // Held for the whole rename, so concurrent renames
+        // can't act on a stale name
+        let mut alias = self.lock_alias();
+
+        let name = self.cached_alias(&mut alias)?;
+        let res = netsh::set_interface_name(&name, newname);
+
+        // On failure the name is unknown, look it up again next time
+        *alias = match &res {
+            Ok(_) => Some(newname.to_owned()),
+            Err(_) => None,
+        };
+
+        res
+    }
+
+    /// Issue an ioctl on the device, reusing the same overlapped
+    /// structure to avoid creating an event on every call
+    fn device_io_control(
+        &self,
+        io_control_code: u32,
+        in_buffer: &impl Copy,
+        out_buffer: &mut impl Copy,
+    ) -> io::Result<()> {
+        let handle = self.raw_handle()?;
+        let mut overlapped = self
+            .ioctl_overlapped
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        let res = ffi::device_io_control(
+            handle,
+            io_control_code,
+            in_buffer,
+            out_buffer,
+            &mut overlapped,
+        );
+        drop(overlapped);
+
+        res.map_err(|err| self.legacy_ioctl_error(err))
+    }
+
+    fn lock_alias(&self) -> MutexGuard<'_, Option<String>> {
+        // The cache is always valid, even if a holder panicked
+        self.alias.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn cached_alias(&self, alias: &mut Option<String>) -> io::Result<String> {
+        // The interface may have been renamed by someone else, in which
+        // case the name is gone or belongs to another interface
+        if let Some(name) = alias {
+            match ffi::alias_to_luid(&encode_utf16(name)) {
+                Ok(luid) if luid.Value == self.luid.Value => {
+                    return Ok(name.clone())
+                }
+                _ => {}
+            }
+        }
+
+        let name =
+            ffi::luid_to_alias(&self.luid).map(|name| decode_utf16(&name))?;
+        *alias = Some(name.clone());
+
+        Ok(name)
+    }
+
+    /// Set the description of the adapter shown in Device Manager,
+    /// this is separate from the name of the interface
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::create()
+    ///     .expect("Failed to create device");
+    ///
+    /// dev.set_description("Acme VPN Adapter")
+    ///     .expect("Failed to set device description");
+    /// ```
+    pub fn set_description(&self, description: &str) -> io::Result<()> {
+        iface::set_interface_description(
+            &self.component_id,
+            &self.luid,
+            description,
+        )
+    }
+
+    /// Tag the adapter as owned by the given product, the tag is
+    /// stored in the driver registry key and reported by `enumerate`
+    /// ```no_run
+    /// use tap_windows::{Device, DEFAULT_COMPONENT_ID};
+    ///
+    /// // Only clean up adapters we created ourselves
+    /// for info in Device::enumerate(DEFAULT_COMPONENT_ID).unwrap() {
+    ///     match info.owner {
+    ///         Some(owner) if owner.product == "Acme VPN" => {
+    ///             let _ = Device::delete_by_name(
+    ///                 DEFAULT_COMPONENT_ID,
+    ///                 &info.name,
+    ///             );
+    ///         }
+    ///         _ => (),
+    ///     }
+    /// }
+    /// ```
+    pub fn set_owner(&self, product: &str) -> io::Result<()> {
+        let key = iface::open_driver_key(
+            &self.component_id,
+            &self.luid,
+            KEY_SET_VALUE,
+        )?;
+
+        OwnerTag::new(product).write(&key)
+    }
+
+    /// Retrieve the ownership tag of the adapter, if any
+    pub fn get_owner(&self) -> io::Result<Option<OwnerTag>> {
+        let key = iface::open_driver_key(
+            &self.component_id,
+            &self.luid,
+            KEY_QUERY_VALUE,
+        )?;
+
+        Ok(OwnerTag::read(&key))
+    }
+
+    /// Read a value from the driver registry key of the adapter,
+    /// for driver settings this crate doesn't wrap. Missing values
+    /// fail with `NotFound` and values of another type with
+    /// `InvalidData`
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let mtu: String = dev.get_driver_key_value("MTU")
+    ///     .expect("Failed to read driver mtu");
+    /// ```
+    pub fn get_driver_key_value<T: RegValue>(
+        &self,
+        name: &str,
+    ) -> io::Result<T> {
+        let key = iface::open_driver_key(
+            &self.component_id,
+            &self.luid,
+            KEY_QUERY_VALUE,
+        )?;
+
+        key.get_value(name)
+    }
+
+    /// Write a value to the driver registry key of the adapter, the
+    /// driver usually reads its settings when starting so `restart`
+    /// is needed to apply them. Requires administrator rights
+    pub fn set_driver_key_value<T: RegValue>(
+        &self,
+        name: &str,
+        value: &T,
+    ) -> io::Result<()> {
+        elevation::require_elevated()?;
+
+        let key = iface::open_driver_key(
+            &self.component_id,
+            &self.luid,
+            KEY_SET_VALUE,
+        )?;
+
+        key.set_value(name, value)
+    }
+
+    /// Set the ip of the interface
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::create()
+    ///     .expect("Failed to create device");
+    ///
+    /// dev.set_ip([192, 168, 60, 1], [255, 255, 255, 0])
+    ///     .expect("Failed to set interface ip");
+    ///
+    /// println!("{:?}", dev.get_name());
+    /// ```
+    pub fn set_ip<A, B>(&self, address: A, mask: B) -> io::Result<()>
+    where
+        A: Into<net::Ipv4Addr>,
+        B: Into<net::Ipv4Addr>,
+    {
+        let index = self.get_index()?;
+        let address = address.into().to_string();
+        let mask = mask.into().to_string();
+
+        netsh::set_interface_ip(index, &address, &mask)
+    }
+
+    /// Set the dns servers of the interface, both ipv4 and ipv6
+    /// servers are accepted, an empty slice clears them.
+    /// Uses SetInterfaceDnsSettings when available (Windows 10 2004+),
+    /// falling back to wmi and then to netsh on older systems
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_dns(&["1.1.1.1".parse().unwrap()])
+    ///     .expect("Failed to set interface dns");
+    /// ```
+    pub fn set_dns(&self, servers: &[net::IpAddr]) -> io::Result<()> {
+        dns::set_interface_dns(&self.luid, servers)
+    }
+
+    /// Set the status of the interface, true for connected,
+    /// false for disconnected.
+    pub fn set_status(&self, status: bool) -> io::Result<()> {
+        let status: u32 = if status { 1 } else { 0 };
+
+        self.device_io_control(
+            CTL_CODE(FILE_DEVICE_UNKNOWN, 6, METHOD_BUFFERED, FILE_ANY_ACCESS),
+            &status,
+            &mut (),
+        )
+    }
+
+    /// Set the address of the interface, replacing the manually
+    /// configured addresses of the same family only, so ipv4 and
+    /// ipv6 can be set independently
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::net::Ipv6Addr;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_address([10, 8, 0, 2], 24)
+    ///     .expect("Failed to set ipv4 address");
+    /// dev.set_address("fd00::2".parse::<Ipv6Addr>().unwrap(), 64)
+    ///     .expect("Failed to set ipv6 address");
+    /// ```
+    pub fn set_address<A>(&self, address: A, prefix: u8) -> io::Result<()>
+    where
+        A: Into<net::IpAddr>,
+    {
+        config::set_address(
+            self,
+            AddressConfig {
+                address: address.into(),
+                prefix,
+            },
+        )
+    }
+
+    /// Set every manually configured address of the interface at
+    /// once, of both families. Addresses missing from the list are
+    /// removed, those already present are left untouched
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_addresses(&[
+    ///     ("10.8.0.2".parse().unwrap(), 24),
+    ///     ("fd00::2".parse().unwrap(), 64),
+    /// ])
+    /// .expect("Failed to set addresses");
+    /// ```
+    pub fn set_addresses(
+        &self,
+        addresses: &[(net::IpAddr, u8)],
+    ) -> io::Result<()> {
+        let addresses: Vec<_> = addresses
+            .iter()
+            .map(|&(address, prefix)| AddressConfig { address, prefix })
+            .collect();
+
+        config::apply_addresses(self, &addresses)
+    }
+
+    /// Add an address to the interface, both ipv4 and ipv6
+    /// addresses are accepted
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::net::Ipv6Addr;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.add_address("fd00::1".parse::<Ipv6Addr>().unwrap(), 64)
+    ///     .expect("Failed to add address");
+    /// ```
+    pub fn add_address<A>(&self, address: A, prefix: u8) -> io::Result<()>
+    where
+        A: Into<net::IpAddr>,
+    {
+        self.add_address_with(address, prefix, &AddressOptions::default())
+    }
+
+    /// Add an address to the interface with the given options
+    /// ```no_run
+    /// use tap_windows::{AddressOptions, Device};
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// // Reachable, but never picked as a source address
+    /// let options = AddressOptions {
+    ///     skip_as_source: true,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// dev.add_address_with([10, 0, 0, 2], 24, &options)
+    ///     .expect("Failed to add address");
+    /// ```
+    pub fn add_address_with<A>(
+        &self,
+        address: A,
+        prefix: u8,
+        options: &AddressOptions,
+    ) -> io::Result<()>
+    where
+        A: Into<net::IpAddr>,
+    {
+        let mut row = ffi::initialize_unicast_ip_address_entry();
+        row.Address = ffi::sockaddr_inet_from_ip(&address.into());
+        row.InterfaceLuid = self.luid;
+        row.OnLinkPrefixLength = prefix;
+        row.SkipAsSource = options.skip_as_source as _;
+        row.ValidLifetime = address::lifetime_to_secs(options.valid_lifetime);
+        row.PreferredLifetime =
+            address::lifetime_to_secs(options.preferred_lifetime);
+
+        ffi::create_unicast_ip_address_entry(&row)
+    }
+
+    /// Removes every unicast address (both ipv4 and ipv6) configured
+    /// on the interface, link-local addresses are left untouched
+    pub fn flush_addresses(&self) -> io::Result<()> {
+        let rows = ffi::get_unicast_ip_address_table(AF_UNSPEC as _)?;
+
+        rows.iter()
+            .filter(|row| row.InterfaceLuid.Value == self.luid.Value)
+            .filter(|row| row.PrefixOrigin != IpPrefixOriginWellKnown)
+            .try_for_each(ffi::delete_unicast_ip_address_entry)
+    }
+
+    /// Enable or disable ipv6 router discovery on the interface.
+    /// Virtual links usually have no router to discover,
+    /// disabling it avoids waiting for router solicitations
+    pub fn set_router_discovery(&self, enabled: bool) -> io::Result<()> {
+        self.update_ip_interface(AF_INET6 as _, |row| {
+            row.RouterDiscoveryBehavior = if enabled {
+                RouterDiscoveryEnabled
+            } else {
+                RouterDiscoveryDisabled
+            };
+        })
+    }
+
+    /// Enable or disable sending ipv6 router advertisements
+    /// from the interface
+    pub fn set_router_advertisement(&self, enabled: bool) -> io::Result<()> {
+        self.update_ip_interface(AF_INET6 as _, |row| {
+            row.AdvertisingEnabled = enabled as _;
+        })
+    }
+
+    /// Set the number of duplicate address detection probes sent
+    /// for each new ipv6 address, 0 skips DAD altogether making
+    /// addresses usable immediately
+    pub fn set_dad_transmits(&self, count: u32) -> io::Result<()> {
+        self.update_ip_interface(AF_INET6 as _, |row| {
+            row.DadTransmits = count;
+        })
+    }
+
+    /// Apply a configuration to the device, fields set to `None` are
+    /// left untouched and only what differs from the current state
+    /// is changed, see `DeviceConfig`
+    pub fn apply(&self, config: &DeviceConfig) -> io::Result<()> {
+        config::apply(self, config)
+    }
+
+    /// Read the current configuration of the device, every field
+    /// is set. Addresses and routes include only manually
+    /// configured entries
+    pub fn current_config(&self) -> io::Result<DeviceConfig> {
+        config::current_config(self)
+    }
+
+    /// Snapshot the name, addresses, dns servers, routes and mtu of
+    /// the device, to be restored with `import_config`, for example
+    /// on a new device after a driver upgrade.
+    /// With the `serde` feature the snapshot can be saved to disk
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let backup = dev.export_config().expect("Failed to export config");
+    /// dev.delete().expect("Failed to delete device");
+    ///
+    /// // ... upgrade the driver ...
+    ///
+    /// let dev = Device::create()
+    ///     .expect("Failed to create device");
+    /// dev.import_config(&backup).expect("Failed to import config");
+    /// ```
+    pub fn export_config(&self) -> io::Result<DeviceConfig> {
+        config::export_config(self)
+    }
+
+    /// Restore a snapshot taken with `export_config`. The media
+    /// status is not part of it and is left untouched
+    pub fn import_config(&self, config: &DeviceConfig) -> io::Result<()> {
+        config::import_config(self, config)
+    }
+
+    /// Read, modify and write back the ip interface entry for a family
+    fn update_ip_interface<F>(
+        &self,
+        family: ADDRESS_FAMILY,
+        update: F,
+    ) -> io::Result<()>
+    where
+        F: FnOnce(&mut MIB_IPINTERFACE_ROW),
+    {
+        let mut row = ffi::get_ip_interface_entry(&self.luid, family)?;
+        update(&mut row);
+        ffi::set_ip_interface_entry(&row)
+    }
+}
+
+impl Device {
+    /// Guid in the `{XXXXXXXX-XXXX-...}` form, for formatting
+    fn guid_string(&self) -> Option<String> {
+        let guid = self.get_guid().ok()?;
+        ffi::string_from_guid(&guid)
+            .ok()
+            .map(|guid| decode_utf16(&guid))
+    }
+
+    /// Raw value of the handle, fails if a `restart` or `reopen`
+    /// couldn't open the interface again
+    fn raw_handle(&self) -> io::Result<HANDLE> {
+        self.handle().map(|handle| handle.as_raw_handle() as HANDLE)
+    }
+}
+
+impl fmt::Debug for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Device")
+            .field("name", &self.get_name().ok())
+            .field("guid", &self.guid_string())
+            .field("luid", &format_args!("{:#x}", self.luid.Value))
+            .field("component_id", &self.component_id)
+            .field("handle_valid", &self.handle.is_some())
+            .finish()
+    }
+}
+
+/// Prints the name and guid of the interface, like
+/// `My Interface {6B9E3A1C-52F4-4B8D-9A07-1E3C5D2F8011}`,
+/// falling back to the luid if they can't be retrieved
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.get_name(), self.guid_string()) {
+            (Ok(name), Some(guid)) => write!(f, "{} {}", name, guid),
+            (Ok(name), None) => write!(f, "{}", name),
+            (Err(_), _) => write!(f, "luid {:#x}", self.luid.Value),
+        }
+    }
+}
 
 impl io::Read for Device {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        ffi::read_file(self.handle, buf).map(|res| res as _)
+        self.recover(|dev| dev.read_once(buf))
     }
 }
 
 impl io::Write for Device {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        ffi::write_file(self.handle, buf).map(|res| res as _)
+        self.recover(|dev| dev.write_once(buf))
     }
 
+    /// Wait for the writes pending in the write queue, if enabled,
+    /// otherwise writes are already complete when `write` returns
     fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+        let handle = self.raw_handle()?;
+        match &mut self.write_queue {
+            Some(queue) => queue.flush(handle),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Device {
+    /// Read a frame, without recovering from a stale handle
+    fn read_once(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let handle = self.raw_handle()?;
+
+        let res = loop {
+            let res = ffi::read_file(handle, buf, &mut self.read_overlapped)?
+                as usize;
+
+            if let Some(res) = self.process_read(buf, res) {
+                break res;
+            }
+        };
+
+        self.counters.add_rx(res);
+
+        #[cfg(feature = "capture")]
+        if let Some(capture) = &mut self.capture {
+            capture.frame(Direction::Rx, &buf[..res]);
+        }
+
+        Ok(res)
+    }
+
+    /// Run a frame read into `buf` through the read filter and mss
+    /// clamping, shared by every read path. Returns the new length of
+    /// the frame, or `None` if the filter dropped it
+    pub(crate) fn process_read(
+        &mut self,
+        buf: &mut [u8],
+        len: usize,
+    ) -> Option<usize> {
+        let len = match filter::apply(self.read_filter.get_mut(), &buf[..len])?
+        {
+            Cow::Borrowed(_) => len,
+            Cow::Owned(frame) => {
+                let len = frame.len().min(buf.len());
+                buf[..len].copy_from_slice(&frame[..len]);
+                len
+            }
+        };
+
+        if let Some(mss) = self.mss_clamp {
+            mss::clamp(&mut buf[..len], mss);
+        }
+
+        Some(len)
+    }
+
+    /// Run a frame about to be written through the write filter,
+    /// mss clamping, dscp marking and rate limiting. Returns `None`
+    /// if the filter dropped it
+    fn prepare_write<'a>(&mut self, buf: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        let mut frame = filter::apply(self.write_filter.get_mut(), buf)?;
+
+        if let Some(mss) = self.mss_clamp {
+            mss::clamp_cow(&mut frame, mss);
+        }
+
+        dscp::mark_cow(self.dscp_classifier.get_mut(), &mut frame);
+
+        if let Some(rate_limit) = &mut self.rate_limit {
+            rate_limit.acquire(frame.len());
+        }
+
+        Some(frame)
+    }
+
+    /// Write a frame, without recovering from a stale handle
+    fn write_once(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let handle = self.raw_handle()?;
+        let frame = match self.prepare_write(buf) {
+            Some(frame) => frame,
+            None => return Ok(buf.len()),
+        };
+
+        let res = match &mut self.write_queue {
+            Some(queue) => queue.write(handle, &frame).map(|_| frame.len())?,
+            None => {
+                ffi::write_file(handle, &frame, &mut self.write_overlapped)?
+                    as usize
+            }
+        };
+        self.counters.add_tx(res);
+
+        #[cfg(feature = "capture")]
+        if let Some(capture) = &mut self.capture {
+            capture.frame(Direction::Tx, &frame[..res]);
+        }
+
+        // A replaced or rewritten frame still counts as all of `buf`
+        // written
+        match frame {
+            Cow::Borrowed(_) => Ok(res),
+            Cow::Owned(_) => Ok(buf.len()),
+        }
     }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
-        let _ = ffi::close_handle(self.handle);
+        // The queued buffers must outlive the writes
+        if let (Ok(handle), Some(queue)) =
+            (self.raw_handle(), &mut self.write_queue)
+        {
+            let _ = queue.flush(handle);
+        }
+
+        // Closed before deleting, the driver refuses to stop
+        // while a handle is open
+        self.handle = None;
+
+        if self.delete_on_drop {
+            let _ = iface::delete_interface(&self.component_id, &self.luid);
+        }
     }
 }
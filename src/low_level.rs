@@ -0,0 +1,53 @@
+//! Adapter management without the `Device` wrapper, for tooling
+//! that creates, checks or deletes adapters but never reads or
+//! writes frames. Interfaces are identified by their luid, handles
+//! returned by `open_interface` are owned by the caller
+
+use winapi::shared::ifdef::NET_LUID;
+use winapi::um::winnt::HANDLE;
+
+use std::io;
+
+use crate::{elevation, iface, CreateOptions};
+
+/// Create a new adapter and return its luid, without waiting for
+/// it to be ready to open. `options.name` is ignored, as the
+/// interface has no alias yet. Requires administrator rights
+/// Example
+/// ```no_run
+/// use tap_windows::{low_level, CreateOptions};
+///
+/// let luid = low_level::create_interface(&CreateOptions::default())
+///     .expect("Failed to create interface");
+///
+/// println!("{}", luid.Value);
+/// ```
+pub fn create_interface(options: &CreateOptions) -> io::Result<NET_LUID> {
+    elevation::require_elevated()?;
+
+    iface::create_interface(options)
+}
+
+/// Check that an interface exists and is bound to the driver
+/// with the given hardware id, fails with `NotFound` otherwise
+pub fn check_interface(component_id: &str, luid: &NET_LUID) -> io::Result<()> {
+    iface::check_interface(component_id, luid)
+}
+
+/// Delete an adapter, fails with `NotFound` if it doesn't exist
+/// or is bound to another driver. Requires administrator rights
+pub fn delete_interface(component_id: &str, luid: &NET_LUID) -> io::Result<()> {
+    elevation::require_elevated()?;
+
+    iface::delete_interface(component_id, luid)
+}
+
+/// Open a handle to an interface, for overlapped io. The caller
+/// must close it with `CloseHandle`, or pass it to
+/// `Device::from_raw_parts`
+pub fn open_interface(
+    luid: &NET_LUID,
+    inheritable: bool,
+) -> io::Result<HANDLE> {
+    iface::open_interface(luid, inheritable)
+}
@@ -0,0 +1,154 @@
+use std::{error, fmt, io, str};
+
+/// Mac address of an adapter, displayed and parsed in the
+/// `00:FF:12:34:56:78` form, `-` is accepted as a separator too
+/// Example
+/// ```no_run
+/// use tap_windows::{CreateOptions, Device, MacAddress};
+///
+/// let mac: MacAddress = "00:ff:12:34:56:78".parse()
+///     .expect("Invalid mac address");
+///
+/// let dev = Device::create_with(CreateOptions {
+///     mac: Some(mac),
+///     ..Default::default()
+/// })
+/// .expect("Failed to create device");
+///
+/// println!("{}", dev.get_mac().expect("Failed to get mac"));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MacAddress(pub [u8; 6]);
+
+impl MacAddress {
+    /// Bytes of the address
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(
+            f,
+            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+            a, b, c, d, e, g
+        )
+    }
+}
+
+/// Error returned when parsing a malformed `MacAddress`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseMacError;
+
+impl fmt::Display for ParseMacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid mac address")
+    }
+}
+
+impl error::Error for ParseMacError {}
+
+impl From<ParseMacError> for io::Error {
+    fn from(err: ParseMacError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+impl str::FromStr for MacAddress {
+    type Err = ParseMacError;
+
+    fn from_str(s: &str) -> Result<Self, ParseMacError> {
+        let mut mac = [0; 6];
+        let mut parts = s.split([':', '-']);
+
+        for byte in mac.iter_mut() {
+            let part = parts.next().ok_or(ParseMacError)?;
+            if part.len() != 2 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ParseMacError);
+            }
+
+            *byte = u8::from_str_radix(part, 16).map_err(|_| ParseMacError)?;
+        }
+
+        match parts.next() {
+            Some(_) => Err(ParseMacError),
+            None => Ok(MacAddress(mac)),
+        }
+    }
+}
+
+impl From<[u8; 6]> for MacAddress {
+    fn from(mac: [u8; 6]) -> Self {
+        MacAddress(mac)
+    }
+}
+
+impl From<MacAddress> for [u8; 6] {
+    fn from(mac: MacAddress) -> Self {
+        mac.0
+    }
+}
+
+#[cfg(feature = "macaddr")]
+impl From<macaddr::MacAddr6> for MacAddress {
+    fn from(mac: macaddr::MacAddr6) -> Self {
+        MacAddress(mac.into_array())
+    }
+}
+
+#[cfg(feature = "macaddr")]
+impl From<MacAddress> for macaddr::MacAddr6 {
+    fn from(mac: MacAddress) -> Self {
+        mac.0.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_both_separators() {
+        let mac = MacAddress([0x00, 0xff, 0x12, 0x34, 0xab, 0xcd]);
+
+        assert_eq!("00:ff:12:34:ab:cd".parse(), Ok(mac));
+        assert_eq!("00-FF-12-34-AB-CD".parse(), Ok(mac));
+        assert_eq!("00:FF:12-34:aB:Cd".parse(), Ok(mac));
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        let mac = MacAddress([0x02, 0x00, 0x5e, 0x10, 0x00, 0x01]);
+
+        assert_eq!(mac.to_string(), "02:00:5E:10:00:01");
+        assert_eq!(mac.to_string().parse(), Ok(mac));
+    }
+
+    #[test]
+    fn rejects_malformed_addresses() {
+        let cases = [
+            "",
+            "00:ff:12:34:ab",
+            "00:ff:12:34:ab:cd:ef",
+            "00:ff:12:34:ab:",
+            "0:ff:12:34:ab:cd",
+            "000:ff:12:34:ab:cd",
+            "00:ff:12:34:ab:cg",
+            "00:ff:12:34:ab:+d",
+            "00 ff 12 34 ab cd",
+            "00ff1234abcd",
+        ];
+
+        for case in cases.iter() {
+            assert_eq!(
+                case.parse::<MacAddress>(),
+                Err(ParseMacError),
+                "{}",
+                case
+            );
+        }
+    }
+}
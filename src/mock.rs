@@ -0,0 +1,253 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::{io, net};
+
+use crate::{validate_name, MacAddress, TrafficStats};
+
+#[derive(Default)]
+struct State {
+    /// Frames waiting to be read by the code under test
+    rx: VecDeque<Vec<u8>>,
+    /// Frames written by the code under test
+    tx: VecDeque<Vec<u8>>,
+    closed: bool,
+    name: String,
+    mac: MacAddress,
+    mtu: u32,
+    up: bool,
+    ip: Option<(net::Ipv4Addr, net::Ipv4Addr)>,
+    dns: Vec<net::IpAddr>,
+    stats: TrafficStats,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    /// Signaled when a frame is injected or the device is closed
+    readable: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// In-memory stand-in for `Device`, with the same read, write and
+/// configuration methods, to unit test packet loops without the
+/// driver or administrator rights. Built with the `test-util` feature.
+/// The test drives the other end through a `MockHandle`
+/// Example
+/// ```
+/// use tap_windows::MockDevice;
+/// use std::io::{Read, Write};
+///
+/// let mut dev = MockDevice::new("My Interface");
+/// let handle = dev.handle();
+///
+/// handle.inject(&[1, 2, 3]);
+///
+/// let mut buf = [0; 1600];
+/// let amt = dev.read(&mut buf).unwrap();
+/// dev.write(&buf[..amt]).unwrap();
+///
+/// assert_eq!(handle.written(), Some(vec![1, 2, 3]));
+/// ```
+pub struct MockDevice {
+    shared: Arc<Shared>,
+}
+
+/// Test side of a `MockDevice`, can be cloned and moved to
+/// other threads
+#[derive(Clone)]
+pub struct MockHandle {
+    shared: Arc<Shared>,
+}
+
+impl MockDevice {
+    /// Create a disconnected mock device with an mtu of 1500
+    pub fn new(name: &str) -> Self {
+        let shared = Shared::default();
+
+        {
+            let mut state = shared.lock();
+            state.name = name.to_owned();
+            state.mac = MacAddress([0x00, 0xff, 0x00, 0x00, 0x00, 0x01]);
+            state.mtu = 1500;
+        }
+
+        Self {
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// Get a handle to inject frames and inspect the device
+    pub fn handle(&self) -> MockHandle {
+        MockHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Sets the status of the interface to connected
+    pub fn up(&self) -> io::Result<()> {
+        self.set_status(true)
+    }
+
+    /// Sets the status of the interface to disconnected
+    pub fn down(&self) -> io::Result<()> {
+        self.set_status(false)
+    }
+
+    /// Set the status of the interface, true for connected
+    pub fn set_status(&self, status: bool) -> io::Result<()> {
+        self.shared.lock().up = status;
+        Ok(())
+    }
+
+    /// Retieve the mac of the interface
+    pub fn get_mac(&self) -> io::Result<MacAddress> {
+        Ok(self.shared.lock().mac)
+    }
+
+    /// Retrieve the mtu of the interface
+    pub fn get_mtu(&self) -> io::Result<u32> {
+        Ok(self.shared.lock().mtu)
+    }
+
+    /// Retrieve the name of the interface
+    pub fn get_name(&self) -> io::Result<String> {
+        Ok(self.shared.lock().name.clone())
+    }
+
+    /// Set the name of the interface, validated like `Device::set_name`
+    pub fn set_name(&self, newname: &str) -> io::Result<()> {
+        validate_name(newname)?;
+
+        self.shared.lock().name = newname.to_owned();
+        Ok(())
+    }
+
+    /// Set the ip of the interface
+    pub fn set_ip<A, B>(&self, address: A, mask: B) -> io::Result<()>
+    where
+        A: Into<net::Ipv4Addr>,
+        B: Into<net::Ipv4Addr>,
+    {
+        self.shared.lock().ip = Some((address.into(), mask.into()));
+        Ok(())
+    }
+
+    /// Set the dns servers of the interface
+    pub fn set_dns(&self, servers: &[net::IpAddr]) -> io::Result<()> {
+        self.shared.lock().dns = servers.to_vec();
+        Ok(())
+    }
+
+    /// Bytes read and written through this device
+    pub fn stats(&self) -> TrafficStats {
+        self.shared.lock().stats
+    }
+}
+
+/// Blocks until a frame is injected, fails with `BrokenPipe` once
+/// the handle closed the device and every frame was read.
+/// Frames larger than `buf` are truncated, like the driver does
+impl io::Read for MockDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.shared.lock();
+
+        let frame = loop {
+            if let Some(frame) = state.rx.pop_front() {
+                break frame;
+            }
+
+            if state.closed {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "Mock device closed",
+                ));
+            }
+
+            state = self
+                .shared
+                .readable
+                .wait(state)
+                .unwrap_or_else(|err| err.into_inner());
+        };
+
+        let len = frame.len().min(buf.len());
+        buf[..len].copy_from_slice(&frame[..len]);
+        state.stats.rx_frames += 1;
+        state.stats.rx_bytes += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl io::Write for MockDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.shared.lock();
+
+        if state.closed {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "Mock device closed",
+            ));
+        }
+
+        state.tx.push_back(buf.to_vec());
+        state.stats.tx_frames += 1;
+        state.stats.tx_bytes += buf.len() as u64;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl MockHandle {
+    /// Queue a frame to be returned by `read`
+    pub fn inject(&self, frame: &[u8]) {
+        self.shared.lock().rx.push_back(frame.to_vec());
+        self.shared.readable.notify_all();
+    }
+
+    /// Pop the oldest frame written to the device
+    pub fn written(&self) -> Option<Vec<u8>> {
+        self.shared.lock().tx.pop_front()
+    }
+
+    /// Make reads fail once the injected frames run out, and
+    /// writes fail right away, like a removed adapter
+    pub fn close(&self) {
+        self.shared.lock().closed = true;
+        self.shared.readable.notify_all();
+    }
+
+    /// Check the media status set through the device
+    pub fn is_up(&self) -> bool {
+        self.shared.lock().up
+    }
+
+    /// Address and mask set through the device
+    pub fn ip(&self) -> Option<(net::Ipv4Addr, net::Ipv4Addr)> {
+        self.shared.lock().ip
+    }
+
+    /// Dns servers set through the device
+    pub fn dns(&self) -> Vec<net::IpAddr> {
+        self.shared.lock().dns.clone()
+    }
+
+    /// Change the mtu reported by the device
+    pub fn set_mtu(&self, mtu: u32) {
+        self.shared.lock().mtu = mtu;
+    }
+
+    /// Change the mac reported by the device
+    pub fn set_mac(&self, mac: MacAddress) {
+        self.shared.lock().mac = mac;
+    }
+}
@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+
+use crate::packet::*;
+use crate::Device;
+
+const TCP_FLAG_SYN: u8 = 0x02;
+
+const OPTION_END: u8 = 0;
+const OPTION_NOP: u8 = 1;
+const OPTION_MSS: u8 = 2;
+
+/// Ip and tcp headers without options
+const TCP_IPV4_OVERHEAD: u32 = 40;
+
+/// Ipv6 headers are 20 bytes longer than ipv4 ones
+const IPV6_EXTRA_OVERHEAD: u16 = 20;
+
+const IPV6_HEADER_LEN: usize = 40;
+
+impl Device {
+    /// Lower the mss option of the tcp SYN frames read from and
+    /// written to the device to at most `mss`, so connections never
+    /// send segments too large for the tunnel and path mtu discovery
+    /// is not needed. Usually the tunnel mtu minus 40, see
+    /// `mss_for_mtu`. Ipv6 SYNs are clamped to 20 bytes less, to
+    /// make up for the longer header. Frames with 802.1Q tags are
+    /// clamped too. An mss of 0 disables clamping.
+    /// Applies to every read and write path, including `ReadRing`,
+    /// `DeviceSet` and `write_many`
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// // Frames are sent over a tunnel with an mtu of 1400
+    /// dev.set_mss_clamp(Device::mss_for_mtu(1400));
+    /// ```
+    pub fn set_mss_clamp(&mut self, mss: u16) {
+        self.mss_clamp = match mss {
+            0 => None,
+            mss => Some(mss),
+        };
+    }
+
+    /// Same as `set_mss_clamp`, in builder form
+    pub fn with_mss_clamp(mut self, mss: u16) -> Self {
+        self.set_mss_clamp(mss);
+        self
+    }
+
+    /// Largest mss fitting in ipv4 packets of `mtu` bytes
+    pub fn mss_for_mtu(mtu: u32) -> u16 {
+        mtu.saturating_sub(TCP_IPV4_OVERHEAD).min(u16::MAX as u32) as u16
+    }
+}
+
+/// Clamp the mss of a frame in place, true if it was changed
+pub(crate) fn clamp(frame: &mut [u8], mss: u16) -> bool {
+    let (option, checksum, mss) = match find_mss(frame, mss) {
+        Some(found) => found,
+        None => return false,
+    };
+
+    let old = read_u16(frame, option);
+    write_u16(frame, option, mss);
+
+    // Incremental update, RFC 1624
+    let sum = !read_u16(frame, checksum) as u32 + !old as u32 + mss as u32;
+    write_u16(frame, checksum, checksum_finish(sum));
+
+    true
+}
+
+/// Clamp the mss of a borrowed frame, copying it only if needed
+pub(crate) fn clamp_cow(frame: &mut Cow<[u8]>, mss: u16) {
+    if find_mss(frame, mss).is_some() {
+        clamp(frame.to_mut(), mss);
+    }
+}
+
+/// Offsets of the mss value and of the tcp checksum, if the frame
+/// is a SYN advertising an mss larger than `mss`, along with the
+/// mss to write for its ip version
+fn find_mss(frame: &[u8], mss: u16) -> Option<(usize, usize, u16)> {
+    let (payload, end, mss) = match untagged_ether_type(frame)? {
+        (ETHERTYPE_IPV4, start) => {
+            let ip = parse_ipv4_at(frame, start)?;
+            if ip.protocol(frame) != IPPROTO_TCP || ip.is_later_fragment(frame)
+            {
+                return None;
+            }
+
+            (ip.payload, ip.end, mss)
+        }
+        (ETHERTYPE_IPV6, start) => {
+            let header = frame.get(start..start + IPV6_HEADER_LEN)?;
+            // Extension headers are not followed
+            if header[0] >> 4 != 6 || header[6] != IPPROTO_TCP {
+                return None;
+            }
+
+            let payload = start + IPV6_HEADER_LEN;
+            let end = payload + read_u16(header, 4) as usize;
+            (payload, end, mss.saturating_sub(IPV6_EXTRA_OVERHEAD))
+        }
+        _ => return None,
+    };
+
+    let tcp = frame.get(payload..end)?;
+    if tcp.len() < 20 || tcp[13] & TCP_FLAG_SYN == 0 {
+        return None;
+    }
+
+    let header_len = (tcp[12] >> 4) as usize * 4;
+    let mut options = tcp.get(20..header_len)?;
+    let mut offset = payload + 20;
+
+    loop {
+        match options {
+            [OPTION_END, ..] | [] => return None,
+            [OPTION_NOP, rest @ ..] => {
+                options = rest;
+                offset += 1;
+            }
+            [OPTION_MSS, 4, value @ ..] if value.len() >= 2 => {
+                return match read_u16(value, 0) > mss {
+                    true => Some((offset + 2, payload + 16, mss)),
+                    false => None,
+                };
+            }
+            [_, len, ..] if *len >= 2 && options.len() >= *len as usize => {
+                options = &options[*len as usize..];
+                offset += *len as usize;
+            }
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{push_vlan_tag, MacAddress, VlanTag};
+
+    const SOURCE: [u8; 16] =
+        [0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    const DESTINATION: [u8; 16] =
+        [0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+
+    /// Tcp SYN with an mss option, checksummed with `pseudo_sum`
+    fn syn(mss: u16, pseudo_sum: u32) -> Vec<u8> {
+        let mut tcp = vec![0; 24];
+        write_u16(&mut tcp, 0, 40000);
+        write_u16(&mut tcp, 2, 80);
+        tcp[12] = 6 << 4;
+        tcp[13] = TCP_FLAG_SYN;
+        write_u16(&mut tcp, 14, 65535);
+        tcp[20..22].copy_from_slice(&[OPTION_MSS, 4]);
+        write_u16(&mut tcp, 22, mss);
+
+        let sum = checksum_add(pseudo_sum + tcp.len() as u32, &tcp);
+        write_u16(&mut tcp, 16, checksum_finish(sum));
+        tcp
+    }
+
+    fn ipv4_pseudo_sum() -> u32 {
+        checksum_add(IPPROTO_TCP as u32, &[10, 0, 0, 1, 10, 0, 0, 2])
+    }
+
+    fn ipv4_syn(mss: u16) -> Vec<u8> {
+        build_ipv4_frame(
+            MacAddress([2; 6]),
+            MacAddress([4; 6]),
+            [10, 0, 0, 1].into(),
+            [10, 0, 0, 2].into(),
+            IPPROTO_TCP,
+            &syn(mss, ipv4_pseudo_sum()),
+        )
+    }
+
+    fn ipv6_pseudo_sum() -> u32 {
+        checksum_add(checksum_add(IPPROTO_TCP as u32, &SOURCE), &DESTINATION)
+    }
+
+    fn ipv6_syn(mss: u16) -> Vec<u8> {
+        let tcp = syn(mss, ipv6_pseudo_sum());
+
+        let mut frame = vec![2; 6];
+        frame.extend_from_slice(&[4; 6]);
+        frame.extend_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+        frame.extend_from_slice(&[0x60, 0, 0, 0]);
+        frame.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&[IPPROTO_TCP, 64]);
+        frame.extend_from_slice(&SOURCE);
+        frame.extend_from_slice(&DESTINATION);
+        frame.extend_from_slice(&tcp);
+        frame
+    }
+
+    /// Mss of the SYN starting at `tcp`, and whether its checksum
+    /// is still valid
+    fn inspect(frame: &[u8], tcp: usize, pseudo_sum: u32) -> (u16, bool) {
+        let tcp = &frame[tcp..];
+        let sum = checksum_add(pseudo_sum + tcp.len() as u32, tcp);
+
+        (read_u16(tcp, 22), checksum_finish(sum) == 0)
+    }
+
+    #[test]
+    fn clamps_ipv4() {
+        let mut frame = ipv4_syn(1460);
+
+        assert!(clamp(&mut frame, 1360));
+        assert_eq!(inspect(&frame, 34, ipv4_pseudo_sum()), (1360, true));
+    }
+
+    #[test]
+    fn leaves_smaller_mss() {
+        let mut frame = ipv4_syn(1200);
+
+        assert!(!clamp(&mut frame, 1360));
+        assert_eq!(frame, ipv4_syn(1200));
+    }
+
+    #[test]
+    fn clamps_ipv6_lower() {
+        let mut frame = ipv6_syn(1440);
+
+        assert!(clamp(&mut frame, 1360));
+        assert_eq!(inspect(&frame, 54, ipv6_pseudo_sum()), (1340, true));
+    }
+
+    #[test]
+    fn clamps_vlan_tagged() {
+        let tag = VlanTag::new(100);
+        let mut frame = push_vlan_tag(&ipv4_syn(1460), tag).unwrap();
+
+        assert!(clamp(&mut frame, 1360));
+        assert_eq!(inspect(&frame, 38, ipv4_pseudo_sum()), (1360, true));
+
+        let mut frame = push_vlan_tag(&ipv6_syn(1440), tag).unwrap();
+
+        assert!(clamp(&mut frame, 1360));
+        assert_eq!(inspect(&frame, 58, ipv6_pseudo_sum()), (1340, true));
+    }
+
+    #[test]
+    fn copies_only_when_clamping() {
+        let frame = ipv4_syn(1200);
+        let mut cow = Cow::Borrowed(&frame[..]);
+
+        clamp_cow(&mut cow, 1360);
+        assert!(matches!(cow, Cow::Borrowed(_)));
+
+        let frame = ipv4_syn(1460);
+        let mut cow = Cow::Borrowed(&frame[..]);
+
+        clamp_cow(&mut cow, 1360);
+        assert_eq!(read_u16(&cow, 34 + 22), 1360);
+    }
+}
@@ -0,0 +1,265 @@
+use winapi::um::winnt::{HANDLE, MAXIMUM_WAIT_OBJECTS};
+
+use std::{io, time};
+
+use crate::overlapped::Overlapped;
+use crate::{ffi, Device};
+
+/// A device of the set, with its pending read
+struct Entry {
+    id: usize,
+    dev: Device,
+    overlapped: Overlapped,
+    buffer: Box<[u8]>,
+    pending: bool,
+}
+
+impl Entry {
+    fn post(&mut self) -> io::Result<()> {
+        ffi::read_file_overlapped(
+            self.dev.raw_handle()?,
+            &mut self.buffer,
+            &mut self.overlapped,
+        )?;
+        self.pending = true;
+
+        Ok(())
+    }
+
+    fn cancel(&mut self) {
+        // The buffer can only be freed once the driver let go of it
+        if let (true, Ok(handle)) = (self.pending, self.dev.raw_handle()) {
+            let _ = ffi::cancel_io_ex(handle, &mut self.overlapped);
+            let _ =
+                ffi::get_overlapped_result(handle, &mut self.overlapped, true);
+            self.pending = false;
+        }
+    }
+}
+
+/// Waits on several devices at once and returns the frames read
+/// from any of them, so a single thread can serve many adapters.
+/// Each device keeps one read pending, up to 64 devices are
+/// supported. Frames go through the read filter and mss clamping, as
+/// with `Device::read`
+/// Example
+/// ```no_run
+/// use tap_windows::{Device, DeviceSet};
+///
+/// let mut set = DeviceSet::new(2048);
+///
+/// let first = set.insert(Device::open("First Device")?)?;
+/// let second = set.insert(Device::open("Second Device")?)?;
+///
+/// loop {
+///     let (id, frame) = set.next_frame()?;
+///
+///     match id {
+///         id if id == first => println!("first: {:?}", frame),
+///         _ => println!("second: {:?}", frame),
+///     }
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct DeviceSet {
+    entries: Vec<Entry>,
+    frame_size: usize,
+    next_id: usize,
+    /// Entry whose frame was returned by the last `next_frame`,
+    /// posted again once the frame is not borrowed anymore
+    returned: Option<usize>,
+    /// Entry waited on first, rotated for fairness
+    start: usize,
+}
+
+impl DeviceSet {
+    /// Create an empty set reading frames of up to `frame_size` bytes
+    pub fn new(frame_size: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            frame_size,
+            next_id: 0,
+            returned: None,
+            start: 0,
+        }
+    }
+
+    /// Add a device to the set and start reading from it,
+    /// returns the id its frames are tagged with
+    pub fn insert(&mut self, dev: Device) -> io::Result<usize> {
+        if self.entries.len() >= MAXIMUM_WAIT_OBJECTS as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Too many devices in the set",
+            ));
+        }
+
+        let mut entry = Entry {
+            id: self.next_id,
+            dev,
+            overlapped: Overlapped::new()?,
+            buffer: vec![0; self.frame_size].into_boxed_slice(),
+            pending: false,
+        };
+        entry.post()?;
+
+        self.next_id += 1;
+        self.entries.push(entry);
+
+        Ok(self.next_id - 1)
+    }
+
+    /// Remove a device from the set, its pending read is cancelled
+    pub fn remove(&mut self, id: usize) -> Option<Device> {
+        let index = self.position(id)?;
+
+        self.returned = match self.returned {
+            Some(returned) if returned == index => None,
+            Some(returned) if returned > index => Some(returned - 1),
+            returned => returned,
+        };
+
+        let mut entry = self.entries.remove(index);
+        entry.cancel();
+
+        Some(entry.dev)
+    }
+
+    /// Borrow a device of the set, to write to it or configure it.
+    /// Reading from it directly would race with the set
+    pub fn get(&self, id: usize) -> Option<&Device> {
+        let index = self.position(id)?;
+        Some(&self.entries[index].dev)
+    }
+
+    /// Mutably borrow a device of the set, see `get`
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Device> {
+        let index = self.position(id)?;
+        Some(&mut self.entries[index].dev)
+    }
+
+    /// Number of devices in the set
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// True if the set holds no device
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Wait for a frame from any device, returned along the id of
+    /// the device. The frame is valid until the next call
+    pub fn next_frame(&mut self) -> io::Result<(usize, &[u8])> {
+        match self.next_frame_timeout(None)? {
+            Some(frame) => Ok(frame),
+            None => unreachable!("Infinite wait timed out"),
+        }
+    }
+
+    /// Same as `next_frame`, giving up after `timeout`
+    pub fn next_frame_timeout(
+        &mut self,
+        timeout: Option<time::Duration>,
+    ) -> io::Result<Option<(usize, &[u8])>> {
+        // Dropped frames don't extend the wait
+        let deadline = timeout
+            .and_then(|timeout| time::Instant::now().checked_add(timeout));
+
+        let (index, len) = loop {
+            if let Some(index) = self.returned.take() {
+                self.entries[index].post()?;
+            }
+
+            if self.entries.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "No device in the set",
+                ));
+            }
+
+            let timeout = deadline.map(|deadline| {
+                deadline.saturating_duration_since(time::Instant::now())
+            });
+
+            if let Some(frame) = self.wait(timeout)? {
+                break frame;
+            }
+
+            let now = time::Instant::now();
+            if deadline.map_or(false, |deadline| now >= deadline) {
+                return Ok(None);
+            }
+        };
+
+        let entry = &mut self.entries[index];
+        entry.dev.counters.add_rx(len);
+
+        let frame = &entry.buffer[..len];
+
+        #[cfg(feature = "capture")]
+        if let Some(capture) = &mut entry.dev.capture {
+            capture.frame(crate::Direction::Rx, frame);
+        }
+
+        Ok(Some((entry.id, frame)))
+    }
+
+    /// Wait for a read to complete and run it through the read hooks,
+    /// returns `None` if the wait timed out or the frame was dropped
+    fn wait(
+        &mut self,
+        timeout: Option<time::Duration>,
+    ) -> io::Result<Option<(usize, usize)>> {
+        // Rotate the wait order, the lowest signaled index wins
+        let count = self.entries.len();
+        let order: Vec<usize> =
+            (0..count).map(|i| (self.start + i) % count).collect();
+        let events: Vec<HANDLE> = order
+            .iter()
+            .map(|&index| self.entries[index].overlapped.hEvent)
+            .collect();
+
+        // u32::MAX waits forever
+        let milliseconds = match timeout {
+            Some(timeout) => timeout.as_millis().min(u32::MAX as u128 - 1),
+            None => u32::MAX as u128,
+        };
+
+        let signaled =
+            ffi::wait_for_multiple_objects(&events, milliseconds as u32)?;
+        let index = match signaled {
+            Some(signaled) => order[signaled],
+            None => return Ok(None),
+        };
+        self.start = (index + 1) % count;
+
+        let entry = &mut self.entries[index];
+        let res = entry.dev.raw_handle().and_then(|handle| {
+            ffi::get_overlapped_result(handle, &mut entry.overlapped, false)
+        });
+        entry.pending = false;
+
+        // Failed and dropped reads are posted again on the next call
+        self.returned = Some(index);
+
+        let len = res? as usize;
+
+        Ok(entry
+            .dev
+            .process_read(&mut entry.buffer, len)
+            .map(|len| (index, len)))
+    }
+
+    fn position(&self, id: usize) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.id == id)
+    }
+}
+
+impl Drop for DeviceSet {
+    fn drop(&mut self) {
+        for entry in &mut self.entries {
+            entry.cancel();
+        }
+    }
+}
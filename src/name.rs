@@ -0,0 +1,113 @@
+use std::{error, fmt, io};
+
+/// Characters Windows refuses in connection names
+const ILLEGAL_CHARS: &[char] = &['\\', '/', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Maximum length of an interface alias, in utf16 units (IF_MAX_STRING_SIZE)
+const MAX_NAME_LEN: usize = 256;
+
+/// Error describing why a name can't be used as an interface name,
+/// returned by `validate_name` and wrapped in an `InvalidInput`
+/// io::Error by the functions that rename interfaces
+/// Example
+/// ```no_run
+/// use tap_windows::{Device, InvalidName};
+///
+/// let dev = Device::create()
+///     .expect("Failed to create device");
+///
+/// let err = dev.set_name("My|Interface").unwrap_err();
+/// let reason = err.get_ref().and_then(|e| e.downcast_ref::<InvalidName>());
+///
+/// assert_eq!(reason, Some(&InvalidName::IllegalChar('|')));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidName {
+    /// The name is empty
+    Empty,
+    /// The name is longer than 256 utf16 characters
+    TooLong,
+    /// The name contains a character not allowed by Windows
+    IllegalChar(char),
+}
+
+impl fmt::Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidName::Empty => write!(f, "Interface name is empty"),
+            InvalidName::TooLong => write!(f, "Interface name is too long"),
+            InvalidName::IllegalChar(c) => {
+                write!(f, "Interface name contains illegal character {:?}", c)
+            }
+        }
+    }
+}
+
+impl error::Error for InvalidName {}
+
+impl From<InvalidName> for io::Error {
+    fn from(err: InvalidName) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Check that a name can be used as an interface name.
+/// Spaces and any unicode character are allowed, while control
+/// characters and `\ / : * ? " < > |` are not
+pub fn validate_name(name: &str) -> Result<(), InvalidName> {
+    if name.is_empty() {
+        return Err(InvalidName::Empty);
+    }
+
+    if name.encode_utf16().count() > MAX_NAME_LEN {
+        return Err(InvalidName::TooLong);
+    }
+
+    match name
+        .chars()
+        .find(|c| c.is_control() || ILLEGAL_CHARS.contains(c))
+    {
+        Some(c) => Err(InvalidName::IllegalChar(c)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_names() {
+        let names = ["Ethernet 2", "My VPN (tap)", "Réseau 100%", "網路"];
+
+        for name in names.iter() {
+            assert_eq!(validate_name(name), Ok(()), "{}", name);
+        }
+
+        assert_eq!(validate_name(&"a".repeat(MAX_NAME_LEN)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert_eq!(validate_name(""), Err(InvalidName::Empty));
+        assert_eq!(
+            validate_name(&"a".repeat(MAX_NAME_LEN + 1)),
+            Err(InvalidName::TooLong)
+        );
+        // Counted in utf16 units, not chars
+        assert_eq!(
+            validate_name(&"😀".repeat(MAX_NAME_LEN / 2 + 1)),
+            Err(InvalidName::TooLong)
+        );
+
+        for &c in ILLEGAL_CHARS.iter().chain(&['\n', '\t', '\0']) {
+            let name = format!("My{}Interface", c);
+            assert_eq!(
+                validate_name(&name),
+                Err(InvalidName::IllegalChar(c)),
+                "{:?}",
+                name
+            );
+        }
+    }
+}
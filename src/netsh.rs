@@ -1,39 +1,104 @@
+use std::os::windows::process::CommandExt;
 use std::{io, process};
 
+use winapi::um::winbase::CREATE_NO_WINDOW;
+use winapi::um::winnls::CP_OEMCP;
+
+use crate::{decode_utf16, elevation, ffi, hook, validate_name};
+
 fn exec_netsh(args: &[&str]) -> io::Result<()> {
-    process::Command::new("netsh")
-        .args(args)
-        .stderr(process::Stdio::null())
-        .stdout(process::Stdio::null())
-        .status()
-        .and_then(|res| {
-            if res.success() {
-                Ok(())
-            } else {
-                Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Failed to execute netsh",
-                ))
-            }
-        })
+    // Spawn without a console window, otherwise gui
+    // applications flash a console on every call
+    hook::run("netsh", args, || {
+        // netsh only says "The requested operation requires
+        // elevation" on stdout and exits with 1
+        elevation::require_elevated()?;
+
+        // Passed raw, netsh does its own unquoting and would choke on
+        // the escaping std adds around arguments with spaces
+        let mut command = process::Command::new("netsh");
+        for arg in args {
+            command.raw_arg(arg);
+        }
+
+        let output = command
+            .stdin(process::Stdio::null())
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()?;
+
+        debug!(?args, code = ?output.status.code(), "executed netsh");
+
+        if output.status.success() {
+            return Ok(());
+        }
+
+        // Without a console netsh prints with the oem code page,
+        // decode it so localized messages survive
+        let mut text = output.stdout;
+        text.extend_from_slice(&output.stderr);
+        let message = ffi::multi_byte_to_wide_char(CP_OEMCP, &text)
+            .map(|text| decode_utf16(&text))
+            .unwrap_or_default();
+
+        Err(netsh_error(output.status.code(), &message))
+    })
+}
+
+/// Build the error of a failed netsh run. The exit code is only a
+/// success flag, it carries no win32 error, so the printed message
+/// is the only detail
+fn netsh_error(code: Option<i32>, message: &str) -> io::Error {
+    let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let error = match code {
+        Some(code) => format!("Failed to execute netsh (exit code {})", code),
+        None => "Failed to execute netsh".to_owned(),
+    };
+
+    if message.is_empty() {
+        io::Error::new(io::ErrorKind::Other, error)
+    } else {
+        io::Error::new(io::ErrorKind::Other, format!("{}: {}", error, message))
+    }
+}
+
+/// Build a `key="value"` argument, `value` must have been validated
+/// as netsh has no way to escape quotes
+fn quoted(key: &str, value: &str) -> String {
+    format!("{}=\"{}\"", key, value)
 }
 
+/// Rename an interface
 pub fn set_interface_name(name: &str, newname: &str) -> io::Result<()> {
-    exec_netsh(&["int", "set", "int", "name=", name, "newname=", newname])
+    validate_name(name)?;
+    validate_name(newname)?;
+
+    exec_netsh(&[
+        "int",
+        "set",
+        "int",
+        &quoted("name", name),
+        &quoted("newname", newname),
+    ])
 }
 
+// The ipv4 and ipv6 contexts accept an interface index in place of
+// the name, which sidesteps quoting issues with arbitrary names
+
 pub fn set_interface_ip(
-    name: &str,
+    index: u32,
     address: &str,
     mask: &str,
 ) -> io::Result<()> {
+    let index = index.to_string();
+
     exec_netsh(&[
         "int",
         "ipv4",
         "set",
         "address",
         "name=",
-        name,
+        &index,
         "source=static",
         "address=",
         address,
@@ -41,3 +106,46 @@ pub fn set_interface_ip(
         mask,
     ])
 }
+
+pub fn set_interface_dns(
+    index: u32,
+    family: &str,
+    servers: &[String],
+) -> io::Result<()> {
+    let index = index.to_string();
+    let first = servers.first().map(|s| s.as_str()).unwrap_or("none");
+
+    exec_netsh(&[
+        "int",
+        family,
+        "set",
+        "dnsservers",
+        "name=",
+        &index,
+        "source=static",
+        "address=",
+        first,
+        "register=none",
+        "validate=no",
+    ])?;
+
+    for (i, server) in servers.iter().enumerate().skip(1) {
+        let position = (i + 1).to_string();
+
+        exec_netsh(&[
+            "int",
+            family,
+            "add",
+            "dnsservers",
+            "name=",
+            &index,
+            "address=",
+            server,
+            "index=",
+            &position,
+            "validate=no",
+        ])?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! Module holding the watchers used to receive system notifications
+
+use winapi::shared::ifdef::{IfOperStatusUp, NET_LUID};
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::netioapi::*;
+use winapi::shared::winerror::ERROR_SUCCESS;
+use winapi::shared::ws2def::AF_UNSPEC;
+use winapi::um::cfgmgr32::*;
+use winapi::um::winnt::{PVOID, WCHAR};
+
+use std::sync::Mutex;
+use std::{io, mem, panic};
+
+use crate::ffi;
+use crate::sync::SendHandle;
+
+/// Event reported by a `DeviceWatcher`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// The adapter was added back to the system
+    Arrived,
+    /// The adapter was started, its handle can be opened again
+    Started,
+    /// The adapter was removed or disabled
+    Removed,
+}
+
+type DeviceCallback = Box<dyn Fn(DeviceEvent) + Send + Sync>;
+
+/// Watches an adapter for arrival and removal,
+/// created with `Device::watch_device`.
+/// The callback is called from a system thread, and is
+/// not called anymore once the watcher is dropped
+pub struct DeviceWatcher {
+    handle: HCMNOTIFICATION,
+    _callback: Box<DeviceCallback>,
+}
+
+// The notification handle is only used to unregister
+unsafe impl Send for DeviceWatcher {}
+unsafe impl Sync for DeviceWatcher {}
+
+impl DeviceWatcher {
+    pub(crate) fn new(
+        instance_id: &[WCHAR],
+        callback: DeviceCallback,
+    ) -> io::Result<Self> {
+        let callback = Box::new(callback);
+
+        let mut filter: CM_NOTIFY_FILTER = unsafe { mem::zeroed() };
+        filter.cbSize = mem::size_of_val(&filter) as _;
+        filter.FilterType = CM_NOTIFY_FILTER_TYPE_DEVICEINSTANCE;
+
+        // Keep the id null terminated
+        let dst = unsafe { &mut filter.u.DeviceInstance_mut().InstanceId };
+        let len = instance_id.len().min(dst.len() - 1);
+        dst[..len].copy_from_slice(&instance_id[..len]);
+
+        let handle = ffi::register_notification(
+            &filter,
+            &*callback as *const DeviceCallback as _,
+            Some(device_callback),
+        )?;
+
+        Ok(Self {
+            handle,
+            _callback: callback,
+        })
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        let _ = ffi::unregister_notification(self.handle);
+    }
+}
+
+unsafe extern "system" fn device_callback(
+    _: HCMNOTIFICATION,
+    context: PVOID,
+    action: CM_NOTIFY_ACTION,
+    _: PCM_NOTIFY_EVENT_DATA,
+    _: DWORD,
+) -> DWORD {
+    let callback = &*(context as *const DeviceCallback);
+
+    let event = match action {
+        CM_NOTIFY_ACTION_DEVICEINSTANCEENUMERATED => DeviceEvent::Arrived,
+        CM_NOTIFY_ACTION_DEVICEINSTANCESTARTED => DeviceEvent::Started,
+        CM_NOTIFY_ACTION_DEVICEINSTANCEREMOVED => DeviceEvent::Removed,
+        _ => return ERROR_SUCCESS,
+    };
+
+    // Unwinding into the system is not allowed
+    let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| callback(event)));
+
+    ERROR_SUCCESS
+}
+
+/// Event reported by a `StatusWatcher`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusEvent {
+    /// The interface became operational
+    Up,
+    /// The interface is not operational anymore, for example after
+    /// the system went to sleep or the driver was reset
+    Down,
+}
+
+type StatusCallback = Box<dyn Fn(StatusEvent) + Send + Sync>;
+
+/// State shared with the notification callback
+struct StatusContext {
+    luid: NET_LUID,
+    up: Mutex<bool>,
+    callback: StatusCallback,
+}
+
+/// Watches the operational status of an interface,
+/// created with `Device::watch_status`.
+/// Only transitions are reported, the callback is called from
+/// a system thread and is not called anymore once the watcher
+/// is dropped
+pub struct StatusWatcher {
+    handle: SendHandle,
+    _context: Box<StatusContext>,
+}
+
+impl StatusWatcher {
+    pub(crate) fn new(
+        luid: NET_LUID,
+        callback: StatusCallback,
+    ) -> io::Result<Self> {
+        let context = Box::new(StatusContext {
+            luid,
+            up: Mutex::new(is_up(&luid)),
+            callback,
+        });
+
+        let handle = ffi::notify_ip_interface_change(
+            AF_UNSPEC as _,
+            Some(status_callback),
+            &*context as *const StatusContext as _,
+        )?;
+
+        Ok(Self {
+            handle: SendHandle(handle),
+            _context: context,
+        })
+    }
+}
+
+impl Drop for StatusWatcher {
+    fn drop(&mut self) {
+        // Waits for running callbacks to complete
+        let _ = ffi::cancel_mib_change_notify2(self.handle.0);
+    }
+}
+
+fn is_up(luid: &NET_LUID) -> bool {
+    ffi::get_if_entry2(luid)
+        .map(|row| row.OperStatus == IfOperStatusUp)
+        .unwrap_or(false)
+}
+
+unsafe extern "system" fn status_callback(
+    context: PVOID,
+    row: PMIB_IPINTERFACE_ROW,
+    notification_type: MIB_NOTIFICATION_TYPE,
+) {
+    let context = &*(context as *const StatusContext);
+
+    if row.is_null() || (*row).InterfaceLuid.Value != context.luid.Value {
+        return;
+    }
+
+    // The row only holds part of the state, so ask for the rest.
+    // Notifications come once per address family, hence the
+    // comparison with the last known state
+    let up = if notification_type == MibDeleteInstance {
+        false
+    } else {
+        is_up(&context.luid)
+    };
+
+    let mut last = match context.up.lock() {
+        Ok(last) => last,
+        Err(_) => return,
+    };
+
+    if *last == up {
+        return;
+    }
+
+    *last = up;
+
+    let event = if up {
+        StatusEvent::Up
+    } else {
+        StatusEvent::Down
+    };
+
+    // Unwinding into the system is not allowed
+    let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        (context.callback)(event)
+    }));
+}
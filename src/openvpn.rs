@@ -0,0 +1,577 @@
+//! Interpreter for the options an OpenVPN server pushes to its clients
+use std::iter::FromIterator;
+use std::{error, fmt, io, net, str};
+
+use crate::{AddressConfig, Device, DeviceConfig, RouteConfig};
+
+/// Error returned when a push option can't be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePushError {
+    directive: String,
+}
+
+impl ParsePushError {
+    fn new(directive: &str) -> Self {
+        ParsePushError {
+            directive: directive.to_owned(),
+        }
+    }
+
+    /// The directive that failed to parse
+    pub fn directive(&self) -> &str {
+        &self.directive
+    }
+}
+
+impl fmt::Display for ParsePushError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid push option {:?}", self.directive)
+    }
+}
+
+impl error::Error for ParsePushError {}
+
+impl From<ParsePushError> for io::Error {
+    fn from(err: ParsePushError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Meaning of the second `ifconfig` argument
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Topology {
+    /// The argument is the netmask of the subnet, the default
+    /// for tap devices
+    Subnet,
+    /// The argument is the remote end of a /30 subnet
+    Net30,
+    /// The argument is the remote end of a point to point link
+    P2p,
+}
+
+/// Next hop of a pushed route
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PushGateway {
+    /// The server end of the tunnel, `vpn_gateway`
+    Vpn,
+    /// The gateway of the physical network, `net_gateway`
+    Net,
+    /// An explicit address
+    Address(net::Ipv4Addr),
+}
+
+/// Single directive understood by `PushOptions`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PushOption {
+    /// `ifconfig local remote-netmask`
+    Ifconfig {
+        local: net::Ipv4Addr,
+        remote_netmask: net::Ipv4Addr,
+    },
+    /// `route network [netmask] [gateway] [metric]`
+    Route {
+        network: net::Ipv4Addr,
+        netmask: net::Ipv4Addr,
+        gateway: Option<PushGateway>,
+        metric: Option<u32>,
+    },
+    /// `route-gateway gateway`
+    RouteGateway(net::Ipv4Addr),
+    /// `redirect-gateway [flags]`, `def1` overrides the default
+    /// route with two /1 routes instead of replacing it
+    RedirectGateway { def1: bool },
+    /// `dhcp-option DNS address` and `dhcp-option DNS6 address`
+    Dns(net::IpAddr),
+    /// `topology subnet|net30|p2p`
+    Topology(Topology),
+    /// `tun-mtu mtu`
+    TunMtu(u32),
+}
+
+impl PushOption {
+    /// Parse a single directive, `None` for directives that are
+    /// valid but have no meaning for the device
+    fn parse(directive: &str) -> Result<Option<Self>, ParsePushError> {
+        let err = || ParsePushError::new(directive);
+        let mut args = directive.split_whitespace();
+
+        fn opt_arg<T: str::FromStr>(
+            arg: Option<&str>,
+        ) -> Result<Option<T>, ()> {
+            match arg {
+                None | Some("default") => Ok(None),
+                Some(arg) => arg.parse().map(Some).map_err(|_| ()),
+            }
+        }
+
+        let option = match args.next() {
+            Some("ifconfig") => {
+                let local = args.next().and_then(|arg| arg.parse().ok());
+                let remote = args.next().and_then(|arg| arg.parse().ok());
+
+                match (local, remote) {
+                    (Some(local), Some(remote_netmask)) => {
+                        PushOption::Ifconfig {
+                            local,
+                            remote_netmask,
+                        }
+                    }
+                    _ => return Err(err()),
+                }
+            }
+            Some("route") => {
+                let network = args
+                    .next()
+                    .and_then(|arg| arg.parse().ok())
+                    .ok_or_else(err)?;
+                let netmask = opt_arg(args.next())
+                    .map_err(|_| err())?
+                    .unwrap_or(net::Ipv4Addr::BROADCAST);
+                prefix_len(netmask).ok_or_else(err)?;
+                let gateway = match args.next() {
+                    None | Some("default") => None,
+                    Some("vpn_gateway") => Some(PushGateway::Vpn),
+                    Some("net_gateway") => Some(PushGateway::Net),
+                    Some(arg) => Some(PushGateway::Address(
+                        arg.parse().map_err(|_| err())?,
+                    )),
+                };
+                let metric = opt_arg(args.next()).map_err(|_| err())?;
+
+                PushOption::Route {
+                    network,
+                    netmask,
+                    gateway,
+                    metric,
+                }
+            }
+            Some("route-gateway") => match args.next() {
+                // Learning the gateway through dhcp is up to Windows
+                Some("dhcp") => return Ok(None),
+                arg => PushOption::RouteGateway(
+                    arg.and_then(|arg| arg.parse().ok()).ok_or_else(err)?,
+                ),
+            },
+            Some("redirect-gateway") => PushOption::RedirectGateway {
+                def1: args.any(|flag| flag == "def1"),
+            },
+            Some("dhcp-option") => match args.next() {
+                Some("DNS") | Some("DNS6") => PushOption::Dns(
+                    args.next()
+                        .and_then(|arg| arg.parse().ok())
+                        .ok_or_else(err)?,
+                ),
+                _ => return Ok(None),
+            },
+            Some("topology") => PushOption::Topology(match args.next() {
+                Some("subnet") => Topology::Subnet,
+                Some("net30") => Topology::Net30,
+                Some("p2p") => Topology::P2p,
+                _ => return Err(err()),
+            }),
+            Some("tun-mtu") => PushOption::TunMtu(
+                args.next()
+                    .and_then(|arg| arg.parse().ok())
+                    .ok_or_else(err)?,
+            ),
+            _ => return Ok(None),
+        };
+
+        Ok(Some(option))
+    }
+}
+
+/// Options pushed by an OpenVPN server, parsed from either a
+/// `PUSH_REPLY` message or a config file, one directive per line.
+/// Directives that have no meaning for the device, such as
+/// `ping` or `cipher`, are ignored.
+/// The route towards the server itself, through the physical
+/// network, is left to the caller
+/// Example
+/// ```no_run
+/// use tap_windows::{Device, PushOptions};
+///
+/// let dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// let options: PushOptions = "PUSH_REPLY,route-gateway 10.8.0.1,\
+///     topology subnet,ifconfig 10.8.0.2 255.255.255.0,\
+///     redirect-gateway def1,dhcp-option DNS 10.8.0.1"
+///     .parse()
+///     .expect("Invalid push reply");
+///
+/// dev.apply_push_options(&options)
+///     .expect("Failed to apply push options");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PushOptions {
+    options: Vec<PushOption>,
+}
+
+impl PushOptions {
+    /// Get the parsed options, in the order they were received
+    pub fn options(&self) -> &[PushOption] {
+        &self.options
+    }
+
+    /// Translate the options into the configuration of the device.
+    /// Fields without a matching option are left `None`, and routes
+    /// through `net_gateway` are skipped as they don't belong to
+    /// the device. Fails if a netmask isn't contiguous
+    pub fn to_config(&self) -> Result<DeviceConfig, ParsePushError> {
+        let mut topology = Topology::Subnet;
+        let mut route_gateway = None;
+        let mut ifconfig = None;
+        let mut mtu = None;
+
+        for option in &self.options {
+            match *option {
+                PushOption::Topology(value) => topology = value,
+                PushOption::RouteGateway(gateway) => {
+                    route_gateway = Some(gateway)
+                }
+                PushOption::Ifconfig {
+                    local,
+                    remote_netmask,
+                } => ifconfig = Some((local, remote_netmask)),
+                PushOption::TunMtu(value) => mtu = Some(value),
+                _ => (),
+            }
+        }
+
+        let (address, peer) = match ifconfig {
+            Some((local, netmask)) if topology == Topology::Subnet => {
+                let prefix = prefix_len(netmask).ok_or_else(|| {
+                    ParsePushError::new(&format!(
+                        "ifconfig {} {}",
+                        local, netmask
+                    ))
+                })?;
+
+                (Some((local, prefix)), None)
+            }
+            Some((local, remote)) if topology == Topology::Net30 => {
+                (Some((local, 30)), Some(remote))
+            }
+            Some((local, remote)) => (Some((local, 32)), Some(remote)),
+            None => (None, None),
+        };
+        let vpn_gateway = route_gateway.or(peer).map(net::IpAddr::from);
+
+        let mut routes = None;
+        let mut dns = None;
+
+        for option in &self.options {
+            match *option {
+                PushOption::Route {
+                    network,
+                    netmask,
+                    gateway,
+                    metric,
+                } => {
+                    let gateway = match gateway {
+                        None | Some(PushGateway::Vpn) => vpn_gateway,
+                        Some(PushGateway::Address(gateway)) => {
+                            Some(gateway.into())
+                        }
+                        Some(PushGateway::Net) => {
+                            warn!(%network, "skipping net_gateway route");
+                            continue;
+                        }
+                    };
+
+                    let prefix = prefix_len(netmask).ok_or_else(|| {
+                        ParsePushError::new(&format!(
+                            "route {} {}",
+                            network, netmask
+                        ))
+                    })?;
+
+                    routes.get_or_insert_with(Vec::new).push(RouteConfig {
+                        destination: network.into(),
+                        prefix,
+                        gateway,
+                        metric,
+                    });
+                }
+                PushOption::RedirectGateway { def1 } => {
+                    let halves: &[_] = if def1 {
+                        &[([0, 0, 0, 0], 1), ([128, 0, 0, 0], 1)]
+                    } else {
+                        &[([0, 0, 0, 0], 0)]
+                    };
+
+                    let routes = routes.get_or_insert_with(Vec::new);
+                    for &(destination, prefix) in halves {
+                        routes.push(RouteConfig {
+                            destination: destination.into(),
+                            prefix,
+                            gateway: vpn_gateway,
+                            metric: None,
+                        });
+                    }
+                }
+                PushOption::Dns(server) => {
+                    dns.get_or_insert_with(Vec::new).push(server)
+                }
+                _ => (),
+            }
+        }
+
+        Ok(DeviceConfig {
+            addresses: address.map(|(address, prefix)| {
+                vec![AddressConfig {
+                    address: address.into(),
+                    prefix,
+                }]
+            }),
+            dns,
+            routes,
+            mtu,
+            ..Default::default()
+        })
+    }
+}
+
+/// Prefix length of a netmask, `None` if its ones aren't contiguous
+fn prefix_len(netmask: net::Ipv4Addr) -> Option<u8> {
+    let mask = u32::from(netmask);
+    let len = mask.leading_ones();
+
+    match mask.checked_shl(len).unwrap_or(0) {
+        0 => Some(len as u8),
+        _ => None,
+    }
+}
+
+impl str::FromStr for PushOptions {
+    type Err = ParsePushError;
+
+    fn from_str(s: &str) -> Result<Self, ParsePushError> {
+        let mut options = Vec::new();
+
+        for directive in s.split([',', '\n']) {
+            let directive = directive.trim();
+
+            if directive.is_empty()
+                || directive == "PUSH_REPLY"
+                || directive.starts_with(['#', ';'])
+            {
+                continue;
+            }
+
+            options.extend(PushOption::parse(directive)?);
+        }
+
+        Ok(PushOptions { options })
+    }
+}
+
+impl From<Vec<PushOption>> for PushOptions {
+    fn from(options: Vec<PushOption>) -> Self {
+        PushOptions { options }
+    }
+}
+
+impl FromIterator<PushOption> for PushOptions {
+    fn from_iter<I: IntoIterator<Item = PushOption>>(iter: I) -> Self {
+        PushOptions {
+            options: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Device {
+    /// Configure the device as an OpenVPN client would after receiving
+    /// the options, see `PushOptions`. Pushed addresses, routes and
+    /// dns servers replace the current ones
+    pub fn apply_push_options(&self, options: &PushOptions) -> io::Result<()> {
+        self.apply(&options.to_config()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(address: &str) -> net::Ipv4Addr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_directives() {
+        let cases = [
+            (
+                "ifconfig 10.8.0.2 255.255.255.0",
+                Some(PushOption::Ifconfig {
+                    local: ip("10.8.0.2"),
+                    remote_netmask: ip("255.255.255.0"),
+                }),
+            ),
+            (
+                "route 192.168.1.0 255.255.255.0",
+                Some(PushOption::Route {
+                    network: ip("192.168.1.0"),
+                    netmask: ip("255.255.255.0"),
+                    gateway: None,
+                    metric: None,
+                }),
+            ),
+            (
+                "route 10.0.0.1",
+                Some(PushOption::Route {
+                    network: ip("10.0.0.1"),
+                    netmask: net::Ipv4Addr::BROADCAST,
+                    gateway: None,
+                    metric: None,
+                }),
+            ),
+            (
+                "route 10.0.0.0 255.0.0.0 net_gateway 5",
+                Some(PushOption::Route {
+                    network: ip("10.0.0.0"),
+                    netmask: ip("255.0.0.0"),
+                    gateway: Some(PushGateway::Net),
+                    metric: Some(5),
+                }),
+            ),
+            (
+                "route 10.0.0.0 default 10.8.0.9 default",
+                Some(PushOption::Route {
+                    network: ip("10.0.0.0"),
+                    netmask: net::Ipv4Addr::BROADCAST,
+                    gateway: Some(PushGateway::Address(ip("10.8.0.9"))),
+                    metric: None,
+                }),
+            ),
+            (
+                "dhcp-option DNS 10.8.0.1",
+                Some(PushOption::Dns(ip("10.8.0.1").into())),
+            ),
+            (
+                "dhcp-option DNS6 fd00::1",
+                Some(PushOption::Dns("fd00::1".parse().unwrap())),
+            ),
+            ("dhcp-option DOMAIN example.com", None),
+            ("route-gateway dhcp", None),
+            ("ping 10", None),
+            (
+                "redirect-gateway def1 bypass-dhcp",
+                Some(PushOption::RedirectGateway { def1: true }),
+            ),
+            (
+                "topology net30",
+                Some(PushOption::Topology(Topology::Net30)),
+            ),
+            ("tun-mtu 1400", Some(PushOption::TunMtu(1400))),
+        ];
+
+        for (directive, expected) in cases.iter() {
+            assert_eq!(
+                PushOption::parse(directive).as_ref(),
+                Ok(expected),
+                "{}",
+                directive
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_directives() {
+        let cases = [
+            "ifconfig 10.8.0.2",
+            "ifconfig 10.8.0.2 netmask",
+            "route",
+            "route 10.0.0.0 255.0.255.0",
+            "route 10.0.0.0 255.0.0.0 gateway",
+            "route 10.0.0.0 255.0.0.0 vpn_gateway -1",
+            "route-gateway",
+            "dhcp-option DNS",
+            "dhcp-option DNS dns.example.com",
+            "topology star",
+            "tun-mtu big",
+        ];
+
+        for directive in cases.iter() {
+            assert_eq!(
+                PushOption::parse(directive),
+                Err(ParsePushError::new(directive)),
+                "{}",
+                directive
+            );
+        }
+    }
+
+    #[test]
+    fn parses_push_reply() {
+        let options: PushOptions = "PUSH_REPLY,route-gateway 10.8.0.1,\
+            topology subnet,ping 10,ifconfig 10.8.0.2 255.255.255.0,\
+            redirect-gateway def1,dhcp-option DNS 10.8.0.1"
+            .parse()
+            .unwrap();
+
+        let config = options.to_config().unwrap();
+        let route = |destination: [u8; 4], prefix| RouteConfig {
+            destination: destination.into(),
+            prefix,
+            gateway: Some(ip("10.8.0.1").into()),
+            metric: None,
+        };
+
+        assert_eq!(
+            config.addresses,
+            Some(vec![AddressConfig {
+                address: ip("10.8.0.2").into(),
+                prefix: 24,
+            }])
+        );
+        assert_eq!(
+            config.routes,
+            Some(vec![route([0, 0, 0, 0], 1), route([128, 0, 0, 0], 1)])
+        );
+        assert_eq!(config.dns, Some(vec![ip("10.8.0.1").into()]));
+    }
+
+    #[test]
+    fn parses_config_files() {
+        let options: PushOptions = "# pushed by hand\n\
+            ; disabled\n\
+            topology net30\n\
+            ifconfig 10.8.0.6 10.8.0.5\n\
+            route 192.168.1.0 255.255.255.0\n"
+            .parse()
+            .unwrap();
+
+        let config = options.to_config().unwrap();
+
+        assert_eq!(
+            config.addresses,
+            Some(vec![AddressConfig {
+                address: ip("10.8.0.6").into(),
+                prefix: 30,
+            }])
+        );
+        assert_eq!(
+            config.routes,
+            Some(vec![RouteConfig {
+                destination: ip("192.168.1.0").into(),
+                prefix: 24,
+                gateway: Some(ip("10.8.0.5").into()),
+                metric: None,
+            }])
+        );
+    }
+
+    #[test]
+    fn rejects_non_contiguous_netmasks() {
+        assert_eq!(prefix_len(ip("255.255.255.0")), Some(24));
+        assert_eq!(prefix_len(ip("255.255.255.255")), Some(32));
+        assert_eq!(prefix_len(ip("0.0.0.0")), Some(0));
+        assert_eq!(prefix_len(ip("255.0.255.0")), None);
+        assert_eq!(prefix_len(ip("0.0.0.255")), None);
+
+        let options: PushOptions =
+            "ifconfig 10.8.0.2 255.0.255.0".parse().unwrap();
+        assert!(options.to_config().is_err());
+    }
+}
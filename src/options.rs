@@ -0,0 +1,70 @@
+use winapi::shared::guiddef::GUID;
+
+use std::time::Duration;
+
+use crate::{MacAddress, DEFAULT_COMPONENT_ID};
+
+/// How long to wait for a new adapter to become available by default
+pub(crate) const DEFAULT_OPEN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// First delay between attempts at opening a new adapter by default
+pub(crate) const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Options used when creating a device with `Device::create_with`
+/// Example
+/// ```no_run
+/// use tap_windows::{CreateOptions, Device};
+///
+/// let dev = Device::create_with(CreateOptions {
+///     name: Some("My Interface"),
+///     description: Some("Acme VPN Adapter"),
+///     mac: Some([0x00, 0xff, 0x12, 0x34, 0x56, 0x78].into()),
+///     ..Default::default()
+/// })
+/// .expect("Failed to create device");
+///
+/// println!("{:?}", dev.get_name());
+/// ```
+#[derive(Clone, Copy)]
+pub struct CreateOptions<'a> {
+    /// Hardware id of the driver to bind the adapter to
+    pub component_id: &'a str,
+    /// Name (alias) to give to the interface
+    pub name: Option<&'a str>,
+    /// Description shown in Device Manager
+    pub description: Option<&'a str>,
+    /// Mac address of the adapter
+    pub mac: Option<MacAddress>,
+    /// Guid of the adapter, stable across reinstalls
+    pub guid: Option<GUID>,
+    /// How long to wait for the new adapter to become available,
+    /// slow or antivirus heavy machines may need more than the
+    /// default of 2 seconds
+    pub open_timeout: Duration,
+    /// Delay before the first retry at opening the new adapter,
+    /// it is doubled after every failed attempt
+    pub poll_interval: Duration,
+    /// Open the handle as inheritable, so it can be passed to a
+    /// child process, see `Device::set_inheritable`
+    pub inheritable: bool,
+    /// Mark the adapter as an endpoint device, so Windows doesn't
+    /// ask the user whether the new network is public or private.
+    /// The interface then gets no network profile at all
+    pub suppress_network_prompt: bool,
+}
+
+impl Default for CreateOptions<'_> {
+    fn default() -> Self {
+        Self {
+            component_id: DEFAULT_COMPONENT_ID,
+            name: None,
+            description: None,
+            mac: None,
+            guid: None,
+            open_timeout: DEFAULT_OPEN_TIMEOUT,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            inheritable: false,
+            suppress_network_prompt: false,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use winapi::um::minwinbase::OVERLAPPED;
+
+use std::{io, mem, ops};
+
+use crate::ffi;
+
+/// An OVERLAPPED structure with its own event, boxed so that it
+/// doesn't move while an operation is pending
+pub(crate) struct Overlapped(Box<OVERLAPPED>);
+
+// The event handle can be used from any thread
+unsafe impl Send for Overlapped {}
+unsafe impl Sync for Overlapped {}
+
+impl Overlapped {
+    pub fn new() -> io::Result<Self> {
+        let mut overlapped: Box<OVERLAPPED> =
+            Box::new(unsafe { mem::zeroed() });
+        overlapped.hEvent = ffi::create_event()?;
+
+        Ok(Self(overlapped))
+    }
+}
+
+impl ops::Deref for Overlapped {
+    type Target = OVERLAPPED;
+
+    fn deref(&self) -> &OVERLAPPED {
+        &self.0
+    }
+}
+
+impl ops::DerefMut for Overlapped {
+    fn deref_mut(&mut self) -> &mut OVERLAPPED {
+        &mut self.0
+    }
+}
+
+impl Drop for Overlapped {
+    fn drop(&mut self) {
+        let _ = ffi::close_handle(self.0.hEvent);
+    }
+}
@@ -0,0 +1,58 @@
+use winapi::shared::minwindef::DWORD;
+
+use std::{io, process, time};
+
+use crate::registry::RegKey;
+
+const OWNER_PRODUCT: &str = "TapWindowsOwnerProduct";
+const OWNER_PID: &str = "TapWindowsOwnerPid";
+const OWNER_CREATED: &str = "TapWindowsOwnerCreated";
+
+/// Ownership tag stored in the driver registry key of an adapter,
+/// lets products sharing the same driver recognize their own adapters
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OwnerTag {
+    /// Name of the product owning the adapter
+    pub product: String,
+    /// Id of the process that tagged the adapter
+    pub pid: u32,
+    /// When the adapter was tagged, in seconds since the unix epoch
+    pub created: u64,
+}
+
+impl OwnerTag {
+    /// Create a new tag for the current process
+    pub fn new(product: &str) -> Self {
+        let created = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .map(|created| created.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            product: product.to_owned(),
+            pid: process::id(),
+            created,
+        }
+    }
+
+    /// Read the tag from a driver key, if present
+    pub(crate) fn read(key: &RegKey) -> Option<Self> {
+        let product: String = key.get_value(OWNER_PRODUCT).ok()?;
+        let pid: DWORD = key.get_value(OWNER_PID).unwrap_or(0);
+        let created: u64 = key.get_value(OWNER_CREATED).unwrap_or(0);
+
+        Some(Self {
+            product,
+            pid,
+            created,
+        })
+    }
+
+    /// Write the tag to a driver key
+    pub(crate) fn write(&self, key: &RegKey) -> io::Result<()> {
+        key.set_value(OWNER_PRODUCT, &self.product)?;
+        key.set_value(OWNER_PID, &(self.pid as DWORD))?;
+        key.set_value(OWNER_CREATED, &self.created)
+    }
+}
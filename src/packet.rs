@@ -0,0 +1,182 @@
+// Not every helper is used by every feature
+#![allow(unused)]
+
+//! Minimal ethernet, ipv4 and udp handling shared by the packet
+//! utilities (dhcp, icmp, mss clamping, vlan, dscp marking)
+
+use std::net::Ipv4Addr;
+
+use crate::MacAddress;
+
+pub const ETHER_HEADER_LEN: usize = 14;
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+pub const IPPROTO_ICMP: u8 = 1;
+pub const IPPROTO_TCP: u8 = 6;
+pub const IPPROTO_UDP: u8 = 17;
+
+pub fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+pub fn write_u16(data: &mut [u8], offset: usize, value: u16) {
+    data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+pub fn ether_type(frame: &[u8]) -> Option<u16> {
+    match frame.len() >= ETHER_HEADER_LEN {
+        true => Some(read_u16(frame, 12)),
+        false => None,
+    }
+}
+
+pub fn source_mac(frame: &[u8]) -> MacAddress {
+    let mut mac = [0; 6];
+    mac.copy_from_slice(&frame[6..12]);
+    MacAddress(mac)
+}
+
+/// Location of the ipv4 header and payload inside a frame
+pub struct Ipv4 {
+    /// Offset of the ip header
+    pub start: usize,
+    /// Offset of the payload
+    pub payload: usize,
+    /// End of the ip packet, ethernet padding excluded
+    pub end: usize,
+}
+
+impl Ipv4 {
+    pub fn protocol(&self, frame: &[u8]) -> u8 {
+        frame[self.start + 9]
+    }
+
+    pub fn source(&self, frame: &[u8]) -> Ipv4Addr {
+        let start = self.start + 12;
+        Ipv4Addr::new(
+            frame[start],
+            frame[start + 1],
+            frame[start + 2],
+            frame[start + 3],
+        )
+    }
+
+    pub fn destination(&self, frame: &[u8]) -> Ipv4Addr {
+        let start = self.start + 16;
+        Ipv4Addr::new(
+            frame[start],
+            frame[start + 1],
+            frame[start + 2],
+            frame[start + 3],
+        )
+    }
+
+    /// True for fragments other than the first one
+    pub fn is_later_fragment(&self, frame: &[u8]) -> bool {
+        read_u16(frame, self.start + 6) & 0x1fff != 0
+    }
+}
+
+/// Ether type and payload offset of a frame, looking past any
+/// 802.1Q tags
+pub fn untagged_ether_type(frame: &[u8]) -> Option<(u16, usize)> {
+    let mut offset = 12;
+
+    loop {
+        match read_u16(frame.get(..offset + 2)?, offset) {
+            ETHERTYPE_VLAN => offset += 4,
+            ether_type => return Some((ether_type, offset + 2)),
+        }
+    }
+}
+
+/// Find the ipv4 packet of an untagged ethernet frame
+pub fn parse_ipv4(frame: &[u8]) -> Option<Ipv4> {
+    if ether_type(frame)? != ETHERTYPE_IPV4 {
+        return None;
+    }
+
+    parse_ipv4_at(frame, ETHER_HEADER_LEN)
+}
+
+/// Find the ipv4 packet whose header starts at `start`
+pub fn parse_ipv4_at(frame: &[u8], start: usize) -> Option<Ipv4> {
+    let version_ihl = *frame.get(start)?;
+    if version_ihl >> 4 != 4 {
+        return None;
+    }
+
+    let payload = start + (version_ihl & 0x0f) as usize * 4;
+    let end = start + read_u16(frame.get(..start + 4)?, start + 2) as usize;
+
+    if payload < start + 20 || end < payload || end > frame.len() {
+        return None;
+    }
+
+    Some(Ipv4 {
+        start,
+        payload,
+        end,
+    })
+}
+
+/// Sum of 16 bit words, as used by the internet checksum
+pub fn checksum_add(mut sum: u32, data: &[u8]) -> u32 {
+    let mut words = data.chunks_exact(2);
+
+    for word in &mut words {
+        sum += u16::from_be_bytes([word[0], word[1]]) as u32;
+    }
+
+    if let [last] = words.remainder() {
+        sum += (*last as u32) << 8;
+    }
+
+    sum
+}
+
+pub fn checksum_finish(mut sum: u32) -> u16 {
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+pub fn checksum(data: &[u8]) -> u16 {
+    checksum_finish(checksum_add(0, data))
+}
+
+/// Build an ethernet frame carrying an ipv4 packet
+pub fn build_ipv4_frame(
+    destination_mac: MacAddress,
+    source_mac: MacAddress,
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    protocol: u8,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(ETHER_HEADER_LEN + 20 + payload.len());
+
+    frame.extend_from_slice(&destination_mac.octets());
+    frame.extend_from_slice(&source_mac.octets());
+    frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+
+    let mut header = [0u8; 20];
+    header[0] = 0x45;
+    write_u16(&mut header, 2, (20 + payload.len()) as u16);
+    header[8] = 64;
+    header[9] = protocol;
+    header[12..16].copy_from_slice(&source.octets());
+    header[16..20].copy_from_slice(&destination.octets());
+    let sum = checksum(&header);
+    write_u16(&mut header, 10, sum);
+
+    frame.extend_from_slice(&header);
+    frame.extend_from_slice(payload);
+
+    frame
+}
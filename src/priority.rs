@@ -0,0 +1,128 @@
+use winapi::shared::ifdef::NET_LUID;
+use winapi::shared::winerror::ERROR_NOT_FOUND;
+use winapi::shared::ws2def::{ADDRESS_FAMILY, AF_INET, AF_INET6};
+
+use std::io;
+
+use crate::{ffi, Device};
+
+/// Where to move an interface in the metric ordering, see
+/// `Device::set_priority`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InterfacePriority {
+    /// Ahead of every other connected interface
+    Highest,
+    /// Behind every other connected interface
+    Lowest,
+}
+
+/// Metric settings of one address family
+struct SavedMetric {
+    family: ADDRESS_FAMILY,
+    use_automatic_metric: bool,
+    metric: u32,
+}
+
+/// Restores the previous metrics of the interface when dropped,
+/// returned by `Device::set_priority`
+#[must_use = "the previous priority is restored when the guard is dropped"]
+pub struct PriorityGuard {
+    luid: NET_LUID,
+    saved: Vec<SavedMetric>,
+}
+
+impl PriorityGuard {
+    /// Keep the new priority, the previous metrics are forgotten
+    pub fn keep(mut self) {
+        self.saved.clear();
+    }
+}
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        for saved in &self.saved {
+            let res = ffi::get_ip_interface_entry(&self.luid, saved.family)
+                .and_then(|mut row| {
+                    row.UseAutomaticMetric = saved.use_automatic_metric as _;
+                    row.Metric = saved.metric;
+                    ffi::set_ip_interface_entry(&row)
+                });
+
+            if let Err(_err) = res {
+                warn!(error = %_err, "failed to restore interface metric");
+            }
+        }
+    }
+}
+
+impl Device {
+    /// Move the interface ahead of or behind every other connected
+    /// interface, for both ipv4 and ipv6, by giving it a metric
+    /// just below or above theirs. The previous metrics are
+    /// restored when the returned guard is dropped, unless `keep`
+    /// is called on it
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, InterfacePriority};
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// // Preferred over the physical interfaces while the tunnel is up
+    /// let _priority = dev.set_priority(InterfacePriority::Highest)
+    ///     .expect("Failed to set device priority");
+    /// ```
+    pub fn set_priority(
+        &self,
+        priority: InterfacePriority,
+    ) -> io::Result<PriorityGuard> {
+        // Restores the families already changed if one fails
+        let mut guard = PriorityGuard {
+            luid: self.luid,
+            saved: Vec::new(),
+        };
+
+        for &family in &[AF_INET as ADDRESS_FAMILY, AF_INET6 as _] {
+            let mut row = match ffi::get_ip_interface_entry(&self.luid, family)
+            {
+                Ok(row) => row,
+                // Ipv6 might be disabled on the interface
+                Err(err)
+                    if err.raw_os_error() == Some(ERROR_NOT_FOUND as _) =>
+                {
+                    continue
+                }
+                Err(err) => return Err(err),
+            };
+
+            let others = ffi::get_ip_interface_table(family)?
+                .into_iter()
+                .filter(|other| other.InterfaceLuid.Value != self.luid.Value)
+                .filter(|other| other.Connected != 0)
+                .map(|other| other.Metric);
+
+            let metric = match priority {
+                InterfacePriority::Highest => {
+                    others.min().map_or(1, |min| min.saturating_sub(1).max(1))
+                }
+                InterfacePriority::Lowest => {
+                    others.max().map_or(9999, |max| max.saturating_add(1))
+                }
+            };
+
+            let saved = SavedMetric {
+                family,
+                use_automatic_metric: row.UseAutomaticMetric != 0,
+                metric: row.Metric,
+            };
+
+            row.UseAutomaticMetric = 0;
+            row.Metric = metric;
+            ffi::set_ip_interface_entry(&row)?;
+
+            guard.saved.push(saved);
+        }
+
+        Ok(guard)
+    }
+}
@@ -0,0 +1,117 @@
+use std::io;
+
+use crate::{elevation, wmi, Device};
+
+/// Category of a network connection profile, which decides the
+/// firewall profile applied to the traffic of the adapter
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum NetworkCategory {
+    /// Untrusted network, the most restrictive firewall profile
+    Public,
+    /// Trusted home or work network
+    Private,
+    /// Network where the machine authenticated to its domain,
+    /// assigned by Windows and never set by hand
+    DomainAuthenticated,
+}
+
+/// Network location awareness profile an adapter is attached to,
+/// see `Device::get_network_profile`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NetworkProfile {
+    /// Name of the network, as shown in the network settings
+    pub name: String,
+    /// Category of the network
+    pub category: NetworkCategory,
+}
+
+impl Device {
+    /// Retrieve the network profile the adapter is attached to, or
+    /// `None` if it has none, for example while it is disconnected
+    /// or when it was created with `suppress_network_prompt`.
+    /// Requires Windows 8 or later
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, NetworkCategory};
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let profile = dev.get_network_profile()
+    ///     .expect("Failed to get network profile");
+    ///
+    /// match profile {
+    ///     Some(profile) if profile.category == NetworkCategory::Public => {
+    ///         println!("{} uses the public firewall profile", profile.name)
+    ///     }
+    ///     Some(profile) => println!("{} is trusted", profile.name),
+    ///     None => println!("No network profile yet"),
+    /// }
+    /// ```
+    pub fn get_network_profile(&self) -> io::Result<Option<NetworkProfile>> {
+        let output = wmi::get_interface_profile(self.get_index()?)?;
+
+        let output = output.trim();
+        if output.is_empty() {
+            return Ok(None);
+        }
+
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid network profile output",
+            )
+        };
+
+        // The name may contain the separator, the category can't
+        let (name, category) = output.rsplit_once('|').ok_or_else(invalid)?;
+
+        let category = match category {
+            "0" => NetworkCategory::Public,
+            "1" => NetworkCategory::Private,
+            "2" => NetworkCategory::DomainAuthenticated,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Some(NetworkProfile {
+            name: name.to_owned(),
+            category,
+        }))
+    }
+
+    /// Set the category of the network profile the adapter is
+    /// attached to, failing if it has none yet.
+    /// `DomainAuthenticated` can't be set and is rejected.
+    /// Requires administrator rights and Windows 8 or later
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, NetworkCategory};
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_network_category(NetworkCategory::Private)
+    ///     .expect("Failed to set network category");
+    /// ```
+    pub fn set_network_category(
+        &self,
+        category: NetworkCategory,
+    ) -> io::Result<()> {
+        elevation::require_elevated()?;
+
+        let category = match category {
+            NetworkCategory::Public => "Public",
+            NetworkCategory::Private => "Private",
+            NetworkCategory::DomainAuthenticated => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The domain category is assigned by Windows",
+                ))
+            }
+        };
+
+        wmi::set_interface_category(self.get_index()?, category)
+    }
+}
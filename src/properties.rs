@@ -0,0 +1,126 @@
+use winapi::um::winnt::{KEY_QUERY_VALUE, KEY_SET_VALUE};
+
+use std::io;
+
+use crate::registry::RegKey;
+use crate::{elevation, iface, Device};
+
+/// Smallest and largest mtu accepted by the tap-windows6 driver
+const MIN_DRIVER_MTU: u32 = 100;
+const MAX_DRIVER_MTU: u32 = 1500;
+
+/// How the driver reports the media status, see `DriverProperties`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MediaStatusMode {
+    /// Disconnected until set with `Device::set_status`, the default
+    ApplicationControlled,
+    /// Always connected
+    AlwaysConnected,
+}
+
+/// Advanced properties of the tap-windows6 driver, as shown in the
+/// Advanced tab of the adapter in Device Manager. When setting,
+/// fields set to `None` are left untouched
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DriverProperties {
+    /// Mtu of the adapter, from 100 to 1500
+    pub mtu: Option<u32>,
+    /// How the media status is reported
+    pub media_status: Option<MediaStatusMode>,
+    /// Let non administrators open the adapter
+    pub allow_non_admin: Option<bool>,
+}
+
+/// The driver stores its properties as strings, unset ones
+/// are `None`
+fn get_string(key: &RegKey, name: &str) -> Option<String> {
+    key.get_value::<String, _>(name).ok()
+}
+
+impl Device {
+    /// Read the advanced properties of the driver, properties
+    /// never set are `None`
+    pub fn get_driver_properties(&self) -> io::Result<DriverProperties> {
+        let key = iface::open_driver_key(
+            &self.component_id,
+            &self.luid,
+            KEY_QUERY_VALUE,
+        )?;
+
+        Ok(DriverProperties {
+            mtu: get_string(&key, "MTU").and_then(|mtu| mtu.parse().ok()),
+            media_status: get_string(&key, "MediaStatus").map(|status| {
+                match status.as_str() {
+                    "1" => MediaStatusMode::AlwaysConnected,
+                    _ => MediaStatusMode::ApplicationControlled,
+                }
+            }),
+            allow_non_admin: get_string(&key, "AllowNonAdmin")
+                .map(|allow| allow == "1"),
+        })
+    }
+
+    /// Write the advanced properties of the driver and restart the
+    /// device to apply them, see `restart`. Nothing is restarted if
+    /// no property is set. Requires administrator rights
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, DriverProperties, MediaStatusMode};
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.set_driver_properties(&DriverProperties {
+    ///     mtu: Some(1400),
+    ///     media_status: Some(MediaStatusMode::AlwaysConnected),
+    ///     ..Default::default()
+    /// })
+    /// .expect("Failed to set driver properties");
+    /// ```
+    pub fn set_driver_properties(
+        &mut self,
+        properties: &DriverProperties,
+    ) -> io::Result<()> {
+        if *properties == DriverProperties::default() {
+            return Ok(());
+        }
+
+        if let Some(mtu) = properties.mtu {
+            if !(MIN_DRIVER_MTU..=MAX_DRIVER_MTU).contains(&mtu) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Driver mtu must be between 100 and 1500",
+                ));
+            }
+        }
+
+        elevation::require_elevated()?;
+
+        let key = iface::open_driver_key(
+            &self.component_id,
+            &self.luid,
+            KEY_SET_VALUE,
+        )?;
+
+        if let Some(mtu) = properties.mtu {
+            key.set_value("MTU", &mtu.to_string())?;
+        }
+
+        if let Some(media_status) = properties.media_status {
+            let value = match media_status {
+                MediaStatusMode::ApplicationControlled => "0",
+                MediaStatusMode::AlwaysConnected => "1",
+            };
+            key.set_value("MediaStatus", &value.to_owned())?;
+        }
+
+        if let Some(allow_non_admin) = properties.allow_non_admin {
+            let value = if allow_non_admin { "1" } else { "0" };
+            key.set_value("AllowNonAdmin", &value.to_owned())?;
+        }
+
+        // The driver only reads its properties when starting
+        drop(key);
+        self.restart()
+    }
+}
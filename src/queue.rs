@@ -0,0 +1,175 @@
+use winapi::shared::winerror::ERROR_IO_INCOMPLETE;
+use winapi::um::winnt::HANDLE;
+
+use std::{io, mem};
+
+use crate::ffi;
+use crate::overlapped::Overlapped;
+use crate::sync::Exclusive;
+
+/// Called once a frame written with `Device::write_with_callback`
+/// has been consumed by the driver, with the result of the write
+/// and the frame, to be reused
+pub type WriteCallback = dyn FnOnce(io::Result<()>, Vec<u8>) + Send;
+
+/// A write posted to the driver, along with a copy of the frame
+struct Slot {
+    overlapped: Overlapped,
+    buffer: Vec<u8>,
+    callback: Exclusive<Option<Box<WriteCallback>>>,
+}
+
+/// Fixed size queue of overlapped writes, once all the slots are
+/// pending a new write waits for the oldest one to complete
+pub(crate) struct WriteQueue {
+    slots: Vec<Slot>,
+    /// Oldest pending slot
+    head: usize,
+    /// Number of pending slots
+    pending: usize,
+}
+
+impl WriteQueue {
+    pub fn new(depth: usize) -> io::Result<Self> {
+        let mut slots = Vec::with_capacity(depth);
+
+        for _ in 0..depth {
+            slots.push(Slot {
+                overlapped: Overlapped::new()?,
+                buffer: Vec::new(),
+                callback: Exclusive::new(None),
+            });
+        }
+
+        Ok(Self {
+            slots,
+            head: 0,
+            pending: 0,
+        })
+    }
+
+    /// Copy the frame and post it, errors of previous writes
+    /// surface here if one had to be waited for
+    pub fn write(&mut self, handle: HANDLE, buf: &[u8]) -> io::Result<()> {
+        if self.pending == self.slots.len() {
+            self.complete_oldest(handle, true).unwrap_or(Ok(()))?;
+        }
+
+        let index = (self.head + self.pending) % self.slots.len();
+        let slot = &mut self.slots[index];
+
+        slot.buffer.clear();
+        slot.buffer.extend_from_slice(buf);
+
+        ffi::write_file_overlapped(handle, &slot.buffer, &mut slot.overlapped)?;
+        self.pending += 1;
+
+        Ok(())
+    }
+
+    /// Post the frame without copying it, its result goes to the
+    /// callback, even if posting fails. `posted` only sees the frame
+    /// once it was posted. Errors of previous writes without a
+    /// callback surface here if one had to be waited for
+    pub fn write_owned<P>(
+        &mut self,
+        handle: HANDLE,
+        buf: Vec<u8>,
+        callback: Box<WriteCallback>,
+        posted: P,
+    ) -> io::Result<()>
+    where
+        P: FnOnce(&[u8]),
+    {
+        // The oldest slot is freed even if its write failed
+        let res = if self.pending == self.slots.len() {
+            self.complete_oldest(handle, true).unwrap_or(Ok(()))
+        } else {
+            Ok(())
+        };
+
+        let index = (self.head + self.pending) % self.slots.len();
+        let slot = &mut self.slots[index];
+
+        slot.buffer = buf;
+
+        match ffi::write_file_overlapped(
+            handle,
+            &slot.buffer,
+            &mut slot.overlapped,
+        ) {
+            Ok(()) => {
+                posted(&slot.buffer);
+                *slot.callback.get_mut() = Some(callback);
+                self.pending += 1;
+            }
+            Err(err) => callback(Err(err), mem::take(&mut slot.buffer)),
+        }
+
+        res
+    }
+
+    /// Complete the writes the driver is done with, without waiting
+    /// for the others
+    pub fn poll(&mut self, handle: HANDLE) -> io::Result<()> {
+        let mut res = Ok(());
+
+        while self.pending > 0 {
+            match self.complete_oldest(handle, false) {
+                Some(Err(err)) => res = res.and(Err(err)),
+                Some(Ok(())) => {}
+                None => break,
+            }
+        }
+
+        res
+    }
+
+    /// Wait for every pending write, returns the first error
+    /// encountered while still waiting for all of them
+    pub fn flush(&mut self, handle: HANDLE) -> io::Result<()> {
+        let mut res = Ok(());
+
+        while self.pending > 0 {
+            if let Some(Err(err)) = self.complete_oldest(handle, true) {
+                res = res.and(Err(err));
+            }
+        }
+
+        res
+    }
+
+    /// Complete the oldest pending write, returns `None` if it is
+    /// still pending and `wait` is false
+    fn complete_oldest(
+        &mut self,
+        handle: HANDLE,
+        wait: bool,
+    ) -> Option<io::Result<()>> {
+        let slot = &mut self.slots[self.head];
+
+        let res =
+            ffi::get_overlapped_result(handle, &mut slot.overlapped, wait)
+                .map(|_| ());
+
+        if let Err(err) = &res {
+            if err.raw_os_error() == Some(ERROR_IO_INCOMPLETE as _) {
+                return None;
+            }
+        }
+
+        // Writes with a callback report to it instead
+        let res = match slot.callback.get_mut().take() {
+            Some(callback) => {
+                callback(res, mem::take(&mut slot.buffer));
+                Ok(())
+            }
+            None => res,
+        };
+
+        self.head = (self.head + 1) % self.slots.len();
+        self.pending -= 1;
+
+        Some(res)
+    }
+}
@@ -0,0 +1,92 @@
+use std::{thread, time};
+
+use crate::Device;
+
+/// Token bucket shaping the egress of a device, see
+/// `Device::set_rate_limit`
+pub(crate) struct TokenBucket {
+    /// Bytes per second
+    rate: f64,
+    /// Maximum amount of bytes sent back to back
+    burst: f64,
+    /// Bytes that can be sent right away, negative when a frame
+    /// larger than the burst was let through
+    tokens: f64,
+    last: time::Instant,
+}
+
+impl TokenBucket {
+    pub fn new(bytes_per_sec: u64, burst: u64) -> Self {
+        Self {
+            rate: bytes_per_sec as f64,
+            burst: burst as f64,
+            tokens: burst as f64,
+            last: time::Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = time::Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last = now;
+    }
+
+    /// Sleep until `len` bytes can be sent, and take them
+    pub fn acquire(&mut self, len: usize) {
+        self.refill();
+
+        // Frames larger than the burst wait for a full bucket
+        let needed = (len as f64).min(self.burst);
+
+        if self.tokens < needed {
+            let wait = (needed - self.tokens) / self.rate;
+            thread::sleep(time::Duration::from_secs_f64(wait));
+            self.refill();
+        }
+
+        self.tokens -= len as f64;
+    }
+}
+
+impl Device {
+    /// Limit the egress of the device to `bytes_per_sec`, letting
+    /// through bursts of up to `burst` bytes. Writes sleep until the
+    /// frame fits in the budget. A rate of 0 removes the limit.
+    /// Applies to `write` and `write_many`
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::io::Write;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// // 10 Mbit/s, 64 KiB bursts
+    /// dev.set_rate_limit(10_000_000 / 8, 64 * 1024);
+    ///
+    /// # let frame = [0u8; 60];
+    /// dev.write(&frame).expect("Failed to write frame");
+    /// ```
+    pub fn set_rate_limit(&mut self, bytes_per_sec: u64, burst: u64) {
+        self.rate_limit = match bytes_per_sec {
+            0 => None,
+            rate => Some(TokenBucket::new(rate, burst)),
+        };
+    }
+
+    /// Same as `set_rate_limit`, in builder form
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device")
+    ///     .with_rate_limit(1_000_000, 16 * 1024);
+    /// ```
+    pub fn with_rate_limit(mut self, bytes_per_sec: u64, burst: u64) -> Self {
+        self.set_rate_limit(bytes_per_sec, burst);
+        self
+    }
+}
@@ -0,0 +1,259 @@
+//! The SetupDi and interface id wrappers used by the crate, for
+//! driver tooling that enumerates drivers or calls the class
+//! installer. Requires the `raw-ffi` feature.
+//!
+//! This module is unstable: signatures follow the needs of the
+//! crate and can change in any release. Functions taking a device
+//! information set are `unsafe`, as windows trusts it to be valid
+
+use winapi::shared::guiddef::GUID;
+use winapi::shared::minwindef::{DWORD, HKEY};
+use winapi::um::setupapi::*;
+use winapi::um::winnt::WCHAR;
+use winapi::um::winreg::REGSAM;
+
+use std::io;
+
+use crate::ffi;
+
+pub use crate::ffi::{
+    alias_to_luid, class_name_from_guid, create_device_info_list,
+    get_class_devs, guid_to_luid, index_to_luid, luid_to_alias, luid_to_guid,
+    luid_to_index, string_from_guid, SP_DRVINFO_DETAIL_DATA_W2,
+};
+
+/// Destroy a device information set, it can't be used afterwards
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet
+pub unsafe fn destroy_device_info_list(devinfo: HDEVINFO) -> io::Result<()> {
+    ffi::destroy_device_info_list(devinfo)
+}
+
+/// Add a new device to a device information set
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet
+pub unsafe fn create_device_info(
+    devinfo: HDEVINFO,
+    device_name: &[WCHAR],
+    guid: &GUID,
+    device_description: &[WCHAR],
+    creation_flags: DWORD,
+) -> io::Result<SP_DEVINFO_DATA> {
+    ffi::create_device_info(
+        devinfo,
+        device_name,
+        guid,
+        device_description,
+        creation_flags,
+    )
+}
+
+/// Get the device at `member_index`, `None` past the last one
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet
+pub unsafe fn enum_device_info(
+    devinfo: HDEVINFO,
+    member_index: DWORD,
+) -> Option<io::Result<SP_DEVINFO_DATA>> {
+    ffi::enum_device_info(devinfo, member_index)
+}
+
+/// Select the device class installer calls apply to
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn set_selected_device(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+) -> io::Result<()> {
+    ffi::set_selected_device(devinfo, devinfo_data)
+}
+
+/// Read a `SPDRP_*` string property of a device
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn get_device_registry_property(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    property: DWORD,
+) -> io::Result<Vec<WCHAR>> {
+    ffi::get_device_registry_property(devinfo, devinfo_data, property)
+}
+
+/// Write a `SPDRP_*` string property of a device
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn set_device_registry_property(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    property: DWORD,
+    value: &[WCHAR],
+) -> io::Result<()> {
+    ffi::set_device_registry_property(devinfo, devinfo_data, property, value)
+}
+
+/// Get the device instance id, such as `ROOT\NET\0001`
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn get_device_instance_id(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+) -> io::Result<Vec<WCHAR>> {
+    ffi::get_device_instance_id(devinfo, devinfo_data)
+}
+
+/// Build the list of drivers that can be installed on a device
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn build_driver_info_list(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    driver_type: DWORD,
+) -> io::Result<()> {
+    ffi::build_driver_info_list(devinfo, devinfo_data, driver_type)
+}
+
+/// Destroy a list built with `build_driver_info_list`
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn destroy_driver_info_list(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    driver_type: DWORD,
+) -> io::Result<()> {
+    ffi::destroy_driver_info_list(devinfo, devinfo_data, driver_type)
+}
+
+/// Get the driver at `member_index`, `None` past the last one
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn enum_driver_info(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    driver_type: DWORD,
+    member_index: DWORD,
+) -> Option<io::Result<SP_DRVINFO_DATA_W>> {
+    ffi::enum_driver_info(devinfo, devinfo_data, driver_type, member_index)
+}
+
+/// Get the details of a driver, including its hardware id
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn get_driver_info_detail(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    drvinfo_data: &SP_DRVINFO_DATA_W,
+) -> io::Result<SP_DRVINFO_DETAIL_DATA_W2> {
+    ffi::get_driver_info_detail(devinfo, devinfo_data, drvinfo_data)
+}
+
+/// Select the driver to install on a device
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn set_selected_driver(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    drvinfo_data: &SP_DRVINFO_DATA_W,
+) -> io::Result<()> {
+    ffi::set_selected_driver(devinfo, devinfo_data, drvinfo_data)
+}
+
+/// Set the parameters of the next class installer call
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn set_class_install_params(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    params: &impl Copy,
+) -> io::Result<()> {
+    ffi::set_class_install_params(devinfo, devinfo_data, params)
+}
+
+/// Call the class installer, for example with `DIF_REMOVE`
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn call_class_installer(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    install_function: DI_FUNCTION,
+) -> io::Result<()> {
+    ffi::call_class_installer(devinfo, devinfo_data, install_function)
+}
+
+/// Open the hardware or driver registry key of a device
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn open_dev_reg_key(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    scope: DWORD,
+    hw_profile: DWORD,
+    key_type: DWORD,
+    sam_desired: REGSAM,
+) -> io::Result<HKEY> {
+    ffi::open_dev_reg_key(
+        devinfo,
+        devinfo_data,
+        scope,
+        hw_profile,
+        key_type,
+        sam_desired,
+    )
+}
+
+/// Create the hardware or driver registry key of a device
+///
+/// # Safety
+/// `devinfo` must come from `create_device_info_list` or
+/// `get_class_devs` and not be destroyed yet, `devinfo_data`
+/// must be one of its devices
+pub unsafe fn create_dev_reg_key(
+    devinfo: HDEVINFO,
+    devinfo_data: &SP_DEVINFO_DATA,
+    scope: DWORD,
+    hw_profile: DWORD,
+    key_type: DWORD,
+) -> io::Result<HKEY> {
+    ffi::create_dev_reg_key(devinfo, devinfo_data, scope, hw_profile, key_type)
+}
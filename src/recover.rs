@@ -0,0 +1,111 @@
+use winapi::shared::winerror::*;
+
+use std::io;
+use std::os::windows::io::{FromRawHandle, OwnedHandle};
+
+use crate::options::{DEFAULT_OPEN_TIMEOUT, DEFAULT_POLL_INTERVAL};
+use crate::{iface, Device};
+
+impl Device {
+    /// Check if an error returned by `read` or `write` means the
+    /// handle went stale, usually after the system resumed from
+    /// sleep or hibernation, and `reopen` is needed
+    pub fn is_handle_lost(err: &io::Error) -> bool {
+        match err.raw_os_error() {
+            Some(code) => {
+                code == ERROR_OPERATION_ABORTED as i32
+                    || code == ERROR_DEVICE_NOT_CONNECTED as i32
+                    || code == ERROR_INVALID_HANDLE as i32
+            }
+            None => false,
+        }
+    }
+
+    /// Close the handle and open the interface again, waiting for
+    /// it to come back. Unlike `restart` the adapter is left alone,
+    /// the configuration, counters and filters are kept.
+    /// Frames still queued for writing are lost. If the interface
+    /// doesn't come back the device fails every operation, with an
+    /// error `is_handle_lost` accepts, until `reopen` succeeds
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::io::Read;
+    ///
+    /// let mut dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let mut buf = [0; 1600];
+    /// loop {
+    ///     match dev.read(&mut buf) {
+    ///         Ok(amt) => println!("{:?}", &buf[..amt]),
+    ///         Err(err) if Device::is_handle_lost(&err) => {
+    ///             dev.reopen().expect("Failed to reopen device");
+    ///         }
+    ///         Err(err) => panic!("Failed to read packet: {}", err),
+    ///     }
+    /// }
+    /// ```
+    pub fn reopen(&mut self) -> io::Result<()> {
+        if let (Ok(handle), Some(queue)) =
+            (self.raw_handle(), &mut self.write_queue)
+        {
+            let _ = queue.flush(handle);
+        }
+
+        // The new handle keeps the same inheritability
+        let inheritable = self.is_inheritable().unwrap_or(false);
+
+        // Try next to the previous handle first, which is kept if
+        // this fails. The driver allows a single open handle though,
+        // so if the previous one still holds it it has to go first
+        let handle = match iface::open_interface(&self.luid, inheritable) {
+            Ok(handle) => handle,
+            Err(_) => {
+                self.handle = None;
+
+                iface::wait_open_interface(
+                    &self.luid,
+                    inheritable,
+                    DEFAULT_OPEN_TIMEOUT,
+                    DEFAULT_POLL_INTERVAL,
+                )?
+            }
+        };
+        self.handle =
+            Some(unsafe { OwnedHandle::from_raw_handle(handle as _) });
+
+        debug!(luid = self.luid.Value, "reopened interface");
+
+        Ok(())
+    }
+
+    /// Make `read` and `write` call `reopen` and try again once when
+    /// the handle went stale, instead of returning the error
+    pub fn set_auto_reopen(&mut self, auto_reopen: bool) {
+        self.auto_reopen = auto_reopen;
+    }
+
+    /// Same as `set_auto_reopen`, in builder form
+    pub fn with_auto_reopen(mut self, auto_reopen: bool) -> Self {
+        self.set_auto_reopen(auto_reopen);
+        self
+    }
+
+    /// Run a datapath operation, reopening the handle and running
+    /// it again if it failed on a stale handle and auto reopen is on
+    pub(crate) fn recover<T, F>(&mut self, mut op: F) -> io::Result<T>
+    where
+        F: FnMut(&mut Self) -> io::Result<T>,
+    {
+        match op(self) {
+            Err(err) if self.auto_reopen && Self::is_handle_lost(&err) => {
+                warn!(error = %err, "handle lost, reopening interface");
+
+                self.reopen()?;
+                op(self)
+            }
+            res => res,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Minimal registry wrappers, covering what the driver
+//! and network class keys need
+
+use winapi::shared::minwindef::{DWORD, HKEY};
+use winapi::um::winnt::{
+    REG_BINARY, REG_DWORD, REG_EXPAND_SZ, REG_MULTI_SZ, REG_QWORD, REG_SZ,
+};
+use winapi::um::winreg::{
+    HKEY_CLASSES_ROOT, HKEY_CURRENT_USER_LOCAL_SETTINGS, REGSAM,
+};
+
+use std::{io, iter, mem};
+
+use crate::{decode_utf16, encode_utf16, ffi};
+
+/// An open registry key, closed on drop unless predefined
+pub struct RegKey {
+    hkey: HKEY,
+}
+
+// Registry handles can be used from any thread
+unsafe impl Send for RegKey {}
+unsafe impl Sync for RegKey {}
+
+impl RegKey {
+    /// Take ownership of a key handle
+    pub fn predef(hkey: HKEY) -> Self {
+        Self { hkey }
+    }
+
+    pub fn raw_handle(&self) -> HKEY {
+        self.hkey
+    }
+
+    pub fn open_subkey_with_flags<P: AsRef<str>>(
+        &self,
+        path: P,
+        sam_desired: REGSAM,
+    ) -> io::Result<RegKey> {
+        let path = encode_utf16(path.as_ref());
+
+        ffi::reg_open_key_ex(self.hkey, &path, sam_desired).map(Self::predef)
+    }
+
+    /// Iterate over the names of the subkeys
+    pub fn enum_keys(&self) -> impl Iterator<Item = io::Result<String>> + '_ {
+        let mut index = 0;
+
+        iter::from_fn(move || {
+            let name = ffi::reg_enum_key_ex(self.hkey, index)?;
+            index += 1;
+
+            Some(name.map(|name| decode_utf16(&name)))
+        })
+    }
+
+    /// Read a value, missing values fail with `NotFound`
+    /// and values of the wrong type with `InvalidData`
+    pub fn get_value<T: RegValue, N: AsRef<str>>(
+        &self,
+        name: N,
+    ) -> io::Result<T> {
+        let name = encode_utf16(name.as_ref());
+        let (value_type, data) = ffi::reg_query_value_ex(self.hkey, &name)?;
+
+        T::from_reg(value_type, &data).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Registry value has an unexpected type",
+            )
+        })
+    }
+
+    pub fn set_value<T: RegValue, N: AsRef<str>>(
+        &self,
+        name: N,
+        value: &T,
+    ) -> io::Result<()> {
+        let name = encode_utf16(name.as_ref());
+        let (value_type, data) = value.to_reg();
+
+        ffi::reg_set_value_ex(self.hkey, &name, value_type, &data)
+    }
+}
+
+impl Drop for RegKey {
+    fn drop(&mut self) {
+        if self.hkey < HKEY_CLASSES_ROOT
+            || self.hkey > HKEY_CURRENT_USER_LOCAL_SETTINGS
+        {
+            let _ = ffi::reg_close_key(self.hkey);
+        }
+    }
+}
+
+/// Types that can be stored in registry values, see
+/// `Device::get_driver_key_value`
+pub trait RegValue: Sized {
+    /// Decode a value, `None` if its type doesn't match
+    fn from_reg(value_type: DWORD, data: &[u8]) -> Option<Self>;
+    /// Encode a value along with its type
+    fn to_reg(&self) -> (DWORD, Vec<u8>);
+}
+
+impl RegValue for String {
+    fn from_reg(value_type: DWORD, data: &[u8]) -> Option<Self> {
+        if value_type != REG_SZ && value_type != REG_EXPAND_SZ {
+            return None;
+        }
+
+        let data = data
+            .chunks_exact(mem::size_of::<u16>())
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect::<Vec<_>>();
+
+        Some(decode_utf16(&data))
+    }
+
+    fn to_reg(&self) -> (DWORD, Vec<u8>) {
+        let data = encode_utf16(self)
+            .iter()
+            .flat_map(|c| c.to_le_bytes().to_vec())
+            .collect();
+
+        (REG_SZ, data)
+    }
+}
+
+impl RegValue for u32 {
+    fn from_reg(value_type: DWORD, data: &[u8]) -> Option<Self> {
+        match (value_type, data) {
+            (REG_DWORD, &[a, b, c, d]) => {
+                Some(u32::from_le_bytes([a, b, c, d]))
+            }
+            _ => None,
+        }
+    }
+
+    fn to_reg(&self) -> (DWORD, Vec<u8>) {
+        (REG_DWORD, self.to_le_bytes().to_vec())
+    }
+}
+
+impl RegValue for u64 {
+    fn from_reg(value_type: DWORD, data: &[u8]) -> Option<Self> {
+        if value_type != REG_QWORD || data.len() != 8 {
+            return None;
+        }
+
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(data);
+
+        Some(u64::from_le_bytes(bytes))
+    }
+
+    fn to_reg(&self) -> (DWORD, Vec<u8>) {
+        (REG_QWORD, self.to_le_bytes().to_vec())
+    }
+}
+
+impl RegValue for Vec<String> {
+    fn from_reg(value_type: DWORD, data: &[u8]) -> Option<Self> {
+        if value_type != REG_MULTI_SZ {
+            return None;
+        }
+
+        let data = data
+            .chunks_exact(mem::size_of::<u16>())
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect::<Vec<_>>();
+
+        // Strings are nul terminated, the list ends with an empty one
+        Some(
+            data.split(|c| *c == 0)
+                .take_while(|string| !string.is_empty())
+                .map(decode_utf16)
+                .collect(),
+        )
+    }
+
+    fn to_reg(&self) -> (DWORD, Vec<u8>) {
+        let data = self
+            .iter()
+            .flat_map(|string| encode_utf16(string))
+            .chain(iter::once(0))
+            .flat_map(|c| c.to_le_bytes().to_vec())
+            .collect();
+
+        (REG_MULTI_SZ, data)
+    }
+}
+
+impl RegValue for Vec<u8> {
+    fn from_reg(value_type: DWORD, data: &[u8]) -> Option<Self> {
+        match value_type {
+            REG_BINARY => Some(data.to_vec()),
+            _ => None,
+        }
+    }
+
+    fn to_reg(&self) -> (DWORD, Vec<u8>) {
+        (REG_BINARY, self.clone())
+    }
+}
@@ -0,0 +1,138 @@
+use winapi::shared::minwindef::FALSE;
+use winapi::um::winbase::INFINITE;
+use winapi::um::winnt::{
+    KEY_NOTIFY, KEY_QUERY_VALUE, REG_NOTIFY_CHANGE_LAST_SET,
+};
+
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::sync::{Arc, Mutex};
+use std::{io, thread};
+
+use crate::registry::RegKey;
+use crate::{ffi, iface, Device};
+
+type RenameCallback = Box<dyn Fn(&str) + Send>;
+
+/// Watches the name of an interface, created with
+/// `Device::on_renamed`.
+/// The callback runs on a dedicated thread, dropping the watcher
+/// stops it and waits for a running callback to return
+pub struct RenameWatcher {
+    stop: Arc<OwnedHandle>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl RenameWatcher {
+    fn new(
+        key: RegKey,
+        alias: Arc<Mutex<Option<String>>>,
+        callback: RenameCallback,
+    ) -> io::Result<Self> {
+        let new_event = || {
+            ffi::create_event().map(|event| unsafe {
+                OwnedHandle::from_raw_handle(event as _)
+            })
+        };
+
+        let stop = Arc::new(new_event()?);
+        let changed = new_event()?;
+        let mut name: String = key.get_value("Name")?;
+
+        let thread_stop = stop.clone();
+        let thread = thread::Builder::new()
+            .name("tap-windows-rename".to_owned())
+            .spawn(move || {
+                let events = [
+                    changed.as_raw_handle() as _,
+                    thread_stop.as_raw_handle() as _,
+                ];
+
+                loop {
+                    // Registered again after every change, as the
+                    // notification only fires once
+                    let res = ffi::reset_event(events[0]).and_then(|_| {
+                        ffi::reg_notify_change_key_value(
+                            key.raw_handle(),
+                            FALSE,
+                            REG_NOTIFY_CHANGE_LAST_SET,
+                            events[0],
+                        )
+                    });
+
+                    if let Err(_err) = res {
+                        warn!(error = %_err, "failed to watch interface name");
+                        return;
+                    }
+
+                    match ffi::wait_for_multiple_objects(&events, INFINITE) {
+                        Ok(Some(0)) => {}
+                        _ => return,
+                    }
+
+                    let new_name: String = match key.get_value("Name") {
+                        Ok(new_name) => new_name,
+                        Err(_) => continue,
+                    };
+
+                    if new_name == name {
+                        continue;
+                    }
+
+                    debug!(old = %name, new = %new_name, "interface renamed");
+
+                    *alias.lock().unwrap_or_else(|err| err.into_inner()) =
+                        Some(new_name.clone());
+
+                    callback(&new_name);
+                    name = new_name;
+                }
+            })?;
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for RenameWatcher {
+    fn drop(&mut self) {
+        let _ = ffi::set_event(self.stop.as_raw_handle() as _);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Device {
+    /// Watch for renames of the interface, including the ones made
+    /// outside this process, for example from the network connections
+    /// window. The name cached by the device is refreshed before the
+    /// callback is called with the new name, so later calls don't act
+    /// on a stale name.
+    /// The callback runs on a dedicated thread until the returned
+    /// watcher is dropped
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let _watcher = dev
+    ///     .on_renamed(|name| println!("Interface renamed to {}", name))
+    ///     .expect("Failed to watch renames");
+    /// ```
+    pub fn on_renamed<F>(&self, callback: F) -> io::Result<RenameWatcher>
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        let key = iface::open_connection_key(
+            &self.luid,
+            KEY_NOTIFY | KEY_QUERY_VALUE,
+        )?;
+
+        RenameWatcher::new(key, self.alias.clone(), Box::new(callback))
+    }
+}
@@ -0,0 +1,507 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::{thread, time};
+
+/// How frames are timed when replaying a capture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pacing {
+    /// Write frames back to back
+    Unpaced,
+    /// Respect the gaps between the captured timestamps
+    Original,
+    /// Respect the captured gaps, scaled by a speed factor,
+    /// `Speed(2.0)` replays twice as fast as captured
+    Speed(f64),
+}
+
+/// Write the ethernet frames of a pcap or pcapng capture into `dev`,
+/// returns the number of frames written.
+/// Frames of non ethernet interfaces are skipped.
+/// Requires the `capture` feature
+/// Example
+/// ```no_run
+/// use tap_windows::{replay, Device, Pacing};
+///
+/// let mut dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// let frames = replay("regression.pcapng", &mut dev, Pacing::Original)
+///     .expect("Failed to replay capture");
+///
+/// println!("Replayed {} frames", frames);
+/// ```
+pub fn replay<P, W>(path: P, dev: &mut W, pacing: Pacing) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+
+    let mut pacer = Pacer::new(pacing);
+    let mut frames = 0;
+
+    let mut on_frame =
+        |timestamp: time::Duration, frame: &[u8]| -> io::Result<()> {
+            pacer.wait(timestamp);
+            // One write per frame, tap devices are packet oriented
+            if dev.write(frame)? != frame.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "Frame was only partially written",
+                ));
+            }
+            frames += 1;
+
+            Ok(())
+        };
+
+    match magic {
+        [0x0a, 0x0d, 0x0d, 0x0a] => read_pcapng(&mut reader, &mut on_frame)?,
+        _ => read_pcap(&mut reader, magic, &mut on_frame)?,
+    }
+
+    Ok(frames)
+}
+
+/// LINKTYPE_ETHERNET, the only link type a tap device accepts
+const LINKTYPE_ETHERNET: u32 = 1;
+
+/// Largest frame accepted from a capture, what tcpdump uses as
+/// its default snaplen. Bounds allocations on corrupted files
+const MAX_FRAME_LEN: usize = 256 * 1024;
+
+/// Largest pcapng block accepted, a frame plus plenty of options
+const MAX_BLOCK_LEN: usize = 1024 * 1024;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_owned())
+}
+
+/// Decode integers with the byte order of the capture
+#[derive(Clone, Copy)]
+struct Endian {
+    big: bool,
+}
+
+impl Endian {
+    fn u16(self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+
+        match self.big {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        }
+    }
+
+    fn u32(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+        match self.big {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        }
+    }
+}
+
+/// Read a classic pcap file, `magic` was already consumed
+fn read_pcap<R, F>(
+    reader: &mut R,
+    magic: [u8; 4],
+    on_frame: &mut F,
+) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(time::Duration, &[u8]) -> io::Result<()>,
+{
+    let (endian, nanos) = match u32::from_le_bytes(magic) {
+        0xa1b2c3d4 => (Endian { big: false }, false),
+        0xa1b23c4d => (Endian { big: false }, true),
+        0xd4c3b2a1 => (Endian { big: true }, false),
+        0x4d3cb2a1 => (Endian { big: true }, true),
+        _ => return Err(invalid_data("Not a pcap or pcapng file")),
+    };
+
+    let mut header = [0; 20];
+    reader.read_exact(&mut header)?;
+
+    if endian.u32(&header[16..]) != LINKTYPE_ETHERNET {
+        return Err(invalid_data("Capture does not contain ethernet frames"));
+    }
+
+    let snaplen = endian.u32(&header[12..]) as usize;
+
+    let mut record = [0; 16];
+    let mut frame = Vec::new();
+
+    loop {
+        match reader.read_exact(&mut record) {
+            Ok(_) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(())
+            }
+            Err(err) => return Err(err),
+        }
+
+        let secs = endian.u32(&record[0..]) as u64;
+        let frac = endian.u32(&record[4..]);
+        let len = endian.u32(&record[8..]) as usize;
+
+        let timestamp = match nanos {
+            true => time::Duration::new(secs, frac),
+            false => {
+                time::Duration::new(secs, 0)
+                    + time::Duration::from_micros(frac as u64)
+            }
+        };
+
+        if len > snaplen || len > MAX_FRAME_LEN {
+            return Err(invalid_data("Invalid pcap record length"));
+        }
+
+        frame.resize(len, 0);
+        reader.read_exact(&mut frame)?;
+
+        on_frame(timestamp, &frame)?;
+    }
+}
+
+/// Per interface state of a pcapng section
+struct PcapngInterface {
+    link_type: u32,
+    /// Timestamp units per second
+    resolution: u64,
+}
+
+/// Read a pcapng file, the block type of the first
+/// section header was already consumed
+fn read_pcapng<R, F>(reader: &mut R, on_frame: &mut F) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(time::Duration, &[u8]) -> io::Result<()>,
+{
+    const SECTION_HEADER_BLOCK: u32 = 0x0a0d0d0a;
+    const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x00000001;
+    const SIMPLE_PACKET_BLOCK: u32 = 0x00000003;
+    const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
+    const IF_TSRESOL: u16 = 9;
+
+    let mut endian = Endian { big: false };
+    let mut interfaces: Vec<PcapngInterface> = Vec::new();
+    let mut block_type = SECTION_HEADER_BLOCK;
+    let mut body = Vec::new();
+
+    loop {
+        let mut head = [0; 4];
+        reader.read_exact(&mut head)?;
+
+        // The byte order magic follows the length of section headers
+        if block_type == SECTION_HEADER_BLOCK {
+            let mut magic = [0; 4];
+            reader.read_exact(&mut magic)?;
+
+            endian = match u32::from_le_bytes(magic) {
+                0x1a2b3c4d => Endian { big: false },
+                0x4d3c2b1a => Endian { big: true },
+                _ => return Err(invalid_data("Invalid pcapng byte order")),
+            };
+
+            interfaces.clear();
+        }
+
+        let len = endian.u32(&head) as usize;
+        let read = match block_type {
+            SECTION_HEADER_BLOCK => 12,
+            _ => 8,
+        };
+
+        if len < read + 4 || len % 4 != 0 || len > MAX_BLOCK_LEN {
+            return Err(invalid_data("Invalid pcapng block length"));
+        }
+
+        // Body plus the trailing copy of the length
+        body.resize(len - read, 0);
+        reader.read_exact(&mut body)?;
+        let body = &body[..body.len() - 4];
+
+        match block_type {
+            INTERFACE_DESCRIPTION_BLOCK if body.len() >= 8 => {
+                let mut resolution = 1_000_000;
+
+                // Only if_tsresol matters, look for it in the options
+                let mut options = &body[8..];
+                while options.len() >= 4 {
+                    let code = endian.u16(&options[0..]);
+                    let len = endian.u16(&options[2..]) as usize;
+                    let padded = (len + 3) & !3;
+
+                    if options.len() < 4 + padded {
+                        break;
+                    }
+
+                    if code == IF_TSRESOL && len >= 1 {
+                        let value = options[4];
+                        let exp = (value & 0x7f) as u32;
+
+                        resolution = match value & 0x80 {
+                            0 => 10u64.checked_pow(exp),
+                            _ => 2u64.checked_pow(exp),
+                        }
+                        .unwrap_or(1_000_000);
+                    }
+
+                    options = &options[4 + padded..];
+                }
+
+                interfaces.push(PcapngInterface {
+                    link_type: endian.u16(&body[0..]) as u32,
+                    resolution,
+                });
+            }
+            ENHANCED_PACKET_BLOCK if body.len() >= 20 => {
+                let id = endian.u32(&body[0..]) as usize;
+                let high = endian.u32(&body[4..]) as u64;
+                let low = endian.u32(&body[8..]) as u64;
+                let caplen = endian.u32(&body[12..]) as usize;
+
+                let interface = interfaces
+                    .get(id)
+                    .ok_or_else(|| invalid_data("Unknown pcapng interface"))?;
+
+                let frame = body
+                    .get(20..20 + caplen)
+                    .ok_or_else(|| invalid_data("Invalid pcapng packet"))?;
+
+                if interface.link_type == LINKTYPE_ETHERNET {
+                    // Resolutions up to 2^63 overflow u64 multiplications
+                    let ticks = ((high << 32) | low) as u128;
+                    let resolution = interface.resolution as u128;
+                    let secs = ticks / resolution;
+                    let nanos =
+                        (ticks % resolution) * 1_000_000_000 / resolution;
+
+                    on_frame(
+                        time::Duration::new(secs as u64, nanos as u32),
+                        frame,
+                    )?;
+                }
+            }
+            SIMPLE_PACKET_BLOCK if body.len() >= 4 => {
+                // No timestamp, and only valid with a single interface
+                let link_type = interfaces.first().map(|i| i.link_type);
+
+                if link_type == Some(LINKTYPE_ETHERNET) {
+                    let len = endian.u32(&body[0..]) as usize;
+                    let frame = &body[4..];
+
+                    on_frame(
+                        time::Duration::default(),
+                        &frame[..len.min(frame.len())],
+                    )?;
+                }
+            }
+            _ => (),
+        }
+
+        let mut next = [0; 4];
+        match reader.read_exact(&mut next) {
+            Ok(_) => block_type = endian.u32(&next),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(())
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Longest wait from the first frame, in seconds. Tiny speeds would
+/// otherwise overflow the duration
+const MAX_OFFSET: f64 = u32::MAX as f64;
+
+/// Sleeps between frames to reproduce the captured timing
+struct Pacer {
+    speed: Option<f64>,
+    start: Option<(time::Instant, time::Duration)>,
+}
+
+impl Pacer {
+    fn new(pacing: Pacing) -> Self {
+        let speed = match pacing {
+            Pacing::Unpaced => None,
+            Pacing::Original => Some(1.0),
+            Pacing::Speed(speed) if speed > 0.0 => Some(speed),
+            Pacing::Speed(_) => None,
+        };
+
+        Self { speed, start: None }
+    }
+
+    fn wait(&mut self, timestamp: time::Duration) {
+        let speed = match self.speed {
+            Some(speed) => speed,
+            None => return,
+        };
+
+        let (start, first) = *self
+            .start
+            .get_or_insert_with(|| (time::Instant::now(), timestamp));
+
+        // Timestamps going back in time are replayed right away
+        let offset = match timestamp.checked_sub(first) {
+            Some(offset) => offset.as_secs_f64() / speed,
+            None => return,
+        };
+
+        let offset = time::Duration::from_secs_f64(offset.min(MAX_OFFSET));
+        let now = time::Instant::now();
+
+        match start.checked_add(offset) {
+            Some(target) if target > now => thread::sleep(target - now),
+            Some(_) => {}
+            // Past what an Instant can hold, wait out the rest anyway
+            None => thread::sleep(offset.saturating_sub(now - start)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Frames = Vec<(time::Duration, Vec<u8>)>;
+
+    fn parse(capture: &[u8]) -> io::Result<Frames> {
+        let mut frames = Vec::new();
+        let mut on_frame = |timestamp: time::Duration, frame: &[u8]| {
+            frames.push((timestamp, frame.to_vec()));
+            Ok(())
+        };
+
+        let (magic, mut rest) = capture.split_at(4);
+        match magic {
+            [0x0a, 0x0d, 0x0d, 0x0a] => read_pcapng(&mut rest, &mut on_frame)?,
+            _ => read_pcap(
+                &mut rest,
+                [magic[0], magic[1], magic[2], magic[3]],
+                &mut on_frame,
+            )?,
+        }
+
+        Ok(frames)
+    }
+
+    fn pcap(snaplen: u32, records: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        file.extend_from_slice(&[2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        file.extend_from_slice(&snaplen.to_le_bytes());
+        file.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+
+        for &(secs, micros, frame) in records {
+            file.extend_from_slice(&secs.to_le_bytes());
+            file.extend_from_slice(&micros.to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(frame);
+        }
+
+        file
+    }
+
+    fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let mut body = body.to_vec();
+        body.resize((body.len() + 3) & !3, 0);
+        let len = (body.len() as u32 + 12).to_le_bytes();
+
+        let mut block = block_type.to_le_bytes().to_vec();
+        block.extend_from_slice(&len);
+        block.extend_from_slice(&body);
+        block.extend_from_slice(&len);
+        block
+    }
+
+    fn section_header() -> Vec<u8> {
+        let mut body = 0x1a2b3c4du32.to_le_bytes().to_vec();
+        body.extend_from_slice(&[1, 0, 0, 0]);
+        body.extend_from_slice(&u64::MAX.to_le_bytes());
+        block(0x0a0d0d0a, &body)
+    }
+
+    fn interface(tsresol: Option<u8>) -> Vec<u8> {
+        let mut body = vec![1, 0, 0, 0, 0, 0, 4, 0];
+        if let Some(tsresol) = tsresol {
+            body.extend_from_slice(&[9, 0, 1, 0, tsresol, 0, 0, 0]);
+        }
+        body.extend_from_slice(&[0, 0, 0, 0]);
+        block(1, &body)
+    }
+
+    fn packet(ticks: u64, frame: &[u8]) -> Vec<u8> {
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ticks as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        body.extend_from_slice(frame);
+        block(6, &body)
+    }
+
+    #[test]
+    fn reads_pcap() {
+        let file = pcap(1514, &[(1, 500, &[1, 2, 3]), (2, 0, &[4; 60])]);
+
+        assert_eq!(
+            parse(&file).unwrap(),
+            vec![
+                (time::Duration::new(1, 500_000), vec![1, 2, 3]),
+                (time::Duration::new(2, 0), vec![4; 60]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_pcap_records_over_snaplen() {
+        let file = pcap(2, &[(0, 0, &[1, 2, 3])]);
+        let err = parse(&file).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reads_pcapng() {
+        let mut file = section_header();
+        file.extend(interface(None));
+        file.extend(packet(1_500_000, &[1, 2, 3, 4, 5]));
+
+        assert_eq!(
+            parse(&file).unwrap(),
+            vec![(time::Duration::new(1, 500_000_000), vec![1, 2, 3, 4, 5])]
+        );
+    }
+
+    #[test]
+    fn reads_pcapng_high_resolutions() {
+        // 2^63 ticks per second, u64 math would overflow
+        let mut file = section_header();
+        file.extend(interface(Some(0x80 | 63)));
+        file.extend(packet(u64::MAX, &[1]));
+
+        assert_eq!(
+            parse(&file).unwrap(),
+            vec![(time::Duration::new(1, 999_999_999), vec![1])]
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_pcapng_blocks() {
+        let mut file = section_header();
+        file.extend_from_slice(&1u32.to_le_bytes());
+        file.extend_from_slice(&(MAX_BLOCK_LEN as u32 + 4).to_le_bytes());
+
+        let err = parse(&file).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,61 @@
+use winapi::shared::winerror::{ERROR_GEN_FAILURE, ERROR_SHARING_VIOLATION};
+
+use std::{io, thread, time};
+
+/// Retry policy used by `Device::open_with_retry`, the delay between
+/// attempts starts at `initial_delay` and doubles up to `max_delay`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one
+    pub attempts: u32,
+    /// Delay after the first failed attempt
+    pub initial_delay: time::Duration,
+    /// Upper bound of the delay between attempts
+    pub max_delay: time::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            initial_delay: time::Duration::from_millis(100),
+            max_delay: time::Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run `f` until it succeeds, fails with a non transient error
+    /// or runs out of attempts
+    pub(crate) fn run<T, F>(&self, mut f: F) -> io::Result<T>
+    where
+        F: FnMut() -> io::Result<T>,
+    {
+        let mut delay = self.initial_delay;
+        let mut attempt = 1;
+
+        loop {
+            match f() {
+                Err(err) if attempt < self.attempts && is_transient(&err) => {
+                    thread::sleep(delay);
+
+                    delay = delay.saturating_mul(2).min(self.max_delay);
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Errors returned by CreateFile while the previous owner of the
+/// adapter is still releasing it
+fn is_transient(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(code) => {
+            code == ERROR_GEN_FAILURE as i32
+                || code == ERROR_SHARING_VIOLATION as i32
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,156 @@
+use winapi::um::winnt::HANDLE;
+
+use std::io;
+
+use crate::overlapped::Overlapped;
+use crate::{ffi, Device};
+
+/// A read posted to the driver, along with its buffer
+struct Slot {
+    overlapped: Overlapped,
+    buffer: Box<[u8]>,
+    pending: bool,
+}
+
+/// Keeps several overlapped reads pending on a device, so the driver
+/// always has a buffer to complete instead of waiting for the next
+/// call to `read`, created with `Device::read_ring`.
+/// Frames are returned in the order the reads complete, which is the
+/// order the driver receives them
+/// Example
+/// ```no_run
+/// use tap_windows::Device;
+///
+/// let mut dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// let mut ring = dev.read_ring(8, 2048)
+///     .expect("Failed to create read ring");
+///
+/// loop {
+///     let frame = ring.next_frame().expect("Failed to read frame");
+///     println!("{:?}", frame);
+/// }
+/// ```
+pub struct ReadRing<'a> {
+    dev: &'a mut Device,
+    slots: Vec<Slot>,
+    /// Next slot to complete
+    head: usize,
+    /// Slot returned by the last `next_frame`, posted again
+    /// once the frame is not borrowed anymore
+    returned: Option<usize>,
+}
+
+impl<'a> ReadRing<'a> {
+    pub(crate) fn new(
+        dev: &'a mut Device,
+        depth: usize,
+        frame_size: usize,
+    ) -> io::Result<Self> {
+        let mut slots = Vec::with_capacity(depth.max(1));
+
+        for _ in 0..depth.max(1) {
+            slots.push(Slot {
+                overlapped: Overlapped::new()?,
+                buffer: vec![0; frame_size].into_boxed_slice(),
+                pending: false,
+            });
+        }
+
+        let mut ring = Self {
+            dev,
+            slots,
+            head: 0,
+            returned: None,
+        };
+
+        for index in 0..ring.slots.len() {
+            ring.post(index)?;
+        }
+
+        Ok(ring)
+    }
+
+    fn post(&mut self, index: usize) -> io::Result<()> {
+        let handle = self.dev.raw_handle()?;
+        let slot = &mut self.slots[index];
+
+        ffi::read_file_overlapped(
+            handle,
+            &mut slot.buffer,
+            &mut slot.overlapped,
+        )?;
+        slot.pending = true;
+
+        Ok(())
+    }
+
+    /// Wait for the next frame, the returned slice is valid
+    /// until the next call. Frames go through the read filter and
+    /// mss clamping, as with `Device::read`
+    pub fn next_frame(&mut self) -> io::Result<&[u8]> {
+        let (index, len) = loop {
+            if let Some(index) = self.returned.take() {
+                self.post(index)?;
+            }
+
+            let handle = self.dev.raw_handle()?;
+            let index = self.head;
+            let slot = &mut self.slots[index];
+
+            let res =
+                ffi::get_overlapped_result(handle, &mut slot.overlapped, true);
+            slot.pending = false;
+
+            // Failed and dropped reads are posted again as well
+            self.head = (index + 1) % self.slots.len();
+            self.returned = Some(index);
+
+            let len = res? as usize;
+            let buffer = &mut self.slots[index].buffer;
+
+            if let Some(len) = self.dev.process_read(buffer, len) {
+                break (index, len);
+            }
+        };
+
+        self.dev.counters.add_rx(len);
+
+        let frame = &self.slots[index].buffer[..len];
+
+        #[cfg(feature = "capture")]
+        if let Some(capture) = &mut self.dev.capture {
+            capture.frame(crate::Direction::Rx, frame);
+        }
+
+        Ok(frame)
+    }
+}
+
+impl io::Read for ReadRing<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let frame = self.next_frame()?;
+
+        let len = frame.len().min(buf.len());
+        buf[..len].copy_from_slice(&frame[..len]);
+
+        Ok(len)
+    }
+}
+
+impl Drop for ReadRing<'_> {
+    fn drop(&mut self) {
+        let handle: HANDLE = match self.dev.raw_handle() {
+            Ok(handle) => handle,
+            Err(_) => return,
+        };
+
+        // The buffers can only be freed once the driver let go of them
+        for slot in self.slots.iter_mut().filter(|slot| slot.pending) {
+            let _ = ffi::cancel_io_ex(handle, &mut slot.overlapped);
+            let _ =
+                ffi::get_overlapped_result(handle, &mut slot.overlapped, true);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+//! Translation of a `DeviceConfig` into netsh commands
+use std::fmt::Write;
+use std::{io, net};
+
+use crate::{validate_name, AddressConfig, Device, DeviceConfig, RouteConfig};
+
+impl DeviceConfig {
+    /// Render the fields that are set as netsh commands, one per line,
+    /// for the interface currently named `interface`. The result can
+    /// be reviewed and then run as a batch file.
+    /// Netsh can only add entries, so addresses and routes not in the
+    /// lists are not removed. Ipv4 addresses and dns servers are the
+    /// exception, being replaced by the first entry of their family.
+    /// The media status has no netsh equivalent and is left out.
+    /// Fails if `interface` or the new name isn't a valid interface
+    /// name, see `validate_name`
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{AddressConfig, DeviceConfig};
+    ///
+    /// let config = DeviceConfig {
+    ///     addresses: Some(vec![AddressConfig {
+    ///         address: [10, 8, 0, 2].into(),
+    ///         prefix: 24,
+    ///     }]),
+    ///     dns: Some(vec![[10, 8, 0, 1].into()]),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let script = config.to_netsh_script("My Own Device")
+    ///     .expect("Invalid interface name");
+    ///
+    /// print!("{}", script);
+    /// ```
+    pub fn to_netsh_script(&self, interface: &str) -> io::Result<String> {
+        validate_name(interface)?;
+
+        let mut script = String::new();
+        let mut interface = escape(interface);
+
+        if let Some(name) = &self.name {
+            validate_name(name)?;
+            let name = escape(name);
+
+            if name != interface {
+                let _ = writeln!(
+                    script,
+                    "netsh interface set interface name=\"{}\" \
+                     newname=\"{}\"",
+                    interface, name
+                );
+                interface = name;
+            }
+        }
+        let interface = interface.as_str();
+
+        if let Some(mtu) = self.mtu {
+            for family in &["ipv4", "ipv6"] {
+                let _ = writeln!(
+                    script,
+                    "netsh interface {} set subinterface \"{}\" mtu={} \
+                     store=persistent",
+                    family, interface, mtu
+                );
+            }
+        }
+
+        if let Some(addresses) = &self.addresses {
+            write_addresses(&mut script, interface, addresses);
+        }
+
+        if let Some(routes) = &self.routes {
+            for route in routes {
+                write_route(&mut script, interface, route);
+            }
+        }
+
+        if let Some(servers) = &self.dns {
+            let (v4, v6): (Vec<_>, Vec<_>) =
+                servers.iter().partition(|server| server.is_ipv4());
+
+            write_dns(&mut script, interface, "ipv4", &v4);
+            write_dns(&mut script, interface, "ipv6", &v6);
+        }
+
+        Ok(script)
+    }
+}
+
+/// Escape a name for a batch file. Valid names can't contain
+/// quotes, but `%` would still start a variable expansion
+fn escape(name: &str) -> String {
+    name.replace('%', "%%")
+}
+
+fn family(address: &net::IpAddr) -> &'static str {
+    match address {
+        net::IpAddr::V4(_) => "ipv4",
+        net::IpAddr::V6(_) => "ipv6",
+    }
+}
+
+fn write_addresses(
+    script: &mut String,
+    interface: &str,
+    addresses: &[AddressConfig],
+) {
+    let mut first_v4 = true;
+
+    for address in addresses {
+        match address.address {
+            net::IpAddr::V4(ip) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(address.prefix.min(32)))
+                    .unwrap_or(0);
+                let verb = if first_v4 { "set" } else { "add" };
+                let source = if first_v4 { " source=static" } else { "" };
+                first_v4 = false;
+
+                let _ = writeln!(
+                    script,
+                    "netsh interface ipv4 {} address name=\"{}\"{} \
+                     address={} mask={}",
+                    verb,
+                    interface,
+                    source,
+                    ip,
+                    net::Ipv4Addr::from(mask)
+                );
+            }
+            net::IpAddr::V6(ip) => {
+                let _ = writeln!(
+                    script,
+                    "netsh interface ipv6 add address interface=\"{}\" \
+                     address={}/{}",
+                    interface, ip, address.prefix
+                );
+            }
+        }
+    }
+}
+
+fn write_route(script: &mut String, interface: &str, route: &RouteConfig) {
+    let _ = write!(
+        script,
+        "netsh interface {} add route prefix={}/{} interface=\"{}\"",
+        family(&route.destination),
+        route.destination,
+        route.prefix,
+        interface
+    );
+
+    if let Some(gateway) = route.gateway {
+        let _ = write!(script, " nexthop={}", gateway);
+    }
+
+    if let Some(metric) = route.metric {
+        let _ = write!(script, " metric={}", metric);
+    }
+
+    script.push('\n');
+}
+
+fn write_dns(
+    script: &mut String,
+    interface: &str,
+    family: &str,
+    servers: &[&net::IpAddr],
+) {
+    match servers.split_first() {
+        None => {
+            let _ = writeln!(
+                script,
+                "netsh interface {} set dnsservers name=\"{}\" \
+                 source=static address=none validate=no",
+                family, interface
+            );
+        }
+        Some((first, rest)) => {
+            let _ = writeln!(
+                script,
+                "netsh interface {} set dnsservers name=\"{}\" \
+                 source=static address={} register=none validate=no",
+                family, interface, first
+            );
+
+            for (i, server) in rest.iter().enumerate() {
+                let _ = writeln!(
+                    script,
+                    "netsh interface {} add dnsservers name=\"{}\" \
+                     address={} index={} validate=no",
+                    family,
+                    interface,
+                    server,
+                    i + 2
+                );
+            }
+        }
+    }
+}
+
+impl Device {
+    /// Render the current configuration of the device as netsh
+    /// commands, see `DeviceConfig::to_netsh_script`. To get the
+    /// commands reaching a desired state instead, render the
+    /// `DeviceConfig::diff` against `current_config`
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let script = dev.to_netsh_script()
+    ///     .expect("Failed to generate script");
+    ///
+    /// std::fs::write("restore.cmd", script)
+    ///     .expect("Failed to write script");
+    /// ```
+    pub fn to_netsh_script(&self) -> io::Result<String> {
+        let config = self.current_config()?;
+        let name = self.get_name()?;
+
+        config.to_netsh_script(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_rename_mtu_and_addresses() {
+        let config = DeviceConfig {
+            name: Some("Vpn".to_owned()),
+            mtu: Some(1400),
+            addresses: Some(vec![
+                AddressConfig {
+                    address: [10, 8, 0, 2].into(),
+                    prefix: 24,
+                },
+                AddressConfig {
+                    address: [10, 9, 0, 2].into(),
+                    prefix: 16,
+                },
+                AddressConfig {
+                    address: "fd00::2".parse().unwrap(),
+                    prefix: 64,
+                },
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.to_netsh_script("Tap").unwrap(),
+            "netsh interface set interface name=\"Tap\" newname=\"Vpn\"\n\
+             netsh interface ipv4 set subinterface \"Vpn\" mtu=1400 \
+             store=persistent\n\
+             netsh interface ipv6 set subinterface \"Vpn\" mtu=1400 \
+             store=persistent\n\
+             netsh interface ipv4 set address name=\"Vpn\" source=static \
+             address=10.8.0.2 mask=255.255.255.0\n\
+             netsh interface ipv4 add address name=\"Vpn\" \
+             address=10.9.0.2 mask=255.255.0.0\n\
+             netsh interface ipv6 add address interface=\"Vpn\" \
+             address=fd00::2/64\n"
+        );
+    }
+
+    #[test]
+    fn renders_routes_and_dns() {
+        let config = DeviceConfig {
+            routes: Some(vec![
+                RouteConfig {
+                    destination: [0, 0, 0, 0].into(),
+                    prefix: 1,
+                    gateway: Some([10, 8, 0, 1].into()),
+                    metric: Some(5),
+                },
+                RouteConfig {
+                    destination: "2000::".parse().unwrap(),
+                    prefix: 3,
+                    gateway: None,
+                    metric: None,
+                },
+            ]),
+            dns: Some(vec![[10, 8, 0, 1].into(), [1, 1, 1, 1].into()]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.to_netsh_script("Tap").unwrap(),
+            "netsh interface ipv4 add route prefix=0.0.0.0/1 \
+             interface=\"Tap\" nexthop=10.8.0.1 metric=5\n\
+             netsh interface ipv6 add route prefix=2000::/3 \
+             interface=\"Tap\"\n\
+             netsh interface ipv4 set dnsservers name=\"Tap\" \
+             source=static address=10.8.0.1 register=none validate=no\n\
+             netsh interface ipv4 add dnsservers name=\"Tap\" \
+             address=1.1.1.1 index=2 validate=no\n\
+             netsh interface ipv6 set dnsservers name=\"Tap\" \
+             source=static address=none validate=no\n"
+        );
+    }
+
+    #[test]
+    fn escapes_percent() {
+        let config = DeviceConfig {
+            mtu: Some(1500),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.to_netsh_script("100% Tap").unwrap(),
+            "netsh interface ipv4 set subinterface \"100%% Tap\" mtu=1500 \
+             store=persistent\n\
+             netsh interface ipv6 set subinterface \"100%% Tap\" mtu=1500 \
+             store=persistent\n"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let config = DeviceConfig {
+            name: Some("Evil\" & calc".to_owned()),
+            ..Default::default()
+        };
+
+        assert!(config.to_netsh_script("Tap").is_err());
+        assert!(DeviceConfig::default()
+            .to_netsh_script("Tap\"\r\ndel x")
+            .is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use winapi::shared::ifdef::MediaConnectStateConnected;
+
+use std::{io, net};
+
+use crate::{dns, ffi, CreateOptions, Device};
+
+/// A change made by a `DeviceSetup`, along with what is needed to
+/// undo it
+enum Step {
+    Name(String),
+    Address(net::IpAddr),
+    Dns(Vec<net::IpAddr>),
+    Status(bool),
+}
+
+/// Configures a device step by step, recording every change so all
+/// of them are undone if the setup is not committed, usually because
+/// a step failed and the setup was dropped while returning the error.
+/// A device created by the setup is deleted instead
+/// Example
+/// ```no_run
+/// use tap_windows::{CreateOptions, Device, DeviceSetup};
+/// use std::io;
+///
+/// fn setup() -> io::Result<Device> {
+///     let mut setup = DeviceSetup::create_with(CreateOptions::default())?;
+///
+///     setup.set_name("My Interface")?;
+///     setup.add_address([10, 8, 0, 2], 24)?;
+///     setup.set_dns(&["10.8.0.1".parse().unwrap()])?;
+///     setup.up()?;
+///
+///     Ok(setup.commit())
+/// }
+///
+/// let dev = setup().expect("Failed to set up device");
+/// ```
+pub struct DeviceSetup {
+    dev: Option<Device>,
+    created: bool,
+    steps: Vec<Step>,
+}
+
+impl DeviceSetup {
+    /// Creates a new device to set up, it is deleted on rollback,
+    /// see `Device::create_with`
+    pub fn create_with(options: CreateOptions) -> io::Result<Self> {
+        Ok(Self {
+            dev: Some(Device::create_with(options)?),
+            created: true,
+            steps: Vec::new(),
+        })
+    }
+
+    /// Sets up an existing device, on rollback only the changes made
+    /// through the setup are undone
+    pub fn open(name: &str) -> io::Result<Self> {
+        Ok(Self::from(Device::open(name)?))
+    }
+
+    /// Set the name of the interface, see `Device::set_name`
+    pub fn set_name(&mut self, name: &str) -> io::Result<()> {
+        let dev = self.device();
+        let old = dev.get_name()?;
+
+        dev.set_name(name)?;
+        self.steps.push(Step::Name(old));
+
+        Ok(())
+    }
+
+    /// Add an address to the interface, see `Device::add_address`
+    pub fn add_address<A>(&mut self, address: A, prefix: u8) -> io::Result<()>
+    where
+        A: Into<net::IpAddr>,
+    {
+        let address = address.into();
+
+        self.device().add_address(address, prefix)?;
+        self.steps.push(Step::Address(address));
+
+        Ok(())
+    }
+
+    /// Set the dns servers of the interface, see `Device::set_dns`
+    pub fn set_dns(&mut self, servers: &[net::IpAddr]) -> io::Result<()> {
+        let dev = self.device();
+        let old = dns::get_interface_dns(&dev.luid)?;
+
+        dev.set_dns(servers)?;
+        self.steps.push(Step::Dns(old));
+
+        Ok(())
+    }
+
+    /// Set the status of the interface to connected, see `Device::up`
+    pub fn up(&mut self) -> io::Result<()> {
+        let dev = self.device();
+        let old = ffi::get_if_entry2(&dev.luid)?.MediaConnectState
+            == MediaConnectStateConnected;
+
+        dev.up()?;
+        self.steps.push(Step::Status(old));
+
+        Ok(())
+    }
+
+    /// Keep every change and give back the device
+    pub fn commit(mut self) -> Device {
+        self.steps.clear();
+        self.dev.take().unwrap()
+    }
+
+    /// Undo every change in reverse order, deleting the device if
+    /// the setup created it. Every step is undone even if some fail,
+    /// the first error is returned
+    pub fn rollback(mut self) -> io::Result<()> {
+        self.undo()
+    }
+
+    fn device(&self) -> &Device {
+        // Only taken by `commit` and `undo`, which consume the setup
+        self.dev.as_ref().unwrap()
+    }
+
+    fn undo(&mut self) -> io::Result<()> {
+        let dev = match self.dev.take() {
+            Some(dev) => dev,
+            None => return Ok(()),
+        };
+
+        if self.created {
+            debug!(luid = dev.luid.Value, "rolling back created interface");
+            return dev.delete();
+        }
+
+        let mut res = Ok(());
+        while let Some(step) = self.steps.pop() {
+            let step_res = match step {
+                Step::Name(name) => dev.set_name(&name),
+                Step::Address(address) => remove_address(&dev, &address),
+                Step::Dns(servers) => dev.set_dns(&servers),
+                Step::Status(status) => dev.set_status(status),
+            };
+
+            if let Err(err) = step_res {
+                warn!(error = %err, "failed to undo setup step");
+                res = res.and(Err(err));
+            }
+        }
+
+        res
+    }
+}
+
+impl From<Device> for DeviceSetup {
+    fn from(dev: Device) -> Self {
+        Self {
+            dev: Some(dev),
+            created: false,
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl Drop for DeviceSetup {
+    fn drop(&mut self) {
+        let _ = self.undo();
+    }
+}
+
+fn remove_address(dev: &Device, address: &net::IpAddr) -> io::Result<()> {
+    let mut row = ffi::initialize_unicast_ip_address_entry();
+    row.Address = ffi::sockaddr_inet_from_ip(address);
+    row.InterfaceLuid = dev.luid;
+
+    ffi::delete_unicast_ip_address_entry(&row)
+}
@@ -0,0 +1,81 @@
+use std::io;
+
+use crate::{ffi, Device, DeviceConfig};
+
+/// Options used when tearing a device down with
+/// `Device::shutdown_with`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownOptions {
+    /// Remove the manually configured addresses of the interface
+    pub remove_addresses: bool,
+    /// Remove the manually configured routes of the interface
+    pub remove_routes: bool,
+}
+
+impl Device {
+    /// Tear the device down in order: wait for the queued writes,
+    /// set the media status to disconnected, cancel the pending
+    /// operations and close the handle. The interface is deleted
+    /// afterwards if `set_delete_on_drop` was set.
+    /// Every step is attempted, the first error is returned
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.shutdown().expect("Failed to shut device down");
+    /// ```
+    pub fn shutdown(self) -> io::Result<()> {
+        self.shutdown_with(ShutdownOptions::default())
+    }
+
+    /// Same as `shutdown`, optionally removing the addresses and
+    /// routes configured on the interface, so no half configured
+    /// interface is left behind
+    /// Example
+    /// ```no_run
+    /// use tap_windows::{Device, ShutdownOptions};
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// dev.shutdown_with(ShutdownOptions {
+    ///     remove_addresses: true,
+    ///     remove_routes: true,
+    /// })
+    /// .expect("Failed to shut device down");
+    /// ```
+    pub fn shutdown_with(mut self, options: ShutdownOptions) -> io::Result<()> {
+        debug!(luid = self.luid.Value, "shutting interface down");
+
+        let handle = self.raw_handle()?;
+        let mut res = Ok(());
+
+        if let Some(queue) = &mut self.write_queue {
+            res = res.and(queue.flush(handle));
+        }
+
+        res = res.and(self.set_status(false));
+        res = res.and(ffi::cancel_all_io(handle));
+
+        let config = DeviceConfig {
+            routes: match options.remove_routes {
+                true => Some(Vec::new()),
+                false => None,
+            },
+            addresses: match options.remove_addresses {
+                true => Some(Vec::new()),
+                false => None,
+            },
+            ..Default::default()
+        };
+        res = res.and(self.apply(&config));
+
+        // The rest, including deleting the interface, is up to drop
+        self.handle = None;
+
+        res
+    }
+}
@@ -0,0 +1,223 @@
+use winapi::shared::ifdef::NET_LUID;
+use winapi::shared::netioapi::MIB_IF_ROW2;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::{io, thread, time};
+
+use crate::ffi;
+
+/// Traffic counters of an interface as reported by Windows,
+/// returned by `Device::get_stats`.
+/// They include all the traffic passing through the interface
+/// since it was started, not only the one of this process
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InterfaceStats {
+    /// Bytes received by the interface
+    pub in_octets: u64,
+    /// Bytes sent by the interface
+    pub out_octets: u64,
+    /// Packets received by the interface, unicast and not
+    pub in_packets: u64,
+    /// Packets sent by the interface, unicast and not
+    pub out_packets: u64,
+    /// Received packets dropped because of errors
+    pub in_errors: u64,
+    /// Outgoing packets dropped because of errors
+    pub out_errors: u64,
+    /// Received packets dropped without errors, for example
+    /// to free buffer space
+    pub in_discards: u64,
+    /// Outgoing packets dropped without errors
+    pub out_discards: u64,
+}
+
+impl InterfaceStats {
+    pub(crate) fn from_row(row: &MIB_IF_ROW2) -> Self {
+        Self {
+            in_octets: row.InOctets,
+            out_octets: row.OutOctets,
+            in_packets: row.InUcastPkts + row.InNUcastPkts,
+            out_packets: row.OutUcastPkts + row.OutNUcastPkts,
+            in_errors: row.InErrors,
+            out_errors: row.OutErrors,
+            in_discards: row.InDiscards,
+            out_discards: row.OutDiscards,
+        }
+    }
+}
+
+/// Frames and bytes that went through `read` and `write` of a
+/// `Device`, returned by `Device::stats`.
+/// Unlike `InterfaceStats` only the traffic of this handle is counted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TrafficStats {
+    /// Frames read from the device
+    pub rx_frames: u64,
+    /// Bytes read from the device
+    pub rx_bytes: u64,
+    /// Frames written to the device
+    pub tx_frames: u64,
+    /// Bytes written to the device
+    pub tx_bytes: u64,
+}
+
+/// Counters backing `TrafficStats`
+#[derive(Default)]
+pub(crate) struct Counters {
+    rx_frames: AtomicU64,
+    rx_bytes: AtomicU64,
+    tx_frames: AtomicU64,
+    tx_bytes: AtomicU64,
+}
+
+impl Counters {
+    pub fn add_rx(&self, bytes: usize) {
+        self.rx_frames.fetch_add(1, Ordering::Relaxed);
+        self.rx_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_tx(&self, bytes: usize) {
+        self.tx_frames.fetch_add(1, Ordering::Relaxed);
+        self.tx_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> TrafficStats {
+        TrafficStats {
+            rx_frames: self.rx_frames.load(Ordering::Relaxed),
+            rx_bytes: self.rx_bytes.load(Ordering::Relaxed),
+            tx_frames: self.tx_frames.load(Ordering::Relaxed),
+            tx_bytes: self.tx_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.rx_frames.store(0, Ordering::Relaxed);
+        self.rx_bytes.store(0, Ordering::Relaxed);
+        self.tx_frames.store(0, Ordering::Relaxed);
+        self.tx_bytes.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Traffic rates of an interface over the last interval, passed to
+/// the callback of `Device::watch_stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StatsRate {
+    /// Counters at the end of the interval
+    pub stats: InterfaceStats,
+    /// Actual length of the interval
+    pub elapsed: time::Duration,
+    /// Bits received per second
+    pub rx_bps: f64,
+    /// Bits sent per second
+    pub tx_bps: f64,
+    /// Packets received per second
+    pub rx_pps: f64,
+    /// Packets sent per second
+    pub tx_pps: f64,
+}
+
+impl StatsRate {
+    fn new(
+        prev: &InterfaceStats,
+        stats: InterfaceStats,
+        elapsed: time::Duration,
+    ) -> Self {
+        // Counters restart from zero if the adapter is restarted
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let rate = |prev: u64, cur: u64| cur.saturating_sub(prev) as f64 / secs;
+
+        Self {
+            stats,
+            elapsed,
+            rx_bps: rate(prev.in_octets, stats.in_octets) * 8.0,
+            tx_bps: rate(prev.out_octets, stats.out_octets) * 8.0,
+            rx_pps: rate(prev.in_packets, stats.in_packets),
+            tx_pps: rate(prev.out_packets, stats.out_packets),
+        }
+    }
+}
+
+/// Periodically samples the traffic counters of an interface,
+/// created with `Device::watch_stats`.
+/// The callback runs on a dedicated thread, dropping the watcher
+/// stops it and waits for a running callback to return
+pub struct StatsWatcher {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl StatsWatcher {
+    pub(crate) fn new<F>(
+        luid: NET_LUID,
+        interval: time::Duration,
+        mut callback: F,
+    ) -> io::Result<Self>
+    where
+        F: FnMut(&StatsRate) + Send + 'static,
+    {
+        let mut prev = InterfaceStats::from_row(&ffi::get_if_entry2(&luid)?);
+        let mut prev_time = time::Instant::now();
+
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_stop = stop.clone();
+
+        let thread = thread::Builder::new()
+            .name("tap-windows-stats".to_owned())
+            .spawn(move || loop {
+                if wait_stop(&thread_stop, interval) {
+                    break;
+                }
+
+                let row = match ffi::get_if_entry2(&luid) {
+                    Ok(row) => row,
+                    Err(_err) => {
+                        // The interface may come back, keep polling
+                        warn!(error = %_err, "failed to sample stats");
+                        continue;
+                    }
+                };
+
+                let now = time::Instant::now();
+                let stats = InterfaceStats::from_row(&row);
+
+                callback(&StatsRate::new(&prev, stats, now - prev_time));
+
+                prev = stats;
+                prev_time = now;
+            })?;
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// Sleep for `interval`, returns true if the watcher was dropped
+/// in the meantime
+fn wait_stop(stop: &(Mutex<bool>, Condvar), interval: time::Duration) -> bool {
+    let (lock, cvar) = stop;
+    let stopped = lock.lock().unwrap_or_else(|err| err.into_inner());
+
+    let (stopped, _) = cvar
+        .wait_timeout_while(stopped, interval, |stopped| !*stopped)
+        .unwrap_or_else(|err| err.into_inner());
+
+    *stopped
+}
+
+impl Drop for StatsWatcher {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.stop;
+        *lock.lock().unwrap_or_else(|err| err.into_inner()) = true;
+        cvar.notify_one();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use winapi::shared::ifdef::{
+    IfOperStatusUp, MediaConnectStateConnected, NET_IF_ADMIN_STATUS_UP,
+};
+use winapi::shared::ws2def::AF_UNSPEC;
+
+use std::io;
+
+use crate::{ffi, Device};
+
+/// State of an interface at one point in time, returned by
+/// `Device::status`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeviceStatus {
+    /// True if the adapter is enabled
+    pub admin_up: bool,
+    /// True if the interface is operational and can pass traffic
+    pub oper_up: bool,
+    /// True if the media status of the adapter is connected, see
+    /// `Device::set_status`
+    pub media_connected: bool,
+    /// Mtu of the interface
+    pub mtu: u32,
+    /// Number of unicast addresses on the interface, ipv4 and ipv6,
+    /// including link-local ones
+    pub address_count: usize,
+}
+
+impl Device {
+    /// Retrieve the admin, operational and media state of the
+    /// interface along with its mtu and address count, all read
+    /// together
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let status = dev.status().expect("Failed to get device status");
+    /// if !status.oper_up || status.address_count == 0 {
+    ///     println!("Unhealthy: {:?}", status);
+    /// }
+    /// ```
+    pub fn status(&self) -> io::Result<DeviceStatus> {
+        let row = ffi::get_if_entry2(&self.luid)?;
+
+        let address_count = ffi::get_unicast_ip_address_table(AF_UNSPEC as _)?
+            .iter()
+            .filter(|row| row.InterfaceLuid.Value == self.luid.Value)
+            .count();
+
+        Ok(DeviceStatus {
+            admin_up: row.AdminStatus == NET_IF_ADMIN_STATUS_UP,
+            oper_up: row.OperStatus == IfOperStatusUp,
+            media_connected: row.MediaConnectState
+                == MediaConnectStateConnected,
+            mtu: row.Mtu,
+            address_count,
+        })
+    }
+}
@@ -0,0 +1,30 @@
+//! Wrappers letting `Device` and the watchers be `Send` and `Sync`
+//! without opting whole types out of the compiler checks
+
+use winapi::um::winnt::HANDLE;
+
+use std::sync::Mutex;
+
+/// Raw handle that can be used from any thread, kernel handles
+/// aren't tied to the thread that opened them
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SendHandle(pub HANDLE);
+
+unsafe impl Send for SendHandle {}
+unsafe impl Sync for SendHandle {}
+
+/// Value only reachable through `&mut self`, so it can be shared
+/// between threads even if it is only `Send`, like the closures
+/// installed on a device. The mutex is never locked, `get_mut`
+/// borrows through it
+pub(crate) struct Exclusive<T>(Mutex<T>);
+
+impl<T> Exclusive<T> {
+    pub fn new(value: T) -> Self {
+        Self(Mutex::new(value))
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -0,0 +1,77 @@
+//! Module exporting device metrics through the `metrics` facade
+
+use winapi::shared::ifdef::{IfOperStatusUp, NET_LUID};
+
+use std::{io, time};
+
+use crate::{ffi, Device, InterfaceStats, StatsWatcher};
+
+/// Record the counters and link state of an interface, labelled
+/// with the device name
+fn record(device: &str, luid: &NET_LUID) -> io::Result<()> {
+    let row = ffi::get_if_entry2(luid)?;
+    let stats = InterfaceStats::from_row(&row);
+
+    let counters = [
+        ("tap_windows_rx_bytes_total", stats.in_octets),
+        ("tap_windows_tx_bytes_total", stats.out_octets),
+        ("tap_windows_rx_packets_total", stats.in_packets),
+        ("tap_windows_tx_packets_total", stats.out_packets),
+        ("tap_windows_rx_errors_total", stats.in_errors),
+        ("tap_windows_tx_errors_total", stats.out_errors),
+        ("tap_windows_rx_discards_total", stats.in_discards),
+        ("tap_windows_tx_discards_total", stats.out_discards),
+    ];
+
+    for &(name, value) in &counters {
+        metrics::counter!(name, "device" => device.to_owned()).absolute(value);
+    }
+
+    let up = row.OperStatus == IfOperStatusUp;
+    metrics::gauge!("tap_windows_link_up", "device" => device.to_owned())
+        .set(if up { 1.0 } else { 0.0 });
+
+    Ok(())
+}
+
+impl Device {
+    /// Record the traffic counters and link state of the interface
+    /// through the `metrics` facade, for whatever recorder the
+    /// application installed (a prometheus exporter for example).
+    /// Every metric is named `tap_windows_*` and labelled with the
+    /// name of the device
+    pub fn record_metrics(&self) -> io::Result<()> {
+        record(&self.get_name()?, &self.luid)
+    }
+
+    /// Call `record_metrics` every `interval` from a background
+    /// thread, until the returned watcher is dropped. The device
+    /// label keeps the name the device had when this was called
+    /// Example
+    /// ```no_run
+    /// use tap_windows::Device;
+    /// use std::time::Duration;
+    ///
+    /// let dev = Device::open("My Own Device")
+    ///     .expect("Failed to open device");
+    ///
+    /// let _exporter = dev
+    ///     .export_metrics(Duration::from_secs(15))
+    ///     .expect("Failed to export metrics");
+    /// ```
+    pub fn export_metrics(
+        &self,
+        interval: time::Duration,
+    ) -> io::Result<StatsWatcher> {
+        let device = self.get_name()?;
+        let luid = self.luid;
+
+        record(&device, &luid)?;
+
+        self.watch_stats(interval, move |_| {
+            if let Err(_err) = record(&device, &luid) {
+                warn!(error = %_err, "failed to record metrics");
+            }
+        })
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::{io, ops, process};
+
+use crate::{encode_utf16, ffi, CreateOptions, Device};
+
+/// Names of the live `TempDevice`s of this process
+static NAMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Adapter with a unique name that is deleted when dropped, even
+/// while unwinding from a panic, for integration tests against the
+/// real driver. Names are unique across the threads of the process
+/// and never clash with adapters left over by previous runs.
+/// Built with the `test-util` feature, derefs to `Device`
+/// Example
+/// ```no_run
+/// use tap_windows::TempDevice;
+///
+/// let dev = TempDevice::new().expect("Failed to create device");
+/// dev.set_ip([10, 99, 0, 1], [255, 255, 255, 0])
+///     .expect("Failed to set device ip");
+///
+/// // The adapter is gone after this
+/// drop(dev);
+/// ```
+pub struct TempDevice {
+    dev: Device,
+    name: String,
+}
+
+impl TempDevice {
+    /// Create an adapter named `tap-test-<pid>-<n>`
+    pub fn new() -> io::Result<Self> {
+        Self::with_prefix("tap-test")
+    }
+
+    /// Create an adapter named `<prefix>-<pid>-<n>`
+    pub fn with_prefix(prefix: &str) -> io::Result<Self> {
+        let name = reserve_name(prefix);
+
+        let dev = Device::create_with(CreateOptions {
+            name: Some(&name),
+            ..Default::default()
+        });
+
+        match dev {
+            Ok(mut dev) => {
+                dev.set_delete_on_drop(true);
+                Ok(Self { dev, name })
+            }
+            Err(err) => {
+                release_name(&name);
+                Err(err)
+            }
+        }
+    }
+
+    /// Name given to the adapter
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+fn lock_names() -> MutexGuard<'static, Vec<String>> {
+    // A test panicking while holding the lock must not break the others
+    NAMES.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Pick a name no live `TempDevice` nor existing adapter uses
+fn reserve_name(prefix: &str) -> String {
+    let mut names = lock_names();
+
+    loop {
+        let name = format!(
+            "{}-{}-{}",
+            prefix,
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let in_use = names.contains(&name)
+            || ffi::alias_to_luid(&encode_utf16(&name)).is_ok();
+
+        if !in_use {
+            names.push(name.clone());
+            return name;
+        }
+    }
+}
+
+fn release_name(name: &str) {
+    lock_names().retain(|other| other != name);
+}
+
+impl Drop for TempDevice {
+    fn drop(&mut self) {
+        // The adapter itself is deleted when `dev` is dropped
+        release_name(&self.name);
+    }
+}
+
+impl ops::Deref for TempDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Device {
+        &self.dev
+    }
+}
+
+impl ops::DerefMut for TempDevice {
+    fn deref_mut(&mut self) -> &mut Device {
+        &mut self.dev
+    }
+}
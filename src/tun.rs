@@ -0,0 +1,175 @@
+use winapi::um::winioctl::*;
+
+use std::{io, net};
+
+use crate::{Device, DeviceConfig};
+
+/// Tun mode of a `Device`, reads and writes carry ipv4 packets
+/// instead of ethernet frames, the driver answers arp on behalf
+/// of the interface. Implements the `tun2` crate's
+/// `AbstractDevice`, so it can be used as the Windows backend of
+/// code written against `tun2`
+/// Example
+/// ```no_run
+/// use tap_windows::Device;
+/// use tun2::AbstractDevice;
+///
+/// let dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// let mut tun = dev.into_tun([10, 8, 0, 2], [255, 255, 255, 0])
+///     .expect("Failed to switch to tun mode");
+///
+/// tun.enabled(true).expect("Failed to set device up");
+/// ```
+pub struct TunDevice {
+    dev: Device,
+    address: net::Ipv4Addr,
+    netmask: net::Ipv4Addr,
+}
+
+impl Device {
+    /// Switch the device to tun mode and give it an ipv4 address
+    pub fn into_tun<A, B>(self, address: A, netmask: B) -> io::Result<TunDevice>
+    where
+        A: Into<net::Ipv4Addr>,
+        B: Into<net::Ipv4Addr>,
+    {
+        let mut tun = TunDevice {
+            dev: self,
+            address: address.into(),
+            netmask: netmask.into(),
+        };
+        tun.configure(tun.address, tun.netmask)?;
+
+        Ok(tun)
+    }
+}
+
+impl TunDevice {
+    /// Give back the underlying device, still in tun mode
+    pub fn into_inner(self) -> Device {
+        self.dev
+    }
+
+    /// Borrow the underlying device
+    pub fn get_ref(&self) -> &Device {
+        &self.dev
+    }
+
+    /// Send the address and network to the driver with
+    /// TAP_WIN_IOCTL_CONFIG_TUN and set the interface address
+    fn configure(
+        &mut self,
+        address: net::Ipv4Addr,
+        netmask: net::Ipv4Addr,
+    ) -> io::Result<()> {
+        let network = u32::from(address) & u32::from(netmask);
+
+        let config: [[u8; 4]; 3] =
+            [address.octets(), network.to_be_bytes(), netmask.octets()];
+
+        self.dev.device_io_control(
+            CTL_CODE(FILE_DEVICE_UNKNOWN, 10, METHOD_BUFFERED, FILE_ANY_ACCESS),
+            &config,
+            &mut (),
+        )?;
+
+        self.dev.set_ip(address, netmask)
+    }
+}
+
+impl io::Read for TunDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.dev.read(buf)
+    }
+}
+
+impl io::Write for TunDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.dev.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.dev.flush()
+    }
+}
+
+fn ipv4(value: net::IpAddr) -> tun2::Result<net::Ipv4Addr> {
+    match value {
+        net::IpAddr::V4(value) => Ok(value),
+        net::IpAddr::V6(_) => Err(tun2::Error::InvalidAddress),
+    }
+}
+
+impl tun2::AbstractDevice for TunDevice {
+    fn tun_name(&self) -> tun2::Result<String> {
+        Ok(self.dev.get_name()?)
+    }
+
+    fn set_tun_name(&mut self, tun_name: &str) -> tun2::Result<()> {
+        Ok(self.dev.set_name(tun_name)?)
+    }
+
+    fn enabled(&mut self, value: bool) -> tun2::Result<()> {
+        Ok(self.dev.set_status(value)?)
+    }
+
+    fn address(&self) -> tun2::Result<net::IpAddr> {
+        Ok(self.address.into())
+    }
+
+    fn set_address(&mut self, value: net::IpAddr) -> tun2::Result<()> {
+        let address = ipv4(value)?;
+        self.configure(address, self.netmask)?;
+
+        // Only kept once the driver took it
+        self.address = address;
+        Ok(())
+    }
+
+    /// There is no peer, the driver emulates the whole network
+    fn destination(&self) -> tun2::Result<net::IpAddr> {
+        Err(tun2::Error::NotImplemented)
+    }
+
+    fn set_destination(&mut self, _value: net::IpAddr) -> tun2::Result<()> {
+        Err(tun2::Error::NotImplemented)
+    }
+
+    fn broadcast(&self) -> tun2::Result<net::IpAddr> {
+        let broadcast = u32::from(self.address) | !u32::from(self.netmask);
+        Ok(net::Ipv4Addr::from(broadcast).into())
+    }
+
+    fn set_broadcast(&mut self, _value: net::IpAddr) -> tun2::Result<()> {
+        Err(tun2::Error::NotImplemented)
+    }
+
+    fn netmask(&self) -> tun2::Result<net::IpAddr> {
+        Ok(self.netmask.into())
+    }
+
+    fn set_netmask(&mut self, value: net::IpAddr) -> tun2::Result<()> {
+        let netmask = ipv4(value)?;
+        self.configure(self.address, netmask)?;
+
+        self.netmask = netmask;
+        Ok(())
+    }
+
+    fn mtu(&self) -> tun2::Result<u16> {
+        Ok(self.dev.get_mtu()? as u16)
+    }
+
+    fn set_mtu(&mut self, value: u16) -> tun2::Result<()> {
+        Ok(self.dev.apply(&DeviceConfig {
+            mtu: Some(value as u32),
+            ..Default::default()
+        })?)
+    }
+
+    fn packet_information(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,68 @@
+use std::{io, ops};
+
+use crate::{CreateOptions, Device};
+
+/// A device that was not brought up yet, it offers every
+/// configuration method of `Device` but no way to read or write
+/// packets, so reading before setting the media status is caught
+/// at compile time. Call `up` once configured to get the `Device`
+/// Example
+/// ```no_run
+/// use tap_windows::{CreateOptions, UnconfiguredDevice};
+/// use std::io::Read;
+///
+/// let dev = UnconfiguredDevice::create_with(CreateOptions::default())
+///     .expect("Failed to create device");
+///
+/// dev.add_address([10, 8, 0, 2], 24)
+///     .expect("Failed to add address");
+///
+/// // dev.read(...) does not compile here
+///
+/// let mut dev = dev.up().expect("Failed to set device up");
+///
+/// let mut buf = [0; 1600];
+/// let amt = dev.read(&mut buf).expect("Failed to read packet");
+/// ```
+pub struct UnconfiguredDevice(Device);
+
+impl UnconfiguredDevice {
+    /// Creates a new device, see `Device::create_with`
+    pub fn create_with(options: CreateOptions) -> io::Result<Self> {
+        Device::create_with(options).map(Self)
+    }
+
+    /// Opens an existing device by name, see `Device::open`
+    pub fn open(name: &str) -> io::Result<Self> {
+        Device::open(name).map(Self)
+    }
+
+    /// Set the media status to connected and return the device,
+    /// ready to read and write packets
+    pub fn up(self) -> io::Result<Device> {
+        self.0.up()?;
+
+        Ok(self.0)
+    }
+
+    /// Give back the device without changing its status
+    pub fn into_inner(self) -> Device {
+        self.0
+    }
+}
+
+impl From<Device> for UnconfiguredDevice {
+    fn from(dev: Device) -> Self {
+        Self(dev)
+    }
+}
+
+/// Only shared access is given, which is enough for configuration
+/// but not for `Read` and `Write`, they need `&mut Device`
+impl ops::Deref for UnconfiguredDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Device {
+        &self.0
+    }
+}
@@ -0,0 +1,258 @@
+use std::io;
+
+use crate::packet::*;
+use crate::Device;
+
+/// Length of an 802.1Q tag
+const TAG_LEN: usize = 4;
+
+/// 802.1Q tag carried by an ethernet frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VlanTag {
+    /// Priority code point, from 0 to 7
+    pub priority: u8,
+    /// Drop eligible indicator
+    pub drop_eligible: bool,
+    /// Vlan identifier, from 0 to 4095
+    pub id: u16,
+}
+
+impl VlanTag {
+    /// Tag with the given vlan id, priority 0
+    pub fn new(id: u16) -> Self {
+        Self {
+            id,
+            ..Default::default()
+        }
+    }
+
+    /// Parse a tag control information field
+    pub fn from_tci(tci: u16) -> Self {
+        Self {
+            priority: (tci >> 13) as u8,
+            drop_eligible: tci & 0x1000 != 0,
+            id: tci & 0x0fff,
+        }
+    }
+
+    /// Encode the tag control information field, out of range
+    /// values are truncated
+    pub fn tci(&self) -> u16 {
+        ((self.priority as u16 & 0x7) << 13)
+            | ((self.drop_eligible as u16) << 12)
+            | (self.id & 0x0fff)
+    }
+}
+
+/// Insert an 802.1Q tag after the mac addresses of `frame`,
+/// `None` if the frame is shorter than an ethernet header
+pub fn push_vlan_tag(frame: &[u8], tag: VlanTag) -> Option<Vec<u8>> {
+    if frame.len() < ETHER_HEADER_LEN {
+        return None;
+    }
+
+    let mut tagged = Vec::with_capacity(frame.len() + TAG_LEN);
+    tagged.extend_from_slice(&frame[..12]);
+    tagged.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+    tagged.extend_from_slice(&tag.tci().to_be_bytes());
+    tagged.extend_from_slice(&frame[12..]);
+
+    Some(tagged)
+}
+
+/// Remove the outer 802.1Q tag of `frame`, `None` if it is untagged
+pub fn pop_vlan_tag(frame: &[u8]) -> Option<(VlanTag, Vec<u8>)> {
+    let tag = read_tag(frame)?;
+
+    let mut untagged = Vec::with_capacity(frame.len() - TAG_LEN);
+    untagged.extend_from_slice(&frame[..12]);
+    untagged.extend_from_slice(&frame[12 + TAG_LEN..]);
+
+    Some((tag, untagged))
+}
+
+fn read_tag(frame: &[u8]) -> Option<VlanTag> {
+    if ether_type(frame)? != ETHERTYPE_VLAN
+        || frame.len() < ETHER_HEADER_LEN + TAG_LEN
+    {
+        return None;
+    }
+
+    Some(VlanTag::from_tci(read_u16(frame, 14)))
+}
+
+/// A `Device` on a single vlan, frames written are tagged and
+/// only frames read with the same vlan id are let through, with
+/// their tag stripped. Windows only exchanges tagged frames once
+/// the adapter's vlan id is set, from its advanced properties
+/// Example
+/// ```no_run
+/// use tap_windows::{Device, VlanTag};
+/// use std::io::{Read, Write};
+///
+/// let dev = Device::open("My Own Device")
+///     .expect("Failed to open device");
+///
+/// let mut dev = dev.into_vlan(VlanTag::new(42));
+///
+/// // Read an untagged frame from vlan 42
+/// let mut buf = [0; 1600];
+/// let amt = dev.read(&mut buf).expect("Failed to read packet");
+///
+/// // And send it back tagged
+/// dev.write(&buf[..amt]).expect("Failed to write packet");
+/// ```
+pub struct VlanDevice {
+    dev: Device,
+    tag: VlanTag,
+    /// Reused to build tagged frames
+    buffer: Vec<u8>,
+}
+
+impl Device {
+    /// Tag and untag the frames going through the device
+    pub fn into_vlan(self, tag: VlanTag) -> VlanDevice {
+        VlanDevice {
+            dev: self,
+            tag,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl VlanDevice {
+    /// Tag added to the written frames
+    pub fn tag(&self) -> VlanTag {
+        self.tag
+    }
+
+    /// Change the tag added to the written frames, and the vlan
+    /// id accepted by reads
+    pub fn set_tag(&mut self, tag: VlanTag) {
+        self.tag = tag;
+    }
+
+    /// Give back the underlying device
+    pub fn into_inner(self) -> Device {
+        self.dev
+    }
+
+    /// Borrow the underlying device
+    pub fn get_ref(&self) -> &Device {
+        &self.dev
+    }
+}
+
+/// Untagged frames and frames of other vlans are skipped,
+/// the read waits for the next one
+impl io::Read for VlanDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let amt = self.dev.read(buf)?;
+
+            match read_tag(&buf[..amt]) {
+                Some(tag) if tag.id == self.tag.id => {
+                    buf.copy_within(12 + TAG_LEN..amt, 12);
+                    return Ok(amt - TAG_LEN);
+                }
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl io::Write for VlanDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() < ETHER_HEADER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Frame shorter than an ethernet header",
+            ));
+        }
+
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&buf[..12]);
+        self.buffer.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+        self.buffer.extend_from_slice(&self.tag.tci().to_be_bytes());
+        self.buffer.extend_from_slice(&buf[12..]);
+
+        self.dev.write(&self.buffer)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.dev.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> Vec<u8> {
+        let mut frame = vec![0xff; 6];
+        frame.extend_from_slice(&[2, 0, 0, 0, 0, 1]);
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(&[0x45, 0, 0, 20]);
+        frame
+    }
+
+    #[test]
+    fn encodes_tci() {
+        let tag = VlanTag {
+            priority: 5,
+            drop_eligible: true,
+            id: 0x123,
+        };
+
+        assert_eq!(tag.tci(), 0xb123);
+        assert_eq!(VlanTag::from_tci(0xb123), tag);
+
+        // Out of range values are truncated
+        let tag = VlanTag {
+            priority: 9,
+            drop_eligible: false,
+            id: 0x1fff,
+        };
+        assert_eq!(tag.tci(), 0x2fff);
+    }
+
+    #[test]
+    fn pushes_and_pops_tags() {
+        let tag = VlanTag {
+            priority: 3,
+            drop_eligible: false,
+            id: 42,
+        };
+        let tagged = push_vlan_tag(&frame(), tag).unwrap();
+
+        assert_eq!(tagged.len(), frame().len() + TAG_LEN);
+        assert_eq!(tagged[..12], frame()[..12]);
+        assert_eq!(tagged[12..16], [0x81, 0x00, 0x60, 42]);
+        assert_eq!(tagged[16..], frame()[12..]);
+
+        assert_eq!(pop_vlan_tag(&tagged), Some((tag, frame())));
+    }
+
+    #[test]
+    fn pops_only_the_outer_tag() {
+        let inner = push_vlan_tag(&frame(), VlanTag::new(10)).unwrap();
+        let outer = push_vlan_tag(&inner, VlanTag::new(20)).unwrap();
+
+        assert_eq!(pop_vlan_tag(&outer), Some((VlanTag::new(20), inner)));
+    }
+
+    #[test]
+    fn rejects_untagged_and_short_frames() {
+        assert_eq!(pop_vlan_tag(&frame()), None);
+        assert_eq!(pop_vlan_tag(&[0x81, 0x00]), None);
+        assert_eq!(
+            pop_vlan_tag(
+                &push_vlan_tag(&frame(), VlanTag::new(1)).unwrap()[..16]
+            ),
+            None
+        );
+        assert_eq!(push_vlan_tag(&[0; 13], VlanTag::new(1)), None);
+    }
+}
@@ -0,0 +1,143 @@
+//! Module holding wmi operations, executed through powershell
+
+use std::os::windows::process::CommandExt;
+use std::{io, net, process};
+
+use winapi::um::winbase::CREATE_NO_WINDOW;
+
+use crate::hook;
+
+fn exec_powershell(script: &str) -> io::Result<()> {
+    let args = ["-NoProfile", "-NonInteractive", "-Command", script];
+
+    hook::run("powershell", &args, || {
+        let status = process::Command::new("powershell")
+            .args(args)
+            .stdin(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .stdout(process::Stdio::null())
+            .creation_flags(CREATE_NO_WINDOW)
+            .status()?;
+
+        debug!(script, code = ?status.code(), "executed powershell");
+
+        // Powershell also exits with 1 when the script throws, so
+        // scripts must report success with 0 only
+        match status.code() {
+            Some(0) => Ok(()),
+            Some(code) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Failed to execute wmi method (return value {})", code),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to execute powershell",
+            )),
+        }
+    })
+}
+
+/// Run a script and return what it printed, failing if it exits
+/// with a non zero code
+fn query_powershell(script: &str) -> io::Result<String> {
+    // Otherwise names are printed in the OEM code page
+    let script = format!(
+        "[Console]::OutputEncoding = [Text.Encoding]::UTF8; {}",
+        script
+    );
+    let args = ["-NoProfile", "-NonInteractive", "-Command", script.as_str()];
+
+    hook::run("powershell", &args, || {
+        let output = process::Command::new("powershell")
+            .args(args)
+            .stdin(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()?;
+
+        debug!(script, code = ?output.status.code(), "queried powershell");
+
+        match output.status.code() {
+            Some(0) => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+            Some(code) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Failed to query wmi (return value {})", code),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Failed to execute powershell",
+            )),
+        }
+    })
+}
+
+/// Set the ipv4 dns servers of an interface through
+/// Win32_NetworkAdapterConfiguration, available since Windows 7
+pub fn set_interface_dns(
+    index: u32,
+    servers: &[net::Ipv4Addr],
+) -> io::Result<()> {
+    let servers = servers
+        .iter()
+        .map(|server| format!("'{}'", server))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    // Get-WmiObject is used as it is the only cmdlet
+    // available on the powershell 2.0 shipped with Windows 7.
+    // A return value of 1 means the change needs a reboot
+    exec_powershell(&format!(
+        "$c = Get-WmiObject Win32_NetworkAdapterConfiguration \
+         -Filter 'InterfaceIndex={}'; \
+         if (!$c) {{ exit 2 }}; \
+         $r = $c.SetDNSServerSearchOrder([string[]]@({})).ReturnValue; \
+         if ($r -eq 1) {{ exit 0 }}; \
+         exit $r",
+        index, servers
+    ))
+}
+
+/// Enable or disable a binding of an interface, the component id
+/// must have been validated as it is pasted in the script.
+/// Uses the NetAdapter module, available since Windows 8
+pub fn set_interface_binding(
+    index: u32,
+    component_id: &str,
+    enabled: bool,
+) -> io::Result<()> {
+    exec_powershell(&format!(
+        "try {{ \
+         $a = Get-NetAdapter -InterfaceIndex {} -ErrorAction Stop; \
+         Set-NetAdapterBinding -Name $a.Name -ComponentID '{}' \
+         -Enabled ${} -ErrorAction Stop \
+         }} catch {{ exit 2 }}",
+        index, component_id, enabled
+    ))
+}
+
+/// Get the name and category of the network connection profile of
+/// an interface, as `name|category`, or an empty string if it has
+/// none. Uses the NetConnection module, available since Windows 8
+pub fn get_interface_profile(index: u32) -> io::Result<String> {
+    query_powershell(&format!(
+        "if (!(Get-Command Get-NetConnectionProfile \
+         -ErrorAction SilentlyContinue)) {{ exit 2 }}; \
+         $p = Get-NetConnectionProfile -InterfaceIndex {} \
+         -ErrorAction SilentlyContinue; \
+         if ($p) {{ '{{0}}|{{1}}' -f $p.Name, [int]$p.NetworkCategory }}",
+        index
+    ))
+}
+
+/// Set the category of the network connection profile of an
+/// interface. Uses the NetConnection module, available since
+/// Windows 8
+pub fn set_interface_category(index: u32, category: &str) -> io::Result<()> {
+    exec_powershell(&format!(
+        "try {{ \
+         Set-NetConnectionProfile -InterfaceIndex {} \
+         -NetworkCategory {} -ErrorAction Stop \
+         }} catch {{ exit 2 }}",
+        index, category
+    ))
+}